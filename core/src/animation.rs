@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+
+// Sprite animation - named clips of sprite bank patterns, shipped by the
+// cartridge as .zsa data and uploaded into the sprite bank. Entities hold an
+// AnimationPlayer, play clips by name and tick it once per frame; sprites
+// carry the clip and frame they're on, and the renderer resolves that to the
+// frame's pattern from the bank. Frames can carry an event the game reacts to
+// when the frame starts.
+//
+// .zsa layout (all values are bytes, pattern IDs little-endian):
+//
+//   Header, 5 bytes
+//     0-3   "ZSA" and format version 1
+//     4     clip count
+//   Clips, one after another
+//     0     name length N
+//     1..N  name (UTF-8)
+//     N+1   playback mode: 0 loop, 1 ping-pong, 2 once
+//     N+2   frame count, at least 1
+//     then 4 bytes per frame:
+//       0-1 sprite bank pattern ID
+//       2   duration in frames, at least 1
+//       3   event ID, or $FF for none
+
+const HEADER_SIZE: usize = 5;
+const FRAME_SIZE: usize = 4;
+pub const NO_EVENT: u8 = 0xFF;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlaybackMode {
+    Loop,     // 0, 1, 2, 0, 1, 2, ...
+    PingPong, // 0, 1, 2, 1, 0, 1, ...
+    Once,     // 0, 1, 2 then hold the last frame and report finished
+}
+
+impl PlaybackMode {
+    pub fn from_u8(mode: u8) -> Option<PlaybackMode> {
+        match mode {
+            0 => Some(PlaybackMode::Loop),
+            1 => Some(PlaybackMode::PingPong),
+            2 => Some(PlaybackMode::Once),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct AnimationFrame {
+    pub pattern_id: u32,    // Sprite bank pattern shown for this frame
+    pub duration: u32,      // Frames (ticks) the frame is held, at least 1
+    pub event: Option<u32>, // Reported when the frame starts (footstep, hop, hit...)
+}
+
+#[derive(Clone)]
+pub struct AnimationClip {
+    pub frames: Vec<AnimationFrame>,
+    pub mode: PlaybackMode,
+}
+
+#[derive(Clone, Default)]
+pub struct AnimationLibrary {
+    clips: HashMap<String, AnimationClip>,
+}
+
+impl AnimationLibrary {
+    pub fn new() -> AnimationLibrary {
+        AnimationLibrary {
+            clips: HashMap::new(),
+        }
+    }
+
+    // Clips without frames are ignored
+    pub fn insert(&mut self, name: &str, clip: AnimationClip) {
+        if !clip.frames.is_empty() {
+            self.clips.insert(name.to_string(), clip);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AnimationClip> {
+        self.clips.get(name)
+    }
+
+    // Parse .zsa data
+    pub fn from_bytes(data: &[u8]) -> Result<AnimationLibrary, String> {
+        if data.len() < HEADER_SIZE || &data[0..3] != b"ZSA" {
+            return Err(String::from("Not a .zsa file"));
+        }
+        if data[3] != 1 {
+            return Err(format!("Unsupported .zsa version {}", data[3]));
+        }
+
+        let mut library = AnimationLibrary::new();
+        let mut position = HEADER_SIZE;
+        let truncated = || String::from(".zsa data ends inside a clip");
+        for _ in 0..data[4] {
+            let name_length = *data.get(position).ok_or_else(truncated)? as usize;
+            let name = data.get(position + 1..position + 1 + name_length).ok_or_else(truncated)?;
+            let name = std::str::from_utf8(name).map_err(|_| String::from("Clip name is not UTF-8"))?;
+            position += 1 + name_length;
+
+            let header = data.get(position..position + 2).ok_or_else(truncated)?;
+            let mode = PlaybackMode::from_u8(header[0]).ok_or_else(|| format!("Clip {} has unknown playback mode {}", name, header[0]))?;
+            let frame_count = header[1] as usize;
+            if frame_count == 0 {
+                return Err(format!("Clip {} has no frames", name));
+            }
+            position += 2;
+
+            let frames = data.get(position..position + frame_count * FRAME_SIZE).ok_or_else(truncated)?;
+            let frames = frames
+                .chunks_exact(FRAME_SIZE)
+                .map(|frame| AnimationFrame {
+                    pattern_id: u16::from_le_bytes([frame[0], frame[1]]) as u32,
+                    duration: frame[2].max(1) as u32,
+                    event: if frame[3] == NO_EVENT { None } else { Some(frame[3] as u32) },
+                })
+                .collect();
+            position += frame_count * FRAME_SIZE;
+
+            library.insert(name, AnimationClip { frames, mode });
+        }
+        Ok(library)
+    }
+
+    // Pattern shown on a clip's frame
+    pub fn pattern(&self, position: &ClipFrame) -> Option<u32> {
+        self.get(&position.clip)?.frames.get(position.frame).map(|frame| frame.pattern_id)
+    }
+}
+
+// Where a player is in its clip - what a sprite hands the renderer
+#[derive(Clone, PartialEq, Debug)]
+pub struct ClipFrame {
+    pub clip: String,
+    pub frame: usize,
+}
+
+#[derive(Clone, Default)]
+pub struct AnimationPlayer {
+    clip: Option<String>,
+    frame: usize,
+    timer: u32,
+    reverse: bool,   // Ping-pong direction
+    started: bool,   // First frame's event not reported yet
+    finished: bool,
+}
+
+impl AnimationPlayer {
+    pub fn new() -> AnimationPlayer {
+        AnimationPlayer::default()
+    }
+
+    // Start a clip from its first frame. Playing the clip that's already
+    // running leaves it alone, so this can be called every frame.
+    pub fn play(&mut self, name: &str) {
+        if self.clip.as_deref() == Some(name) {
+            return;
+        }
+        self.clip = Some(name.to_string());
+        self.frame = 0;
+        self.timer = 0;
+        self.reverse = false;
+        self.started = false;
+        self.finished = false;
+    }
+
+    // A Once clip whose last frame has been shown for its full duration
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // Advance one frame. Returns the event of the frame that started, if any.
+    pub fn tick(&mut self, library: &AnimationLibrary) -> Option<u32> {
+        let clip = library.get(self.clip.as_deref()?)?;
+        if !self.started {
+            self.started = true;
+            return clip.frames[0].event;
+        }
+        if self.finished {
+            return None;
+        }
+
+        self.timer += 1;
+        if self.timer < clip.frames[self.frame].duration.max(1) {
+            return None;
+        }
+        self.timer = 0;
+
+        let last = clip.frames.len() - 1;
+        match clip.mode {
+            PlaybackMode::Loop => {
+                self.frame = if self.frame >= last { 0 } else { self.frame + 1 };
+            }
+            PlaybackMode::PingPong => {
+                if last == 0 {
+                    return None;
+                }
+                if self.frame >= last {
+                    self.reverse = true;
+                } else if self.frame == 0 {
+                    self.reverse = false;
+                }
+                self.frame = if self.reverse { self.frame - 1 } else { self.frame + 1 };
+            }
+            PlaybackMode::Once => {
+                if self.frame >= last {
+                    self.finished = true;
+                    return None;
+                }
+                self.frame += 1;
+            }
+        }
+        clip.frames[self.frame].event
+    }
+
+    // Current clip and frame, None when no clip is playing
+    pub fn position(&self) -> Option<ClipFrame> {
+        self.clip.as_ref().map(|clip| ClipFrame { clip: clip.clone(), frame: self.frame })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(pattern_id: u32, event: Option<u32>) -> AnimationFrame {
+        AnimationFrame { pattern_id, duration: 2, event }
+    }
+
+    #[test]
+    fn test_ping_pong_with_events() {
+        let mut library = AnimationLibrary::new();
+        library.insert("walk", AnimationClip {
+            frames: vec![frame(10, Some(1)), frame(11, None), frame(12, Some(2))],
+            mode: PlaybackMode::PingPong,
+        });
+
+        let mut player = AnimationPlayer::new();
+        player.play("walk");
+        let mut patterns = Vec::new();
+        let mut events = Vec::new();
+        for _ in 0..10 {
+            if let Some(event) = player.tick(&library) {
+                events.push(event);
+            }
+            patterns.push(library.pattern(&player.position().unwrap()).unwrap());
+        }
+
+        assert_eq!(patterns, [10, 10, 11, 11, 12, 12, 11, 11, 10, 10]);
+        assert_eq!(events, [1, 2, 1]);
+    }
+
+    #[test]
+    fn test_clips_from_zsa() {
+        let mut data = b"ZSA\x01\x01\x03hop\x00\x02".to_vec();
+        data.extend_from_slice(&[7, 1, 112, 0, 9, 0, 8, NO_EVENT]);
+        let library = AnimationLibrary::from_bytes(&data).unwrap();
+        let clip = library.get("hop").unwrap();
+        assert_eq!(clip.mode, PlaybackMode::Loop);
+        assert_eq!(clip.frames.iter().map(|frame| (frame.pattern_id, frame.duration, frame.event)).collect::<Vec<_>>(), [(263, 112, Some(0)), (9, 8, None)]);
+        assert_eq!(library.pattern(&ClipFrame { clip: String::from("hop"), frame: 1 }), Some(9));
+
+        assert!(AnimationLibrary::from_bytes(&data[..data.len() - 1]).is_err());
+        data[9] = 3; // Unknown playback mode
+        assert!(AnimationLibrary::from_bytes(&data).is_err());
+    }
+}
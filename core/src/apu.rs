@@ -1,15 +1,39 @@
 use wasm_bindgen::prelude::*;
 use std::f32::consts::PI;
 use std::collections::HashMap;
-use crate::laugh_sample::{LAUGH_SAMPLE_RETRO_SAMPLE_DATA, LAUGH_SAMPLE_RETRO_SAMPLE_RATE};
+use crate::apu_channels::{FrameSequencer, NoiseChannel, PulseChannel, SequencerClocks, TriangleChannel};
+use crate::wavetable::WavetableBank;
+use crate::modulation::{Adsr, Lfo, LfoTarget, Modulation, LFOS_PER_VOICE};
+use crate::music::{MusicDriver, Song, MAX_MUSIC_CHANNELS};
+use crate::sample_bank::SamplePlayer;
+use crate::sfx::{self, SfxManager, SoundEffect, SFX_VOICES};
+use crate::mixer;
+use crate::sid::{self, SidControl, SID_FILTER_6581, SID_GATE, SID_NOISE, SID_RING_MOD, SID_SYNC, SID_TEST, SID_WAVEFORMS};
+
+// The frame sequencer ticks at 240 Hz: a quarter of the 89,342 APU steps the
+// console runs per 60 Hz frame
+const STEPS_PER_SEQUENCER_TICK: u32 = 22_335;
+
+// Sound effect voices at full effect volume
+const SFX_LEVEL: f32 = 0.7;
+
+// How much further back the right side of a full-width delay reads
+const DELAY_SPREAD_SECONDS: f32 = 0.02;
+
+// Noise generator steps per oscillator cycle (the SID's shift register is
+// clocked by bit 19 of its 24-bit accumulator), so noise pitch follows frequency
+const NOISE_CLOCKS_PER_CYCLE: f32 = 16.0;
 
 #[wasm_bindgen]
 pub struct Apu {
-    // Audio channels
+    // Register-driven channels and the frame sequencer that clocks them
     pulse1: PulseChannel,
     pulse2: PulseChannel,
     triangle: TriangleChannel,
     noise: NoiseChannel,
+    frame_sequencer: FrameSequencer,
+    sequencer_divider: u32,
+    channel_pans: [f32; 4], // Pulse 1, pulse 2, triangle, noise
 
     // New digital oscillator for sound test
     test_osc: DigitalOscillator,
@@ -33,13 +57,10 @@ pub struct Apu {
     melody_tempo: f32,  // Steps per second
     melody_notes: [u8; 16], // MIDI notes for the melody
 
-    // Sound effect system
-    sfx_active: bool,
-    sfx_timer: f32,
-    sfx_duration: f32,
-    sfx_start_note: u8,
-    sfx_end_note: u8,
-    sfx_waveform: u8,
+    // Sound effect voices and the manager that allocates them
+    sfx: SfxManager,
+    sfx_voices: Vec<DigitalOscillator>,
+    sfx_duck_volume: f32, // Music level while a ducking effect plays
 
     // Voice synthesis system for chip-tune voice effects
     voice_active: bool,
@@ -58,6 +79,11 @@ pub struct Apu {
     global_filter_type: u8,
     global_filter_cutoff: f32,
     global_filter_resonance: f32,
+
+    // Envelope and LFOs given to each new Z-Synth note
+    synth_envelope: Adsr,
+    synth_lfos: [Lfo; LFOS_PER_VOICE],
+    synth_pan: f32,
     
     // SID-style 3-voice synthesizer for games
     sid_voice1: DigitalOscillator,
@@ -65,35 +91,21 @@ pub struct Apu {
     sid_voice3: DigitalOscillator,
     sid_enabled: bool,
     sid_volume: f32,
+    sid_filter_cutoff: f32,    // Hz, as set (before the 6581 curve)
+    sid_filter_resonance: f32,
+    sid_filter_6581: bool,     // 6581 cutoff curve and softer resonance instead of 8580
     poly_volume: f32,
-    
-    // Sample playback system for short audio clips
-    sample_active: bool,
-    sample_data: Option<&'static [u8]>, // Pointer to sample data
-    sample_data_rate: u32,              // Sample rate of the data
-    sample_position: f32,               // Current playback position (fractional)
-    sample_volume: f32,                 // Volume for sample playback
-}
 
-struct PulseChannel {
-    enabled: bool,
-    frequency: f32,
-    pulse_width: u8,
-    volume: u8,
-    phase: f32,
-}
+    // 8 wavetable voices playing cartridge-written waveforms
+    wavetable: WavetableBank,
 
-struct TriangleChannel {
-    enabled: bool,
-    frequency: f32,
-    phase: f32,
-}
-
-struct NoiseChannel {
-    enabled: bool,
-    volume: u8,
-    period: u16,
-    shift_register: u16,
+    // Tracker music driver and the voices it plays on
+    music: MusicDriver,
+    music_voices: Vec<DigitalOscillator>,
+    
+    // PCM sample bank and the channels playing it
+    samples: SamplePlayer,
+    sample_volume: f32, // Volume for all sample channels
 }
 
 struct DigitalOscillator {
@@ -104,7 +116,11 @@ struct DigitalOscillator {
     pulse_width: f32,    // For pulse wave (0.0 to 1.0)
     volume: f32,
     detune: f32,         // Fine tuning offset
+    pan: f32,            // -1.0 (left) to 1.0 (right)
     lfsr: u16,          // For noise generation
+    envelope: Adsr,     // Volume envelope, a plain gate unless shaped
+    lfos: [Lfo; LFOS_PER_VOICE],
+    sid: SidControl,    // Sync, ring modulation, test bit and combined waveforms
     filter: ResonantFilter, // SID-style resonant filter
     delay: DigitalDelay,    // Digital delay effect
 }
@@ -131,6 +147,7 @@ struct DigitalDelay {
     delay_time: f32,     // 0.0 to 1.0 (maps to 0ms - 1000ms)
     feedback: f32,       // 0.0 to 0.95 (0.95+ = infinite feedback)
     mix: f32,           // 0.0 = dry only, 1.0 = wet only, 0.5 = balanced
+    width: f32,         // 0.0 = mono echoes, 1.0 = echoes spread wide
 
     // Delay buffer (circular buffer)
     buffer: Vec<f32>,
@@ -147,31 +164,13 @@ impl Apu {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Apu {
         Apu {
-            pulse1: PulseChannel {
-                enabled: false,
-                frequency: 440.0,
-                pulse_width: 0,
-                volume: 0,
-                phase: 0.0,
-            },
-            pulse2: PulseChannel {
-                enabled: false,
-                frequency: 440.0,
-                pulse_width: 0,
-                volume: 0,
-                phase: 0.0,
-            },
-            triangle: TriangleChannel {
-                enabled: false,
-                frequency: 220.0,
-                phase: 0.0,
-            },
-            noise: NoiseChannel {
-                enabled: false,
-                volume: 0,
-                period: 1,
-                shift_register: 1,
-            },
+            pulse1: PulseChannel::new(true),
+            pulse2: PulseChannel::new(false),
+            triangle: TriangleChannel::new(),
+            noise: NoiseChannel::new(),
+            frame_sequencer: FrameSequencer::default(),
+            sequencer_divider: 0,
+            channel_pans: [0.0; 4],
             test_osc: DigitalOscillator {
                 enabled: false,
                 frequency: 440.0,
@@ -180,7 +179,11 @@ impl Apu {
                 pulse_width: 0.5,
                 volume: 0.7,
                 detune: 0.0,
+                pan: 0.0,
                 lfsr: 0x7FFF,
+                envelope: Adsr::gate(),
+                lfos: [Lfo::OFF; LFOS_PER_VOICE],
+                sid: SidControl::default(),
                 filter: ResonantFilter {
                     enabled: true,
                     filter_type: 0,  // Lowpass (classic SID)
@@ -200,6 +203,7 @@ impl Apu {
                     delay_time: 0.3,        // 300ms default
                     feedback: 0.3,          // 30% feedback
                     mix: 0.25,             // 25% wet signal
+                    width: 0.0,
 
                     // Initialize delay buffer (1 second max at 44.1kHz)
                     buffer: vec![0.0; 44100],
@@ -228,12 +232,9 @@ impl Apu {
                           72, 70, 67, 65, 62, 0, 62, 0],   // C B♭ A F D rest D rest
 
             // Initialize sound effects
-            sfx_active: false,
-            sfx_timer: 0.0,
-            sfx_duration: 0.0,
-            sfx_start_note: 60,
-            sfx_end_note: 60,
-            sfx_waveform: 0,
+            sfx: SfxManager::new(),
+            sfx_voices: (0..SFX_VOICES).map(|_| Self::plain_voice()).collect(),
+            sfx_duck_volume: 0.4,
 
             // Initialize voice synthesis system
             voice_active: false,
@@ -249,7 +250,11 @@ impl Apu {
                 phase: 0.0,
                 pulse_width: 0.25, // Thin pulse for crisp voice sound
                 detune: 0.0,
+                pan: 0.0,
                 lfsr: 0x7FFF,
+                envelope: Adsr::gate(),
+                lfos: [Lfo::OFF; LFOS_PER_VOICE],
+                sid: SidControl::default(),
                 filter: ResonantFilter {
                     enabled: false,
                     filter_type: 0,
@@ -265,6 +270,7 @@ impl Apu {
                     delay_time: 0.0,
                     feedback: 0.0,
                     mix: 0.0,
+                    width: 0.0,
                     buffer: vec![0.0; 44100],
                     buffer_size: 44100,
                     write_pos: 0,
@@ -282,6 +288,9 @@ impl Apu {
             global_filter_type: 0, // Low pass
             global_filter_cutoff: 1000.0, // Hz
             global_filter_resonance: 0.5,
+            synth_envelope: Adsr::gate(),
+            synth_lfos: [Lfo::OFF; LFOS_PER_VOICE],
+            synth_pan: 0.0,
             
             // Initialize SID-style voices
             sid_voice1: DigitalOscillator {
@@ -292,7 +301,11 @@ impl Apu {
                 pulse_width: 0.5,
                 volume: 0.7,
                 detune: 0.0,
+                pan: 0.0,
                 lfsr: 0x7FFF,
+                envelope: Adsr::gate(),
+                lfos: [Lfo::OFF; LFOS_PER_VOICE],
+                sid: SidControl::default(),
                 filter: ResonantFilter {
                     enabled: false,
                     filter_type: 0,
@@ -308,6 +321,7 @@ impl Apu {
                     delay_time: 0.3,
                     feedback: 0.4,
                     mix: 0.2,
+                    width: 0.0,
                     buffer: vec![0.0; 44100],
                     buffer_size: 44100,
                     write_pos: 0,
@@ -323,7 +337,11 @@ impl Apu {
                 pulse_width: 0.5,
                 volume: 0.7,
                 detune: 0.0,
+                pan: 0.0,
                 lfsr: 0x7FFF,
+                envelope: Adsr::gate(),
+                lfos: [Lfo::OFF; LFOS_PER_VOICE],
+                sid: SidControl::default(),
                 filter: ResonantFilter {
                     enabled: false,
                     filter_type: 0,
@@ -339,6 +357,7 @@ impl Apu {
                     delay_time: 0.3,
                     feedback: 0.4,
                     mix: 0.2,
+                    width: 0.0,
                     buffer: vec![0.0; 44100],
                     buffer_size: 44100,
                     write_pos: 0,
@@ -354,7 +373,11 @@ impl Apu {
                 pulse_width: 0.5,
                 volume: 0.7,
                 detune: 0.0,
+                pan: 0.0,
                 lfsr: 0x7FFF,
+                envelope: Adsr::gate(),
+                lfos: [Lfo::OFF; LFOS_PER_VOICE],
+                sid: SidControl::default(),
                 filter: ResonantFilter {
                     enabled: false,
                     filter_type: 0,
@@ -370,6 +393,7 @@ impl Apu {
                     delay_time: 0.3,
                     feedback: 0.4,
                     mix: 0.2,
+                    width: 0.0,
                     buffer: vec![0.0; 44100],
                     buffer_size: 44100,
                     write_pos: 0,
@@ -379,13 +403,15 @@ impl Apu {
             },
             sid_enabled: false,
             sid_volume: 0.8,
+            sid_filter_cutoff: 17_640.0,
+            sid_filter_resonance: 0.2,
+            sid_filter_6581: false,
             poly_volume: 0.8,
+            wavetable: WavetableBank::new(),
+            music: MusicDriver::new(),
+            music_voices: (0..MAX_MUSIC_CHANNELS).map(|_| Self::plain_voice()).collect(),
             
-            // Sample playback initialization
-            sample_active: false,
-            sample_data: None,
-            sample_data_rate: 5512,
-            sample_position: 0.0,
+            samples: SamplePlayer::new(),
             sample_volume: 0.8,
         }
     }
@@ -419,37 +445,6 @@ impl Apu {
             }
         }
 
-        // Update sound effect if active
-        if self.sfx_active {
-            // Advance sound effect timer
-            self.sfx_timer += 1.0 / (29780.0 * 60.0); // Same rate as melody timer
-
-            // Calculate progress (0.0 to 1.0)
-            let progress = (self.sfx_timer / self.sfx_duration).min(1.0);
-
-            if progress >= 1.0 {
-                // Sound effect finished
-                self.sfx_active = false;
-                if self.sound_test_mode && !self.melody_enabled {
-                    // Return to manual control
-                    self.test_osc.frequency = Self::midi_to_frequency(self.current_note);
-                } else {
-                    // Disable oscillator if not in sound test mode
-                    self.test_osc.enabled = false;
-                }
-            } else {
-                // Interpolate between start and end note
-                let current_note_float = self.sfx_start_note as f32 +
-                    (self.sfx_end_note as f32 - self.sfx_start_note as f32) * progress;
-                let current_freq = Self::midi_to_frequency(current_note_float as u8);
-
-                // Apply to test oscillator
-                self.test_osc.frequency = current_freq;
-                self.test_osc.waveform = self.sfx_waveform;
-                self.test_osc.enabled = true;
-            }
-        }
-
         // Update voice effect if active
         if self.voice_active {
             // Advance voice timer
@@ -465,146 +460,188 @@ impl Apu {
             }
         }
 
-        // TODO: Implement proper frame sequencer timing for other channels
+        // Frame sequencer for the register-driven channels
+        self.sequencer_divider += 1;
+        if self.sequencer_divider >= STEPS_PER_SEQUENCER_TICK {
+            self.sequencer_divider = 0;
+            let clocks = self.frame_sequencer.clock();
+            self.clock_channels(clocks);
+        }
+    }
+
+    fn clock_channels(&mut self, clocks: SequencerClocks) {
+        if clocks.quarter_frame {
+            self.pulse1.clock_quarter_frame();
+            self.pulse2.clock_quarter_frame();
+            self.triangle.clock_quarter_frame();
+            self.noise.clock_quarter_frame();
+        }
+        if clocks.half_frame {
+            self.pulse1.clock_half_frame();
+            self.pulse2.clock_half_frame();
+            self.triangle.clock_half_frame();
+            self.noise.clock_half_frame();
+        }
     }
 
+    // Mono output: the stereo mix folded down
     pub fn generate_sample(&mut self) -> f32 {
-        let mut sample = 0.0;
+        let (left, right) = self.generate_stereo_sample();
+        (left + right) / 2.0
+    }
+
+    // Interleaved left/right pairs for count stereo frames
+    pub fn generate_stereo_samples(&mut self, count: usize) -> Vec<f32> {
+        let mut samples = Vec::with_capacity(count * 2);
+        for _ in 0..count {
+            let (left, right) = self.generate_stereo_sample();
+            samples.push(left);
+            samples.push(right);
+        }
+        samples
+    }
+
+    fn generate_stereo_sample(&mut self) -> (f32, f32) {
+        let (mut left, mut right) = (0.0, 0.0);
+        let mut mix = |(l, r): (f32, f32), gain: f32| {
+            left += l * gain;
+            right += r * gain;
+        };
 
         // Voice effects (highest priority)
         if self.voice_active && self.voice_oscillator.enabled {
-            sample += Self::generate_digital_oscillator_sample(&mut self.voice_oscillator, self.sample_rate);
+            mix(Self::generate_digital_oscillator_sample(&mut self.voice_oscillator, self.sample_rate), 1.0);
         }
         
-        // Sample playback (also high priority)
-        let dt = 1.0 / self.sample_rate;
-        sample += self.update_sample_playback(dt);
+        // Sample channels (also high priority)
+        mix(self.samples.sample(self.sample_rate), self.sample_volume);
         
-        // Always check for sound effects first
-        if self.sfx_active && self.test_osc.enabled {
-            sample += Self::generate_digital_oscillator_sample(&mut self.test_osc, self.sample_rate);
-        } else if self.sound_test_mode {
+        // Sound effects, each on its own voice
+        for osc in self.sfx_voices.iter_mut().filter(|osc| osc.enabled) {
+            mix(Self::generate_digital_oscillator_sample(osc, self.sample_rate), 1.0);
+        }
+
+        if self.sound_test_mode {
             // In sound test mode, only use the test oscillator
             if self.test_osc.enabled {
-                sample += Self::generate_digital_oscillator_sample(&mut self.test_osc, self.sample_rate);
+                mix(Self::generate_digital_oscillator_sample(&mut self.test_osc, self.sample_rate), 1.0);
             }
         } else {
-            // Normal game mode - use all channels
-            // Generate pulse channel 1
-            if self.pulse1.enabled {
-                sample += Self::generate_pulse_sample(&mut self.pulse1, self.sample_rate);
-            }
-
-            // Generate pulse channel 2
-            if self.pulse2.enabled {
-                sample += Self::generate_pulse_sample(&mut self.pulse2, self.sample_rate);
-            }
-
-            // Generate triangle channel
-            if self.triangle.enabled {
-                sample += Self::generate_triangle_sample(&mut self.triangle, self.sample_rate);
-            }
-
-            // Generate noise channel
-            if self.noise.enabled {
-                sample += Self::generate_noise_sample(&mut self.noise);
-            }
+            // Normal game mode - use all channels (each is silent until
+            // enabled and given a length)
+            let [pulse1_pan, pulse2_pan, triangle_pan, noise_pan] = self.channel_pans;
+            mix(mixer::pan(self.pulse1.sample(self.sample_rate), pulse1_pan), 1.0);
+            mix(mixer::pan(self.pulse2.sample(self.sample_rate), pulse2_pan), 1.0);
+            mix(mixer::pan(self.triangle.sample(self.sample_rate), triangle_pan), 1.0);
+            mix(mixer::pan(self.noise.sample(self.sample_rate), noise_pan), 1.0);
         }
 
         // Generate polyphonic synthesizer (always active when notes are playing)
         if self.synth_enabled && !self.synth_oscillators.is_empty() {
-            let mut poly_sample = 0.0;
             for osc in self.synth_oscillators.values_mut() {
                 if osc.enabled {
-                    poly_sample += Self::generate_digital_oscillator_sample(osc, self.sample_rate);
+                    mix(Self::generate_digital_oscillator_sample(osc, self.sample_rate), self.poly_volume);
                 }
             }
-            sample += poly_sample * self.poly_volume;
-        }
 
-        // Generate SID-style 3-voice synthesizer (for games) - only if voices are active
-        if self.sid_enabled && (self.sid_voice1.enabled || self.sid_voice2.enabled || self.sid_voice3.enabled) {
-            let mut sid_sample = 0.0;
-            if self.sid_voice1.enabled {
-                sid_sample += Self::generate_digital_oscillator_sample(&mut self.sid_voice1, self.sample_rate);
-            }
-            if self.sid_voice2.enabled {
-                sid_sample += Self::generate_digital_oscillator_sample(&mut self.sid_voice2, self.sample_rate);
-            }
-            if self.sid_voice3.enabled {
-                sid_sample += Self::generate_digital_oscillator_sample(&mut self.sid_voice3, self.sample_rate);
+            // Drop notes whose release has finished
+            self.synth_oscillators.retain(|_, osc| osc.enabled);
+            if self.synth_oscillators.is_empty() {
+                self.synth_enabled = false;
             }
-            sample += sid_sample * self.sid_volume;
         }
 
-        sample * self.master_volume
-    }
+        // Generate SID-style 3-voice synthesizer (for games). All three
+        // oscillators run every sample, so a silent voice can still sync or
+        // ring modulate the next one; only active voices are heard.
+        self.route_sid_modulators();
+        for osc in [&mut self.sid_voice1, &mut self.sid_voice2, &mut self.sid_voice3] {
+            if self.sid_enabled && osc.enabled {
+                mix(Self::generate_digital_oscillator_sample(osc, self.sample_rate), self.sid_volume);
+            } else {
+                Self::advance_silent_oscillator(osc, self.sample_rate);
+            }
+        }
 
-    fn generate_pulse_sample(channel: &mut PulseChannel, sample_rate: f32) -> f32 {
-        let duty_table = [0.125, 0.25, 0.5, 0.75];
-        let duty_threshold = duty_table[channel.pulse_width as usize];
+        // Voices switch themselves off when their release finishes
+        if self.sid_enabled {
+            self.check_sid_enabled();
+        }
 
-        channel.phase += channel.frequency / sample_rate;
-        if channel.phase >= 1.0 {
-            channel.phase -= 1.0;
+        // Tracker music voices, ducked under effects that ask for it
+        let duck = if self.sfx.is_ducking() { self.sfx_duck_volume } else { 1.0 };
+        for osc in self.music_voices.iter_mut().filter(|osc| osc.enabled) {
+            mix(Self::generate_digital_oscillator_sample(osc, self.sample_rate), duck);
         }
 
-        let volume = if channel.phase < duty_threshold { 1.0 } else { -1.0 };
-        volume * (channel.volume as f32 / 15.0)
+        // Wavetable voices
+        mix(self.wavetable.sample(self.sample_rate), 1.0);
+
+        (left * self.master_volume, right * self.master_volume)
     }
 
-    fn generate_triangle_sample(channel: &mut TriangleChannel, sample_rate: f32) -> f32 {
-        channel.phase += channel.frequency / sample_rate;
-        if channel.phase >= 1.0 {
-            channel.phase -= 1.0;
-        }
+    // Register writes ($4000-$4017, see apu_channels.rs for the layout).
+    // Each channel takes its four registers as 0-3.
+    pub fn write_pulse1_register(&mut self, register: u8, value: u8) {
+        self.pulse1.write(register, value);
+    }
 
-        // Triangle wave: -1 to 1 and back
-        let volume = if channel.phase < 0.5 {
-            4.0 * channel.phase - 1.0
-        } else {
-            3.0 - 4.0 * channel.phase
-        };
+    pub fn write_pulse2_register(&mut self, register: u8, value: u8) {
+        self.pulse2.write(register, value);
+    }
 
-        volume * 0.5 // Triangle is quieter than pulse
+    pub fn write_triangle_register(&mut self, register: u8, value: u8) {
+        self.triangle.write(register, value);
     }
 
-    fn generate_noise_sample(channel: &mut NoiseChannel) -> f32 {
-        // Simple LFSR-based noise generation
-        let feedback = ((channel.shift_register & 1) ^ ((channel.shift_register >> 1) & 1)) != 0;
-        channel.shift_register >>= 1;
-        if feedback {
-            channel.shift_register |= 0x4000;
-        }
+    pub fn write_noise_register(&mut self, register: u8, value: u8) {
+        self.noise.write(register, value);
+    }
 
-        let volume = if (channel.shift_register & 1) != 0 { 1.0 } else { -1.0 };
-        volume * (channel.volume as f32 / 15.0) * 0.5
+    // Stereo placement of the register channels: 0 = pulse 1, 1 = pulse 2,
+    // 2 = triangle, 3 = noise
+    pub fn set_channel_pan(&mut self, channel: u8, pan: f32) {
+        if let Some(channel_pan) = self.channel_pans.get_mut(channel as usize) {
+            *channel_pan = pan.clamp(-1.0, 1.0);
+        }
     }
 
-    // Register write methods (will be called by CPU when writing to APU registers)
-    pub fn write_pulse1_register(&mut self, register: u8, value: u8) {
-        match register {
-            0 => {
-                self.pulse1.pulse_width = (value >> 6) & 3;
-                self.pulse1.volume = value & 15;
-            }
-            1 => {
-                // Sweep register (TODO: implement sweep)
-            }
-            2 => {
-                // Frequency low byte
-                let freq_raw = (self.pulse1.frequency as u16 & 0x700) | value as u16;
-                self.pulse1.frequency = 1789773.0 / (16.0 * (freq_raw as f32 + 1.0));
-            }
-            3 => {
-                // Frequency high byte + length
-                let freq_raw = ((value as u16 & 7) << 8) | (self.pulse1.frequency as u16 & 0xFF);
-                self.pulse1.frequency = 1789773.0 / (16.0 * (freq_raw as f32 + 1.0));
-                self.pulse1.enabled = true;
-                self.pulse1.phase = 0.0;
-            }
-            _ => {}
+    // $4015 - channel enables: bit 0 = pulse 1, 1 = pulse 2, 2 = triangle, 3 = noise
+    pub fn write_status_register(&mut self, value: u8) {
+        self.pulse1.set_enabled(value & 0x01 != 0);
+        self.pulse2.set_enabled(value & 0x02 != 0);
+        self.triangle.set_enabled(value & 0x04 != 0);
+        self.noise.set_enabled(value & 0x08 != 0);
+    }
+
+    // $4015 - channels whose length counter is still running
+    pub fn read_status_register(&self) -> u8 {
+        self.pulse1.is_playing() as u8
+            | (self.pulse2.is_playing() as u8) << 1
+            | (self.triangle.is_playing() as u8) << 2
+            | (self.noise.is_playing() as u8) << 3
+    }
+
+    // $4017 - sequencer mode; restarts the sequence
+    pub fn write_frame_counter(&mut self, value: u8) {
+        self.sequencer_divider = 0;
+        let clocks = self.frame_sequencer.write(value);
+        self.clock_channels(clocks);
+    }
+
+    // Write by address. Returns false for addresses outside the APU page.
+    pub fn write_register(&mut self, address: u16, value: u8) -> bool {
+        let register = (address & 3) as u8;
+        match address {
+            0x4000..=0x4003 => self.write_pulse1_register(register, value),
+            0x4004..=0x4007 => self.write_pulse2_register(register, value),
+            0x4008..=0x400B => self.write_triangle_register(register, value),
+            0x400C..=0x400F => self.write_noise_register(register, value),
+            0x4015 => self.write_status_register(value),
+            0x4017 => self.write_frame_counter(value),
+            _ => return false,
         }
+        true
     }
 
     fn update_filter_coefficients(filter: &mut ResonantFilter, sample_rate: f32) {
@@ -694,16 +731,22 @@ impl Apu {
         };
     }
 
-    fn apply_digital_delay(delay: &mut DigitalDelay, input: f32, sample_rate: f32) -> f32 {
+    // Returns (left, right): the right side's echoes are read a little
+    // further back as the stereo width grows
+    fn apply_digital_delay(delay: &mut DigitalDelay, input: f32, sample_rate: f32) -> (f32, f32) {
         if !delay.enabled {
-            return input;
+            return (input, input);
         }
 
         // Update buffer positions based on delay time
         Self::update_delay_buffer_positions(delay, sample_rate);
 
-        // Read delayed sample
+        // Read delayed samples, keeping the right tap inside the buffer
         let delayed_sample = delay.buffer[delay.read_pos];
+        let delay_samples = (delay.write_pos + delay.buffer_size - delay.read_pos) % delay.buffer_size;
+        let spread = ((delay.width * DELAY_SPREAD_SECONDS * sample_rate) as usize)
+            .min(delay.buffer_size - 1 - delay_samples);
+        let delayed_right = delay.buffer[(delay.read_pos + delay.buffer_size - spread) % delay.buffer_size];
 
         // Apply feedback with analog-style filtering
         // Simple one-pole lowpass: y[n] = a*x[n] + (1-a)*y[n-1]
@@ -721,65 +764,131 @@ impl Apu {
 
         // Mix dry and wet signals
         let dry = input * (1.0 - delay.mix);
-        let wet = delayed_sample * delay.mix;
+        let (wet_left, wet_right) = (delayed_sample * delay.mix, delayed_right * delay.mix);
 
         // Soft clipping to prevent digital distortion
-        (dry + wet).clamp(-1.5, 1.5)
-    }
+        ((dry + wet_left).clamp(-1.5, 1.5), (dry + wet_right).clamp(-1.5, 1.5))
+    }
+
+    // Step the oscillator one sample, applying the SID test bit and hard sync.
+    // `pitch` is LFO modulation in semitones. Returns how many times the noise
+    // generator clocks.
+    fn advance_phase(osc: &mut DigitalOscillator, pitch: f32, sample_rate: f32) -> u32 {
+        if osc.sid.test {
+            // SID test bit holds the oscillator and noise generator at zero
+            osc.phase = 0.0;
+            osc.lfsr = 0x7FFF;
+            osc.sid.wrapped = false;
+            return 0;
+        }
+
+        // Hard sync restarts the waveform whenever the modulating voice wraps
+        if osc.sid.sync && osc.sid.modulator_wrapped {
+            osc.phase = 0.0;
+        }
 
-    fn generate_digital_oscillator_sample(osc: &mut DigitalOscillator, sample_rate: f32) -> f32 {
-        let effective_freq = osc.frequency * (1.0 + osc.detune);
-        osc.phase += effective_freq / sample_rate;
+        let effective_freq = osc.frequency * (1.0 + osc.detune) * 2.0_f32.powf(pitch / 12.0);
+        let next_phase = osc.phase + effective_freq / sample_rate;
+        let noise_clocks = ((next_phase * NOISE_CLOCKS_PER_CYCLE).floor() - (osc.phase * NOISE_CLOCKS_PER_CYCLE).floor()) as u32;
+        osc.phase = next_phase;
 
         // Keep phase in 0.0 to 1.0 range
+        osc.sid.wrapped = osc.phase >= 1.0;
         while osc.phase >= 1.0 {
             osc.phase -= 1.0;
         }
+        noise_clocks
+    }
+
+    // Keep a voice that isn't heard running, noise generator included
+    fn advance_silent_oscillator(osc: &mut DigitalOscillator, sample_rate: f32) {
+        let noise_clocks = Self::advance_phase(osc, 0.0, sample_rate);
+        let is_noise = if osc.sid.waveform_mask != 0 { osc.sid.waveform_mask & SID_NOISE != 0 } else { osc.waveform == 4 };
+        if is_noise {
+            Self::clock_noise(osc, noise_clocks);
+        }
+    }
+
+    // Returns the voice's (left, right) sample, placed by its pan
+    fn generate_digital_oscillator_sample(osc: &mut DigitalOscillator, sample_rate: f32) -> (f32, f32) {
+        let level = osc.envelope.next_level(sample_rate);
+        if osc.envelope.is_finished() {
+            // Released and faded out
+            osc.enabled = false;
+            return (0.0, 0.0);
+        }
+        let modulation = Modulation::from_lfos(&mut osc.lfos, sample_rate);
+
+        let noise_clocks = Self::advance_phase(osc, modulation.pitch, sample_rate);
 
         // Generate raw waveform
-        let raw_sample = match osc.waveform {
-            0 => {
-                // Pulse wave (square with variable pulse width)
-                if osc.phase < osc.pulse_width { 1.0 } else { -1.0 }
-            },
-            1 => {
-                // Sawtooth wave
-                2.0 * osc.phase - 1.0
-            },
-            2 => {
-                // Triangle wave
-                if osc.phase < 0.5 {
-                    4.0 * osc.phase - 1.0
-                } else {
-                    3.0 - 4.0 * osc.phase
-                }
-            },
-            3 => {
-                // Sine wave
-                (osc.phase * 2.0 * PI).sin()
-            },
-            4 => {
-                // Digital noise (LFSR)
-                let feedback = ((osc.lfsr & 1) ^ ((osc.lfsr >> 1) & 1)) != 0;
-                osc.lfsr >>= 1;
-                if feedback {
-                    osc.lfsr |= 0x4000;
-                }
-                if (osc.lfsr & 1) != 0 { 1.0 } else { -1.0 }
-            },
-            _ => 0.0,
+        let pulse_width = (osc.pulse_width + modulation.pulse_width).clamp(0.05, 0.95);
+        let raw_sample = if osc.sid.waveform_mask != 0 {
+            // Combined SID waveforms
+            if osc.sid.waveform_mask & SID_NOISE != 0 {
+                Self::clock_noise(osc, noise_clocks);
+            }
+            sid::combined_waveform(osc.sid.waveform_mask, osc.phase, pulse_width, osc.sid.ring_inverts(), osc.lfsr)
+        } else {
+            match osc.waveform {
+                0 => {
+                    // Pulse wave (square with variable pulse width)
+                    if osc.phase < pulse_width { 1.0 } else { -1.0 }
+                },
+                1 => {
+                    // Sawtooth wave
+                    2.0 * osc.phase - 1.0
+                },
+                2 => {
+                    // Triangle wave (flipped by ring modulation)
+                    let triangle = if osc.phase < 0.5 {
+                        4.0 * osc.phase - 1.0
+                    } else {
+                        3.0 - 4.0 * osc.phase
+                    };
+                    if osc.sid.ring_inverts() { -triangle } else { triangle }
+                },
+                3 => {
+                    // Sine wave
+                    (osc.phase * 2.0 * PI).sin()
+                },
+                4 => {
+                    // Digital noise (LFSR)
+                    Self::clock_noise(osc, noise_clocks);
+                    if (osc.lfsr & 1) != 0 { 1.0 } else { -1.0 }
+                },
+                _ => 0.0,
+            }
         };
 
-        // Update filter coefficients if needed (for efficiency, could cache this)
+        // Update filter coefficients if needed (for efficiency, could cache this).
+        // LFO cutoff modulation only applies to this sample.
+        let base_cutoff = osc.filter.cutoff;
+        osc.filter.cutoff = (base_cutoff + modulation.cutoff).clamp(0.0, 1.0);
         Self::update_filter_coefficients(&mut osc.filter, sample_rate);
+        osc.filter.cutoff = base_cutoff;
 
         // Apply resonant filter to the raw waveform
         let filtered_sample = Self::apply_resonant_filter(&mut osc.filter, raw_sample);
 
         // Apply digital delay effect
-        let delayed_sample = Self::apply_digital_delay(&mut osc.delay, filtered_sample, sample_rate);
+        let (left, right) = Self::apply_digital_delay(&mut osc.delay, filtered_sample, sample_rate);
+
+        let gain = osc.volume * level * modulation.volume;
+        let (pan_left, pan_right) = mixer::pan_gains(osc.pan);
+        (left * gain * pan_left, right * gain * pan_right)
+    }
 
-        delayed_sample * osc.volume
+    fn clock_noise(osc: &mut DigitalOscillator, clocks: u32) {
+        // Past 15 steps every bit has been shifted out, so the output is
+        // already unrelated to the last sample's
+        for _ in 0..clocks.min(15) {
+            let feedback = ((osc.lfsr & 1) ^ ((osc.lfsr >> 1) & 1)) != 0;
+            osc.lfsr >>= 1;
+            if feedback {
+                osc.lfsr |= 0x4000;
+            }
+        }
     }
 
     // MIDI note to frequency conversion
@@ -878,6 +987,14 @@ impl Apu {
         self.test_osc.delay.mix
     }
 
+    pub fn set_delay_width(&mut self, width: f32) {
+        self.test_osc.delay.width = width.clamp(0.0, 1.0);
+    }
+
+    pub fn get_delay_width(&self) -> f32 {
+        self.test_osc.delay.width
+    }
+
     // Demo melody control methods
     pub fn set_melody_enabled(&mut self, enabled: bool) {
         self.melody_enabled = enabled;
@@ -916,24 +1033,94 @@ impl Apu {
         self.melody_tempo
     }
 
-    // Sound effect methods
-    pub fn play_sound_effect(&mut self, start_note: u8, end_note: u8, waveform: u8, duration: f32) {
-        // Don't interrupt an already playing sound effect
-        if self.sfx_active {
-            return;
+    // Sound effect methods. Effects come from a .zsx table (see sfx.rs) and
+    // are played by sound ID; each takes a free effect voice.
+    pub fn load_sfx_table(&mut self, data: &[u8]) -> bool {
+        match sfx::table_from_bytes(data) {
+            Ok(effects) => {
+                self.stop_sound_effects();
+                self.sfx.load(effects);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn clear_sfx_table(&mut self) {
+        self.stop_sound_effects();
+        self.sfx.load(Vec::new());
+    }
+
+    // Returns false for an unknown ID, or if every voice is playing a more
+    // important effect
+    pub fn play_sfx(&mut self, sound_id: u32) -> bool {
+        self.play_sfx_panned(sound_id, 0.0)
+    }
+
+    pub fn play_sfx_panned(&mut self, sound_id: u32, pan: f32) -> bool {
+        match self.sfx.effect(sound_id) {
+            Some(effect) => self.start_sound_effect(effect, pan),
+            None => false,
+        }
+    }
+
+    // Play a single 16-byte effect definition, for auditioning one while
+    // tuning it
+    pub fn play_sfx_definition(&mut self, definition: &[u8]) -> bool {
+        match SoundEffect::from_bytes(definition) {
+            Ok(effect) => self.start_sound_effect(effect, 0.0),
+            Err(_) => false,
+        }
+    }
+
+    // Effects run once per video frame, like the music driver
+    pub fn step_sfx_frame(&mut self) {
+        self.sfx.step_frame();
+        for voice in 0..SFX_VOICES {
+            self.apply_sfx_output(voice);
+        }
+    }
+
+    pub fn stop_sound_effects(&mut self) {
+        self.sfx.stop_all();
+        self.sfx_voices.iter_mut().for_each(|osc| osc.enabled = false);
+    }
+
+    pub fn set_sfx_duck_volume(&mut self, volume: f32) {
+        self.sfx_duck_volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn is_sound_effect_playing(&self) -> bool {
+        self.sfx.is_playing()
+    }
+
+    fn start_sound_effect(&mut self, effect: SoundEffect, pan: f32) -> bool {
+        let Some(voice) = self.sfx.play(effect) else {
+            return false;
+        };
+
+        // Restart the voice so an effect always sounds the same
+        let osc = &mut self.sfx_voices[voice];
+        Self::set_sid_waveform(osc, effect.waveform);
+        osc.phase = 0.0;
+        osc.lfsr = 0x7FFF;
+        osc.envelope = Adsr::gate();
+        osc.pan = pan.clamp(-1.0, 1.0);
+        osc.enabled = true;
+        self.apply_sfx_output(voice);
+        true
+    }
+
+    fn apply_sfx_output(&mut self, voice: usize) {
+        let osc = &mut self.sfx_voices[voice];
+        match self.sfx.output(voice) {
+            Some(frame) => {
+                osc.frequency = frame.frequency;
+                osc.pulse_width = frame.pulse_width;
+                osc.volume = frame.volume * SFX_LEVEL;
+            }
+            None => osc.enabled = false,
         }
-        
-        self.sfx_active = true;
-        self.sfx_timer = 0.0;
-        self.sfx_duration = duration;
-        self.sfx_start_note = start_note;
-        self.sfx_end_note = end_note;
-        self.sfx_waveform = waveform;
-
-        // Immediately set the starting frequency and waveform
-        self.test_osc.frequency = Self::midi_to_frequency(start_note);
-        self.test_osc.waveform = waveform;
-        self.test_osc.enabled = true;
     }
 
     pub fn play_voice_effect(&mut self, voice_type: u8) {
@@ -1069,57 +1256,54 @@ impl Apu {
     }
     
     // Sample playback methods
+    pub fn load_sample(&mut self, data: &[u8], sample_rate: u32) -> Option<u32> {
+        self.samples.load(data, sample_rate)
+    }
+
+    pub fn set_sample_loop(&mut self, sample_id: u32, start: u32, end: u32) -> bool {
+        self.samples.set_loop(sample_id, start, end)
+    }
+
+    pub fn clear_samples(&mut self) {
+        self.samples.clear();
+    }
+
+    // Returns the sample channel it started on
+    pub fn play_sample(&mut self, sample_id: u32, rate: f32, volume: f32, pan: f32, looping: bool) -> Option<u32> {
+        self.samples.play(sample_id, rate, volume, pan, looping)
+    }
+
+    pub fn stop_sample_channel(&mut self, channel: u32) {
+        self.samples.stop(channel);
+    }
+
+    pub fn set_sample_channel_rate(&mut self, channel: u32, rate: f32) {
+        self.samples.set_rate(channel, rate);
+    }
+
+    pub fn set_sample_channel_volume(&mut self, channel: u32, volume: f32) {
+        self.samples.set_volume(channel, volume);
+    }
+
+    pub fn set_sample_channel_pan(&mut self, channel: u32, pan: f32) {
+        self.samples.set_pan(channel, pan);
+    }
+
+    // Stops every sample channel
     pub fn stop_sample(&mut self) {
-        self.sample_active = false;
-        self.sample_data = None;
-        self.sample_position = 0.0;
+        self.samples.stop_all();
     }
     
     pub fn set_sample_volume(&mut self, volume: f32) {
         self.sample_volume = volume.clamp(0.0, 1.0);
     }
-    
-    pub fn play_laugh_sample(&mut self) {
-        self.sample_data = Some(LAUGH_SAMPLE_RETRO_SAMPLE_DATA);
-        self.sample_data_rate = LAUGH_SAMPLE_RETRO_SAMPLE_RATE;
-        self.sample_position = 0.0;
-        self.sample_active = true;
-    }
-    
-    fn update_sample_playback(&mut self, dt: f32) -> f32 {
-        if !self.sample_active {
-            return 0.0;
-        }
-        
-        if let Some(data) = self.sample_data {
-            let sample_index = self.sample_position as usize;
-            
-            // Check if we've reached the end of the sample
-            if sample_index >= data.len() {
-                self.sample_active = false;
-                self.sample_data = None;
-                return 0.0;
-            }
-            
-            // Get current sample (convert from 8-bit unsigned to signed float)
-            let sample_u8 = data[sample_index];
-            let sample_f32 = (sample_u8 as f32 - 128.0) / 128.0; // Convert to -1.0 to 1.0 range
-            
-            // Advance position based on sample rate ratio
-            // APU runs at 44.1kHz, sample data is at 5.5kHz, but we want proper pitch/duration balance
-            // Using 0.75 to get closer to original pitch while keeping retro quality
-            let advance_rate = (self.sample_data_rate as f32 / self.sample_rate) * 0.75;
-            self.sample_position += advance_rate;
-            
-            return sample_f32 * self.sample_volume;
-        }
-        
-        0.0
-    }
 
     // Polyphonic synthesizer methods for Z-Synth
     pub fn synth_note_on(&mut self, note: u32) {
-        if !self.synth_oscillators.contains_key(&note) {
+        if let Some(osc) = self.synth_oscillators.get_mut(&note) {
+            // Pressed again while still releasing
+            osc.envelope.trigger();
+        } else {
             let mut osc = DigitalOscillator {
                 enabled: true,
                 frequency: Self::midi_to_frequency(note as u8),
@@ -1128,7 +1312,11 @@ impl Apu {
                 pulse_width: 0.5,
                 volume: 0.3, // Lower volume for polyphony
                 detune: 0.0,
+                pan: self.synth_pan,
                 lfsr: 0x7FFF,
+                envelope: self.synth_envelope,
+                lfos: self.synth_lfos,
+                sid: SidControl::default(),
                 filter: ResonantFilter {
                     enabled: self.global_filter_enabled,
                     filter_type: self.global_filter_type,
@@ -1144,6 +1332,7 @@ impl Apu {
                     delay_time: 0.3,
                     feedback: 0.4,
                     mix: 0.2,
+                    width: 0.0,
                     buffer: vec![0.0; 44100], // 1 second buffer at 44.1kHz
                     buffer_size: 44100,
                     write_pos: 0,
@@ -1154,16 +1343,17 @@ impl Apu {
             
             // Calculate filter coefficients for the new oscillator
             Self::update_filter_coefficients(&mut osc.filter, self.sample_rate);
+            osc.envelope.trigger();
             
             self.synth_oscillators.insert(note, osc);
         }
         self.synth_enabled = true;
     }
 
+    // The note keeps sounding through its release and is removed once silent
     pub fn synth_note_off(&mut self, note: u32) {
-        self.synth_oscillators.remove(&note);
-        if self.synth_oscillators.is_empty() {
-            self.synth_enabled = false;
+        if let Some(osc) = self.synth_oscillators.get_mut(&note) {
+            osc.envelope.release();
         }
     }
 
@@ -1174,8 +1364,9 @@ impl Apu {
         }
     }
 
+    // Notes still held down; released notes fading out aren't counted
     pub fn get_synth_active_note_count(&self) -> usize {
-        self.synth_oscillators.len()
+        self.synth_oscillators.values().filter(|osc| osc.envelope.is_held()).count()
     }
     
     // Global filter control methods for Z-Synth
@@ -1224,7 +1415,9 @@ impl Apu {
     #[wasm_bindgen]
     pub fn sid_voice1_play_note(&mut self, note: u8, waveform: u8) {
         self.sid_voice1.frequency = Self::midi_to_frequency(note);
-        self.sid_voice1.waveform = waveform.clamp(0, 4);
+        Self::set_sid_waveform(&mut self.sid_voice1, waveform);
+        self.sid_voice1.sid.gate = true;
+        self.sid_voice1.envelope.trigger();
         self.sid_voice1.enabled = true;
         self.sid_enabled = true;
     }
@@ -1232,7 +1425,9 @@ impl Apu {
     #[wasm_bindgen]
     pub fn sid_voice2_play_note(&mut self, note: u8, waveform: u8) {
         self.sid_voice2.frequency = Self::midi_to_frequency(note);
-        self.sid_voice2.waveform = waveform.clamp(0, 4);
+        Self::set_sid_waveform(&mut self.sid_voice2, waveform);
+        self.sid_voice2.sid.gate = true;
+        self.sid_voice2.envelope.trigger();
         self.sid_voice2.enabled = true;
         self.sid_enabled = true;
     }
@@ -1240,34 +1435,42 @@ impl Apu {
     #[wasm_bindgen]
     pub fn sid_voice3_play_note(&mut self, note: u8, waveform: u8) {
         self.sid_voice3.frequency = Self::midi_to_frequency(note);
-        self.sid_voice3.waveform = waveform.clamp(0, 4);
+        Self::set_sid_waveform(&mut self.sid_voice3, waveform);
+        self.sid_voice3.sid.gate = true;
+        self.sid_voice3.envelope.trigger();
         self.sid_voice3.enabled = true;
         self.sid_enabled = true;
     }
     
     #[wasm_bindgen]
     pub fn sid_voice1_stop(&mut self) {
-        self.sid_voice1.enabled = false;
-        self.check_sid_enabled();
+        // Goes quiet once the envelope's release finishes
+        self.sid_voice1.sid.gate = false;
+        self.sid_voice1.envelope.release();
     }
     
     #[wasm_bindgen]
     pub fn sid_voice2_stop(&mut self) {
-        self.sid_voice2.enabled = false;
-        self.check_sid_enabled();
+        // Goes quiet once the envelope's release finishes
+        self.sid_voice2.sid.gate = false;
+        self.sid_voice2.envelope.release();
     }
     
     #[wasm_bindgen]
     pub fn sid_voice3_stop(&mut self) {
-        self.sid_voice3.enabled = false;
-        self.check_sid_enabled();
+        // Goes quiet once the envelope's release finishes
+        self.sid_voice3.sid.gate = false;
+        self.sid_voice3.envelope.release();
     }
     
     #[wasm_bindgen]
     pub fn sid_stop_all(&mut self) {
-        self.sid_voice1.enabled = false;
-        self.sid_voice2.enabled = false;
-        self.sid_voice3.enabled = false;
+        // Silenced at once, gates cleared so the next gate-on starts a note
+        for osc in [&mut self.sid_voice1, &mut self.sid_voice2, &mut self.sid_voice3] {
+            osc.enabled = false;
+            osc.sid.gate = false;
+            osc.envelope.reset();
+        }
         self.sid_enabled = false;
     }
     
@@ -1292,35 +1495,55 @@ impl Apu {
     
     #[wasm_bindgen]
     pub fn sid_set_filter_cutoff(&mut self, cutoff: f32) {
-        let normalized_cutoff = (cutoff / (self.sample_rate * 0.5)).min(1.0);
-        self.sid_voice1.filter.cutoff = normalized_cutoff;
-        self.sid_voice2.filter.cutoff = normalized_cutoff;
-        self.sid_voice3.filter.cutoff = normalized_cutoff;
-        Self::update_filter_coefficients(&mut self.sid_voice1.filter, self.sample_rate);
-        Self::update_filter_coefficients(&mut self.sid_voice2.filter, self.sample_rate);
-        Self::update_filter_coefficients(&mut self.sid_voice3.filter, self.sample_rate);
+        self.sid_filter_cutoff = cutoff;
+        self.update_sid_filter();
     }
     
     #[wasm_bindgen]
     pub fn sid_set_filter_resonance(&mut self, resonance: f32) {
-        let clamped_resonance = resonance.clamp(0.0, 10.0);
-        self.sid_voice1.filter.resonance = clamped_resonance;
-        self.sid_voice2.filter.resonance = clamped_resonance;
-        self.sid_voice3.filter.resonance = clamped_resonance;
-        Self::update_filter_coefficients(&mut self.sid_voice1.filter, self.sample_rate);
-        Self::update_filter_coefficients(&mut self.sid_voice2.filter, self.sample_rate);
-        Self::update_filter_coefficients(&mut self.sid_voice3.filter, self.sample_rate);
+        self.sid_filter_resonance = resonance.clamp(0.0, 10.0);
+        self.update_sid_filter();
     }
     
+    // 0=lowpass, 1=highpass, 2=bandpass, plus SID_FILTER_6581 ($04) for the
+    // 6581's cutoff curve instead of the 8580's
     #[wasm_bindgen]
     pub fn sid_set_filter_type(&mut self, filter_type: u8) {
-        let clamped_type = filter_type.clamp(0, 2);
+        let clamped_type = (filter_type & !SID_FILTER_6581).clamp(0, 2);
+        self.sid_filter_6581 = filter_type & SID_FILTER_6581 != 0;
         self.sid_voice1.filter.filter_type = clamped_type;
         self.sid_voice2.filter.filter_type = clamped_type;
         self.sid_voice3.filter.filter_type = clamped_type;
-        Self::update_filter_coefficients(&mut self.sid_voice1.filter, self.sample_rate);
-        Self::update_filter_coefficients(&mut self.sid_voice2.filter, self.sample_rate);
-        Self::update_filter_coefficients(&mut self.sid_voice3.filter, self.sample_rate);
+        self.update_sid_filter();
+    }
+
+    // SID control register for voice 1-3 (see sid.rs for the bit layout).
+    // Waveform bits of zero keep the voice's current waveform.
+    #[wasm_bindgen]
+    pub fn sid_set_voice_control(&mut self, voice: u8, control: u8) {
+        let Some(osc) = self.sid_voice_mut(voice) else {
+            return;
+        };
+        osc.sid.sync = control & SID_SYNC != 0;
+        osc.sid.ring_mod = control & SID_RING_MOD != 0;
+        osc.sid.test = control & SID_TEST != 0;
+        if control & SID_WAVEFORMS != 0 {
+            osc.sid.waveform_mask = control & SID_WAVEFORMS;
+        }
+
+        // Gate edges start and release the envelope
+        let gate = control & SID_GATE != 0;
+        let gate_on = gate && !osc.sid.gate;
+        if gate_on {
+            osc.envelope.trigger();
+            osc.enabled = true;
+        } else if !gate && osc.sid.gate {
+            osc.envelope.release();
+        }
+        osc.sid.gate = gate;
+        if gate_on {
+            self.sid_enabled = true;
+        }
     }
     
     // Polyphonic layer API (enhanced Z-Synth access)
@@ -1349,8 +1572,388 @@ impl Apu {
         self.synth_enabled = false;
     }
     
+    // Envelope and LFO shaping. Z-Synth settings apply to held notes and
+    // every new one; SID voices (1-3) are shaped individually.
+    #[wasm_bindgen]
+    pub fn set_synth_envelope(&mut self, attack: f32, decay: f32, sustain: f32, release: f32) {
+        self.synth_envelope = Adsr::new(attack, decay, sustain, release);
+        for osc in self.synth_oscillators.values_mut() {
+            osc.envelope.set_shape(&self.synth_envelope);
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn set_synth_lfo(&mut self, lfo: u8, target: u8, waveform: u8, rate: f32, depth: f32) -> bool {
+        let (Some(slot), Some(target)) = (self.synth_lfos.get_mut(lfo as usize), LfoTarget::from_u8(target)) else {
+            return false;
+        };
+        *slot = Lfo::new(target, waveform, rate, depth);
+        for osc in self.synth_oscillators.values_mut() {
+            osc.lfos[lfo as usize] = *slot;
+        }
+        true
+    }
+
+    // Applies to playing notes and new ones
+    #[wasm_bindgen]
+    pub fn set_synth_pan(&mut self, pan: f32) {
+        self.synth_pan = pan.clamp(-1.0, 1.0);
+        for osc in self.synth_oscillators.values_mut() {
+            osc.pan = self.synth_pan;
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn sid_set_voice_envelope(&mut self, voice: u8, attack: f32, decay: f32, sustain: f32, release: f32) {
+        if let Some(osc) = self.sid_voice_mut(voice) {
+            osc.envelope.set_shape(&Adsr::new(attack, decay, sustain, release));
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn sid_set_voice_lfo(&mut self, voice: u8, lfo: u8, target: u8, waveform: u8, rate: f32, depth: f32) -> bool {
+        let Some(target) = LfoTarget::from_u8(target) else {
+            return false;
+        };
+        match self.sid_voice_mut(voice).and_then(|osc| osc.lfos.get_mut(lfo as usize)) {
+            Some(slot) => {
+                *slot = Lfo::new(target, waveform, rate, depth);
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn sid_set_voice_pan(&mut self, voice: u8, pan: f32) {
+        if let Some(osc) = self.sid_voice_mut(voice) {
+            osc.pan = pan.clamp(-1.0, 1.0);
+        }
+    }
+
+    // Tracker music API. Songs are .zsm data (see music.rs); loading returns
+    // the ID to play them by.
+    #[wasm_bindgen]
+    pub fn load_music(&mut self, data: &[u8]) -> Option<u32> {
+        Song::from_bytes(data).ok().map(|song| self.music.load(song))
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_music(&mut self) {
+        self.music.clear();
+        self.silence_music_voices();
+    }
+
+    #[wasm_bindgen]
+    pub fn play_music(&mut self, music_id: u32) -> bool {
+        self.silence_music_voices();
+        self.music.play(music_id)
+    }
+
+    #[wasm_bindgen]
+    pub fn stop_music(&mut self) {
+        self.music.stop();
+        self.release_music_voices();
+    }
+
+    #[wasm_bindgen]
+    pub fn fade_out_music(&mut self, frames: u32) {
+        self.music.fade_out(frames);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_music_tempo(&mut self, tempo: u8) {
+        self.music.set_tempo(tempo);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.music.set_volume(volume);
+    }
+
+    #[wasm_bindgen]
+    pub fn is_music_playing(&self) -> bool {
+        self.music.is_playing()
+    }
+
+    // Run the music driver for one video frame and apply its output
+    #[wasm_bindgen]
+    pub fn step_music_frame(&mut self) {
+        if !self.music.step_frame() {
+            // Let notes still sounding fade out through their release
+            self.release_music_voices();
+            return;
+        }
+        for (osc, output) in self.music_voices.iter_mut().zip(self.music.channel_outputs()) {
+            if let Some(instrument) = output.trigger {
+                Self::set_sid_waveform(osc, instrument.waveform);
+                osc.pulse_width = instrument.pulse_width();
+                osc.envelope = instrument.envelope();
+                osc.envelope.trigger();
+                osc.enabled = true;
+            }
+            if output.release {
+                osc.envelope.release();
+            }
+            osc.frequency = output.frequency;
+            osc.volume = output.volume;
+            osc.pan = output.pan;
+        }
+    }
+
+    // Wavetable voice API. Tables hold 32 or 64 signed 8-bit samples.
+    #[wasm_bindgen]
+    pub fn write_wavetable(&mut self, table: u8, samples: &[u8]) -> bool {
+        let samples: Vec<i8> = samples.iter().map(|&sample| sample as i8).collect();
+        self.wavetable.wave_ram.write_table(table as usize, &samples)
+    }
+
+    #[wasm_bindgen]
+    pub fn wavetable_voice_play(&mut self, voice: u8, table: u8, frequency: f32, volume: f32, pan: f32) {
+        if let Some(voice) = self.wavetable.voice_mut(voice as usize) {
+            voice.start(table as usize, frequency, volume, pan);
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn wavetable_voice_play_note(&mut self, voice: u8, table: u8, note: u8, volume: f32, pan: f32) {
+        self.wavetable_voice_play(voice, table, Self::midi_to_frequency(note), volume, pan);
+    }
+
+    #[wasm_bindgen]
+    pub fn wavetable_voice_set_frequency(&mut self, voice: u8, frequency: f32) {
+        if let Some(voice) = self.wavetable.voice_mut(voice as usize) {
+            voice.frequency = frequency;
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn wavetable_voice_set_volume(&mut self, voice: u8, volume: f32) {
+        if let Some(voice) = self.wavetable.voice_mut(voice as usize) {
+            voice.volume = volume.clamp(0.0, 1.0);
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn wavetable_voice_set_pan(&mut self, voice: u8, pan: f32) {
+        if let Some(voice) = self.wavetable.voice_mut(voice as usize) {
+            voice.pan = pan.clamp(-1.0, 1.0);
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn wavetable_voice_stop(&mut self, voice: u8) {
+        if let Some(voice) = self.wavetable.voice_mut(voice as usize) {
+            voice.enabled = false;
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn wavetable_stop_all(&mut self) {
+        self.wavetable.stop_all();
+    }
+
+    // Cut off straight away, for a new song or cartridge taking the voices
+    fn silence_music_voices(&mut self) {
+        self.music_voices.iter_mut().for_each(|osc| osc.enabled = false);
+    }
+
+    fn release_music_voices(&mut self) {
+        self.music_voices.iter_mut().for_each(|osc| osc.envelope.release());
+    }
+
+    // Unfiltered voice for tracker music (shaped by each note's instrument)
+    // and sound effects
+    fn plain_voice() -> DigitalOscillator {
+        DigitalOscillator {
+            enabled: false,
+            frequency: 440.0,
+            waveform: 0,
+            phase: 0.0,
+            pulse_width: 0.5,
+            volume: 0.0,
+            detune: 0.0,
+            pan: 0.0,
+            lfsr: 0x7FFF,
+            envelope: Adsr::gate(),
+            lfos: [Lfo::OFF; LFOS_PER_VOICE],
+            sid: SidControl::default(),
+            filter: ResonantFilter {
+                enabled: false,
+                filter_type: 0,
+                cutoff: 1.0,
+                resonance: 0.0,
+                x1: 0.0, x2: 0.0,
+                y1: 0.0, y2: 0.0,
+                a0: 1.0, a1: 0.0, a2: 0.0,
+                b1: 0.0, b2: 0.0,
+            },
+            delay: DigitalDelay {
+                enabled: false,
+                delay_time: 0.0,
+                feedback: 0.0,
+                mix: 0.0,
+                width: 0.0,
+                buffer: Vec::new(), // Delay stays off
+                buffer_size: 0,
+                write_pos: 0,
+                read_pos: 0,
+                feedback_filter: 0.0,
+            },
+        }
+    }
+
+    // Waveforms 0-4 are the oscillator's own (pulse, saw, triangle, sine,
+    // noise); SID waveform bits ($10-$F0) select combined waveforms instead
+    fn set_sid_waveform(osc: &mut DigitalOscillator, waveform: u8) {
+        if waveform & SID_WAVEFORMS != 0 {
+            osc.sid.waveform_mask = waveform & SID_WAVEFORMS;
+        } else {
+            osc.sid.waveform_mask = 0;
+            osc.waveform = waveform.clamp(0, 4);
+        }
+    }
+
+    // Each voice is synced and ring modulated by the previous one, as on
+    // the real chip (voice 1 by voice 3), whether or not that voice is heard.
+    // Uses last sample's state.
+    fn route_sid_modulators(&mut self) {
+        let state = |osc: &DigitalOscillator| (osc.phase, osc.sid.wrapped);
+        let (phase3, wrapped3) = state(&self.sid_voice3);
+        let (phase1, wrapped1) = state(&self.sid_voice1);
+        let (phase2, wrapped2) = state(&self.sid_voice2);
+        (self.sid_voice1.sid.modulator_phase, self.sid_voice1.sid.modulator_wrapped) = (phase3, wrapped3);
+        (self.sid_voice2.sid.modulator_phase, self.sid_voice2.sid.modulator_wrapped) = (phase1, wrapped1);
+        (self.sid_voice3.sid.modulator_phase, self.sid_voice3.sid.modulator_wrapped) = (phase2, wrapped2);
+    }
+
+    // Apply the shared SID filter settings to all three voices
+    fn update_sid_filter(&mut self) {
+        let (cutoff, resonance) = if self.sid_filter_6581 {
+            (sid::cutoff_6581(self.sid_filter_cutoff), self.sid_filter_resonance * 0.6)
+        } else {
+            (self.sid_filter_cutoff, self.sid_filter_resonance)
+        };
+        let normalized_cutoff = (cutoff / (self.sample_rate * 0.5)).min(1.0);
+        for osc in [&mut self.sid_voice1, &mut self.sid_voice2, &mut self.sid_voice3] {
+            osc.filter.cutoff = normalized_cutoff;
+            osc.filter.resonance = resonance;
+            Self::update_filter_coefficients(&mut osc.filter, self.sample_rate);
+        }
+    }
+
+    fn sid_voice_mut(&mut self, voice: u8) -> Option<&mut DigitalOscillator> {
+        match voice {
+            1 => Some(&mut self.sid_voice1),
+            2 => Some(&mut self.sid_voice2),
+            3 => Some(&mut self.sid_voice3),
+            _ => None,
+        }
+    }
+
     // Helper method to check if any SID voices are active
     fn check_sid_enabled(&mut self) {
         self.sid_enabled = self.sid_voice1.enabled || self.sid_voice2.enabled || self.sid_voice3.enabled;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sid::SID_SAWTOOTH;
+
+    // Output changes over one 60 Hz frame
+    fn frame_changes(apu: &mut Apu) -> usize {
+        let samples: Vec<f32> = (0..735).map(|_| apu.generate_sample()).collect();
+        samples.windows(2).filter(|pair| pair[0] != pair[1]).count()
+    }
+
+    #[test]
+    fn test_noise_pitch_follows_slide() {
+        let mut apu = Apu::new();
+        // Noise from middle C, sliding up two semitones a frame for a second
+        let effect = [4, 60, 32, 0, 0, 0, 0, 0, 128, 0, 0, 60, 0, 0, 255, 0];
+        assert!(apu.play_sfx_definition(&effect));
+        let low = frame_changes(&mut apu);
+        for _ in 0..12 {
+            apu.step_sfx_frame();
+        }
+
+        // Two octaves up the register is stepped four times as often
+        let high = frame_changes(&mut apu);
+        assert!(low > 0 && high > low * 3, "{} -> {}", low, high);
+    }
+
+    #[test]
+    fn test_register_channel_pan_and_stereo_interleave() {
+        let mut apu = Apu::new();
+        apu.set_channel_pan(0, -1.0);
+        apu.set_channel_pan(2, -1.0); // Idle triangle holds a DC level
+        apu.write_register(0x4015, 0x01);
+        apu.write_register(0x4000, 0xBF); // 50% duty, constant volume 15
+        apu.write_register(0x4002, 0xFD);
+        apu.write_register(0x4003, 0x08);
+
+        // Left/right pairs: pulse 1 hard left leaves the right side silent
+        let samples = apu.generate_stereo_samples(100);
+        assert_eq!(samples.len(), 200);
+        assert!(samples.iter().skip(1).step_by(2).all(|&right| right == 0.0));
+        let left: Vec<f32> = samples.iter().step_by(2).copied().collect();
+        assert!(left.iter().any(|&level| level > 0.0) && left.iter().any(|&level| level < 0.0));
+    }
+
+    #[test]
+    fn test_sid_gate_on_after_stop_all() {
+        let mut apu = Apu::new();
+        apu.sid_voice1_play_note(60, 1);
+        apu.sid_stop_all();
+        assert!(!apu.sid_voice1.sid.gate && apu.sid_voice1.envelope.is_finished());
+
+        // A gate bit is a rising edge again, so the voice sounds
+        apu.sid_set_voice_control(1, SID_SAWTOOTH | SID_GATE);
+        apu.generate_stereo_samples(100);
+        assert!(apu.sid_voice1.enabled && apu.sid_voice1.envelope.is_held());
+    }
+
+    #[test]
+    fn test_silent_sid_voice_still_syncs() {
+        let mut apu = Apu::new();
+        apu.sid_voice3.frequency = 1000.0; // Never gated, never heard
+        apu.sid_voice1_play_note(45, 1);   // 110 Hz saw, synced to voice 3
+        apu.sid_set_voice_control(1, SID_SAWTOOTH | SID_SYNC | SID_GATE);
+
+        let mut highest = 0.0f32;
+        for _ in 0..2000 {
+            apu.generate_sample();
+            highest = highest.max(apu.sid_voice1.phase);
+        }
+        assert!(!apu.sid_voice3.enabled);
+        assert!(highest < 0.2, "{}", highest);
+    }
+
+    #[test]
+    fn test_delay_width() {
+        let mut apu = Apu::new();
+        apu.set_delay_width(2.0);
+        assert_eq!(apu.get_delay_width(), 1.0);
+        apu.enter_sound_test_mode();
+        apu.set_delay_enabled(true);
+        apu.set_delay_mix(1.0);
+
+        // Half a second of delay leaves room to read the right side further back
+        apu.set_delay_time(0.5);
+        let samples = apu.generate_stereo_samples(30_000);
+        assert!(samples.chunks(2).any(|pair| pair[0] != pair[1]));
+
+        // At the full buffer length there is none, so both sides match
+        // rather than the right reading samples not yet delayed
+        let mut apu = Apu::new();
+        apu.enter_sound_test_mode();
+        apu.set_delay_enabled(true);
+        apu.set_delay_mix(1.0);
+        apu.set_delay_time(1.0);
+        apu.set_delay_width(1.0);
+        let samples = apu.generate_stereo_samples(50_000);
+        assert!(samples.chunks(2).all(|pair| pair[0] == pair[1]));
+    }
+}
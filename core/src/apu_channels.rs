@@ -0,0 +1,478 @@
+// Register-driven chiptune channels: two pulse channels, a triangle and a
+// noise channel, each programmed through four registers. A frame sequencer
+// clocks the volume envelopes and the triangle's linear counter every quarter
+// frame, and the length counters and pulse sweeps every half frame, so notes
+// shape and end themselves without the game touching them again.
+//
+// Registers (offset within the channel's block):
+//   Pulse 1 $4000-$4003, pulse 2 $4004-$4007
+//     0: DDLC VVVV  duty, length halt / envelope loop, constant volume, volume or envelope period
+//     1: EPPP NSSS  sweep enable, period, negate, shift
+//     2: TTTT TTTT  timer low
+//     3: LLLL LTTT  length load, timer high (restarts the envelope)
+//   Triangle $4008-$400B
+//     0: CRRR RRRR  length halt / linear control, linear counter reload
+//     2: TTTT TTTT  timer low
+//     3: LLLL LTTT  length load, timer high (reloads the linear counter)
+//   Noise $400C-$400F
+//     0: --LC VVVV  as pulse
+//     2: M--- PPPP  short (93-step) mode, period index
+//     3: LLLL L---  length load (restarts the envelope)
+//   $4015 write: ---- NT21 channel enables (disabling clears the length counter)
+//   $4015 read:  ---- NT21 channels whose length counter is running
+//   $4017 write: M--- ----  sequencer mode, 0 = 4-step, 1 = 5-step
+
+pub const CPU_CLOCK: f32 = 1_789_773.0;
+
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14,
+    12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+// Noise timer periods in CPU cycles
+const NOISE_PERIODS: [u16; 16] = [4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068];
+
+const DUTY_CYCLES: [f32; 4] = [0.125, 0.25, 0.5, 0.75];
+
+// Volume envelope: a constant volume, or a decay from 15 to 0 (optionally
+// looping) stepping every period + 1 quarter frames
+#[derive(Default)]
+struct Envelope {
+    start: bool,
+    looping: bool,
+    constant: bool,
+    period: u8, // Doubles as the constant volume
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    fn write(&mut self, value: u8) {
+        self.looping = value & 0x20 != 0;
+        self.constant = value & 0x10 != 0;
+        self.period = value & 0x0F;
+    }
+
+    fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.period;
+        } else if self.divider == 0 {
+            self.divider = self.period;
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.looping {
+                self.decay = 15;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    fn volume(&self) -> u8 {
+        if self.constant {
+            self.period
+        } else {
+            self.decay
+        }
+    }
+}
+
+// Counts a note's length down in half frames; the channel is silent at 0
+#[derive(Default)]
+struct LengthCounter {
+    enabled: bool,
+    halt: bool,
+    value: u8,
+}
+
+impl LengthCounter {
+    fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.value = LENGTH_TABLE[(index & 0x1F) as usize];
+        }
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.value = 0;
+        }
+    }
+
+    fn clock(&mut self) {
+        if !self.halt && self.value > 0 {
+            self.value -= 1;
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.value > 0
+    }
+}
+
+// Pitch sweep: every period + 1 half frames the timer moves by timer >> shift
+#[derive(Default)]
+struct Sweep {
+    enabled: bool,
+    period: u8,
+    negate: bool,
+    shift: u8,
+    divider: u8,
+    reload: bool,
+    ones_complement: bool, // Pulse 1 subtracts one more when negating
+}
+
+impl Sweep {
+    fn write(&mut self, value: u8) {
+        self.enabled = value & 0x80 != 0;
+        self.period = (value >> 4) & 7;
+        self.negate = value & 0x08 != 0;
+        self.shift = value & 7;
+        self.reload = true;
+    }
+
+    fn target(&self, timer: u16) -> u16 {
+        let change = timer >> self.shift;
+        if self.negate {
+            timer.saturating_sub(change + self.ones_complement as u16)
+        } else {
+            timer + change
+        }
+    }
+
+    // Returns the (possibly swept) timer period
+    fn clock(&mut self, timer: u16) -> u16 {
+        let target = self.target(timer);
+        let swept = if self.divider == 0 && self.enabled && self.shift > 0 && timer >= 8 && target <= 0x7FF {
+            target
+        } else {
+            timer
+        };
+
+        if self.divider == 0 || self.reload {
+            self.divider = self.period;
+            self.reload = false;
+        } else {
+            self.divider -= 1;
+        }
+        swept
+    }
+}
+
+pub struct PulseChannel {
+    duty: u8,
+    envelope: Envelope,
+    sweep: Sweep,
+    length: LengthCounter,
+    timer: u16, // 11-bit period, frequency = CPU clock / (16 * (timer + 1))
+    phase: f32,
+}
+
+impl PulseChannel {
+    pub fn new(ones_complement_sweep: bool) -> PulseChannel {
+        PulseChannel {
+            duty: 0,
+            envelope: Envelope::default(),
+            sweep: Sweep { ones_complement: ones_complement_sweep, ..Sweep::default() },
+            length: LengthCounter::default(),
+            timer: 0,
+            phase: 0.0,
+        }
+    }
+
+    pub fn write(&mut self, register: u8, value: u8) {
+        match register & 3 {
+            0 => {
+                self.duty = value >> 6;
+                self.length.halt = value & 0x20 != 0;
+                self.envelope.write(value);
+            }
+            1 => self.sweep.write(value),
+            2 => self.timer = (self.timer & 0x700) | value as u16,
+            _ => {
+                self.timer = (self.timer & 0xFF) | ((value as u16 & 7) << 8);
+                self.length.load(value >> 3);
+                self.envelope.start = true;
+                self.phase = 0.0;
+            }
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length.set_enabled(enabled);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.length.is_active()
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+        self.timer = self.sweep.clock(self.timer);
+    }
+
+    // Silent once the length runs out, and muted when the pitch is too high
+    // or the sweep target is out of range (even with the sweep disabled)
+    fn is_muted(&self) -> bool {
+        !self.length.is_active() || self.timer < 8 || self.sweep.target(self.timer) > 0x7FF
+    }
+
+    pub fn sample(&mut self, sample_rate: f32) -> f32 {
+        if self.is_muted() {
+            return 0.0;
+        }
+        let frequency = CPU_CLOCK / (16.0 * (self.timer as f32 + 1.0));
+        self.phase = (self.phase + frequency / sample_rate).fract();
+        let level = if self.phase < DUTY_CYCLES[self.duty as usize] { 1.0 } else { -1.0 };
+        level * self.envelope.volume() as f32 / 15.0
+    }
+}
+
+pub struct TriangleChannel {
+    control: bool, // Halts the length counter and keeps reloading the linear counter
+    linear_reload_value: u8,
+    linear_counter: u8,
+    linear_reload: bool,
+    length: LengthCounter,
+    timer: u16, // Frequency = CPU clock / (32 * (timer + 1))
+    phase: f32,
+}
+
+impl TriangleChannel {
+    pub fn new() -> TriangleChannel {
+        TriangleChannel {
+            control: false,
+            linear_reload_value: 0,
+            linear_counter: 0,
+            linear_reload: false,
+            length: LengthCounter::default(),
+            timer: 0,
+            phase: 0.0,
+        }
+    }
+
+    pub fn write(&mut self, register: u8, value: u8) {
+        match register & 3 {
+            0 => {
+                self.control = value & 0x80 != 0;
+                self.length.halt = self.control;
+                self.linear_reload_value = value & 0x7F;
+            }
+            1 => {} // Unused
+            2 => self.timer = (self.timer & 0x700) | value as u16,
+            _ => {
+                self.timer = (self.timer & 0xFF) | ((value as u16 & 7) << 8);
+                self.length.load(value >> 3);
+                self.linear_reload = true;
+            }
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length.set_enabled(enabled);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.length.is_active()
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+    }
+
+    // No volume control: when silenced the waveform stops where it is
+    // instead of dropping to zero, so notes end without a click
+    pub fn sample(&mut self, sample_rate: f32) -> f32 {
+        if self.length.is_active() && self.linear_counter > 0 && self.timer >= 2 {
+            let frequency = CPU_CLOCK / (32.0 * (self.timer as f32 + 1.0));
+            self.phase = (self.phase + frequency / sample_rate).fract();
+        }
+        let level = if self.phase < 0.5 {
+            4.0 * self.phase - 1.0
+        } else {
+            3.0 - 4.0 * self.phase
+        };
+        level * 0.5 // Triangle is quieter than pulse
+    }
+}
+
+pub struct NoiseChannel {
+    envelope: Envelope,
+    length: LengthCounter,
+    short_mode: bool, // Feedback from bit 6: a metallic 93-step loop
+    period: u16,
+    shift_register: u16,
+    clock_phase: f32, // Fractional shift register clocks carried between samples
+}
+
+impl NoiseChannel {
+    pub fn new() -> NoiseChannel {
+        NoiseChannel {
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+            short_mode: false,
+            period: NOISE_PERIODS[0],
+            shift_register: 1,
+            clock_phase: 0.0,
+        }
+    }
+
+    pub fn write(&mut self, register: u8, value: u8) {
+        match register & 3 {
+            0 => {
+                self.length.halt = value & 0x20 != 0;
+                self.envelope.write(value);
+            }
+            1 => {} // Unused
+            2 => {
+                self.short_mode = value & 0x80 != 0;
+                self.period = NOISE_PERIODS[(value & 0x0F) as usize];
+            }
+            _ => {
+                self.length.load(value >> 3);
+                self.envelope.start = true;
+            }
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length.set_enabled(enabled);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.length.is_active()
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+    }
+
+    fn clock_shift_register(&mut self) {
+        let tap = if self.short_mode { 6 } else { 1 };
+        let feedback = (self.shift_register ^ (self.shift_register >> tap)) & 1;
+        self.shift_register = (self.shift_register >> 1) | (feedback << 14);
+    }
+
+    pub fn sample(&mut self, sample_rate: f32) -> f32 {
+        self.clock_phase += CPU_CLOCK / (self.period as f32 * sample_rate);
+        while self.clock_phase >= 1.0 {
+            self.clock_phase -= 1.0;
+            self.clock_shift_register();
+        }
+
+        if !self.length.is_active() {
+            return 0.0;
+        }
+        let level = if self.shift_register & 1 == 0 { 1.0 } else { -1.0 };
+        level * self.envelope.volume() as f32 / 15.0 * 0.5
+    }
+}
+
+// Frame sequencer, clocked at 240 Hz. 4-step mode clocks envelopes every step
+// and lengths/sweeps every second step; 5-step mode leaves out the fourth
+// step, giving a slightly slower 192 Hz / 96 Hz rhythm.
+#[derive(Default)]
+pub struct FrameSequencer {
+    five_step: bool,
+    step: u8,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SequencerClocks {
+    pub quarter_frame: bool, // Envelopes and the triangle's linear counter
+    pub half_frame: bool,    // Length counters and sweeps
+}
+
+impl FrameSequencer {
+    // $4017 write. Returns the clocks to run right away: 5-step mode
+    // clocks everything immediately.
+    pub fn write(&mut self, value: u8) -> SequencerClocks {
+        self.five_step = value & 0x80 != 0;
+        self.step = 0;
+        SequencerClocks { quarter_frame: self.five_step, half_frame: self.five_step }
+    }
+
+    pub fn clock(&mut self) -> SequencerClocks {
+        let step = self.step;
+        self.step = (self.step + 1) % if self.five_step { 5 } else { 4 };
+
+        let (quarter_frame, half_frame) = match (self.five_step, step) {
+            (_, 0) | (_, 2) => (true, false),
+            (false, 1) | (false, 3) | (true, 1) | (true, 4) => (true, true),
+            _ => (false, false), // 5-step mode's idle step
+        };
+        SequencerClocks { quarter_frame, half_frame }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_and_length_counter() {
+        let mut pulse = PulseChannel::new(true);
+        pulse.set_enabled(true);
+        pulse.write(0, 0x80 | 0x01); // 50% duty, decaying envelope with period 1
+        pulse.write(2, 0xFD);
+        pulse.write(3, 0x00); // Length index 0 = 10 half frames
+        assert!(pulse.is_playing());
+
+        let mut sequencer = FrameSequencer::default();
+        for _ in 0..4 {
+            let clocks = sequencer.clock();
+            if clocks.quarter_frame {
+                pulse.clock_quarter_frame();
+            }
+            if clocks.half_frame {
+                pulse.clock_half_frame();
+            }
+        }
+        // Start, then one decay step every two quarter frames
+        assert_eq!(pulse.envelope.volume(), 14);
+        assert_eq!(pulse.length.value, 8);
+
+        for _ in 0..8 {
+            pulse.clock_half_frame();
+        }
+        assert!(!pulse.is_playing());
+        assert_eq!(pulse.sample(44100.0), 0.0);
+    }
+
+    #[test]
+    fn test_sweep_moves_pitch_and_mutes() {
+        let mut pulse = PulseChannel::new(true);
+        pulse.set_enabled(true);
+        pulse.write(1, 0x80 | 0x09); // Enabled, period 0, negate, shift 1
+        pulse.write(2, 0x00);
+        pulse.write(3, 0x09); // Timer 0x100
+        pulse.clock_half_frame();
+        assert_eq!(pulse.timer, 0x100 - 0x80 - 1); // Pulse 1 negates with one's complement
+
+        // An upward sweep target past 0x7FF mutes the channel
+        pulse.write(1, 0x81);
+        pulse.write(3, 0x0E); // Timer 0x6xx
+        assert!(pulse.is_muted());
+    }
+}
@@ -1,7 +1,25 @@
 use wasm_bindgen::prelude::*;
-use std::collections::HashMap;
-
-// Sound effect IDs for the Hambert game
+use std::collections::{HashMap, VecDeque};
+use crate::sprite_bank::{SpriteBank, SpritePattern};
+use crate::color_math::BlendMode;
+use crate::hambert_sprites::{
+    hambert_animations, hambert_sprite_bank, hambert_sprite_palettes, heart_hud_tiles, heart_tile_animations, BLOOD_GOBLIN_HOP,
+    BLOOD_GOBLIN_PALETTE, BLOOD_GOBLIN_SPRITE, EVENT_HOP_LEFT, EVENT_HOP_RIGHT, FLASH_RED_PALETTE, FLASH_WHITE_PALETTE,
+    HAMBERRY_COLLECT, HAMBERRY_PALETTE, HAMBERRY_PULSE, HAMBERRY_SPRITE, HAMBERT_PALETTE, HAMBERT_PORTRAIT_SPRITE, HEART_COLOR,
+    HEXAGNOME_PALETTE, HEXAGNOME_VARIANT_PALETTE, PROPS_PALETTE,
+};
+use crate::animation::{AnimationLibrary, AnimationPlayer};
+use crate::metasprite::{MetaSprite, MetaSpritePiece};
+use crate::hud::{HudCell, HudLayer, HudTile};
+use crate::tile_animation::TileAnimation;
+use crate::transition::{Transition, TransitionDirection, TransitionKind};
+use crate::hambert_music::{hambert_level_theme, HAMBERT_LEVEL_THEME};
+use crate::hambert_sfx::hambert_sound_effects;
+use crate::laugh_sample::{LAUGH_SAMPLE_RETRO_SAMPLE_DATA, LAUGH_SAMPLE_RETRO_SAMPLE_RATE};
+
+// Sound effect IDs for the Hambert game. 0-5 index the sound effect table
+// (hambert_sfx.rs); 6-8 are a voice clip and voice effects, mapped by
+// HambertCartridge::sound_source.
 #[derive(Clone, Copy)]
 pub enum SoundEffect {
     Jump = 0,
@@ -15,6 +33,15 @@ pub enum SoundEffect {
     Grunt = 8,
 }
 
+// What the console plays for a cartridge's sound ID. Each cartridge maps its
+// own IDs; without a mapping every ID is a sound effect table entry.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SoundSource {
+    Table(u32),                          // Entry in the cartridge's .zsx table
+    Sample { sample_id: u32, rate: f32 }, // PCM sample at a playback rate
+    VoiceEffect(u8),                     // APU voice effect (0 laughter, 1 gasp, 2 grunt)
+}
+
 // Audio commands that cartridges can send to the console
 pub trait AudioCommands {
     fn play_sound_effect(&mut self, sound_id: u32);
@@ -23,6 +50,21 @@ pub trait AudioCommands {
     fn set_music_volume(&mut self, volume: f32);
 }
 
+// Music requests queued by a cartridge for the console to carry out. IDs
+// are positions in the cartridge's music track list.
+#[derive(Clone, Copy)]
+pub enum MusicCommand {
+    Play(u32),
+    Stop,
+    SetVolume(f32),
+}
+
+const MUSIC_VOLUME: f32 = 0.5;
+const MUSIC_DUCKED_VOLUME: f32 = 0.2; // Under the interlude and ending screens
+
+pub const HAMBERT_LAUGH_SAMPLE: u32 = 0; // Sample ID (first sample loaded)
+const STARTING_LIVES: u32 = 3;
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum EntityType {
     Player,
@@ -44,16 +86,18 @@ pub struct Entity {
     pub height: f32,
     pub vel_x: f32,
     pub vel_y: f32,
-    pub sprite_id: u32,
+    pub sprite_id: u32,           // Pattern drawn when no animation clip is playing
     pub active: bool,
     pub on_ground: bool,
     pub health: i32,
-    pub animation_frame: u32,
-    pub animation_timer: f32,
+    pub animation: AnimationPlayer,
+    pub pending_events: VecDeque<u32>, // Clip events waiting until the entity can act on them
     pub is_dying: bool,
     pub death_timer: f32,
     pub death_flash_timer: f32,
     pub facing_left: bool,
+    pub palette: Option<u8>, // Sprite sub-palette, swapped for enemy variants
+    pub rotation: f32,       // Sprite rotation in radians (drawn with the PPU's affine sprites)
 }
 
 impl Entity {
@@ -87,12 +131,20 @@ impl Entity {
                 EntityType::BloodGoblin => 1,
                 _ => 1,
             },
-            animation_frame: 0,
-            animation_timer: 0.0,
+            animation: AnimationPlayer::new(),
+            pending_events: VecDeque::new(),
             is_dying: false,
             death_timer: 0.0,
             death_flash_timer: 0.0,
             facing_left: false,
+            palette: Some(match entity_type {
+                EntityType::Player => HAMBERT_PALETTE,
+                EntityType::Hexagnome => HEXAGNOME_PALETTE,
+                EntityType::BloodGoblin => BLOOD_GOBLIN_PALETTE,
+                EntityType::Collectible => HAMBERRY_PALETTE,
+                EntityType::Enemy | EntityType::Platform | EntityType::Projectile | EntityType::Shuriken => PROPS_PALETTE,
+            }),
+            rotation: 0.0,
         }
     }
 }
@@ -136,6 +188,21 @@ pub enum GameState {
     GameOver,
 }
 
+// Scene changes wait for the screen to be covered by a transition
+#[derive(Clone, Copy, PartialEq)]
+enum SceneChange {
+    StartPlaying,  // Intro finished
+    LostLife,      // Death animation finished
+    LevelComplete, // On to the interlude, or game over after the last level
+    NextLevel,     // Interlude finished
+    Restart,       // From game over
+}
+
+// Transition IDs the Hambert cartridge starts
+const TRANSITION_SCENE_OUT: u32 = 0;
+const TRANSITION_SCENE_IN: u32 = 1;
+const SCENE_TRANSITION_FRAMES: u32 = 30;
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum LevelType {
     Platformer,
@@ -167,6 +234,13 @@ pub struct HambertCartridge {
     pending_shuriken: Vec<Entity>,
     frame_count: u64,
     pending_sounds: Vec<SoundEffect>,
+    pending_music: Vec<MusicCommand>,
+    pending_apu_writes: Vec<(u16, u8)>, // APU register writes, applied in order
+    animations: AnimationLibrary,
+    pending_scene: Option<(SceneChange, TransitionKind)>, // Applied when the Out transition finishes
+    pending_transition: Option<Transition>, // Picked up by the console
+    screen_width: f32, // Active video mode, set by the console
+    screen_height: f32,
 
     // Game state management
     game_state: GameState,
@@ -198,13 +272,20 @@ impl HambertCartridge {
             pending_shuriken: Vec::new(),
             frame_count: 0,
             pending_sounds: Vec::new(),
+            pending_music: Vec::new(),
+            pending_apu_writes: Vec::new(),
+            animations: AnimationLibrary::from_bytes(&hambert_animations()).unwrap_or_default(),
+            pending_scene: None,
+            pending_transition: None,
+            screen_width: 320.0,
+            screen_height: 240.0,
 
             // Start with intro screen
             game_state: GameState::Intro,
             text_timer: 0.0,
             text_index: 0,
             current_level: 1,
-            lives: 3,
+            lives: STARTING_LIVES,
             score: 0,
             invulnerability_timer: 0.0,
             
@@ -245,10 +326,19 @@ impl HambertCartridge {
 
         // Add some hexagnome enemies - sprite ID 3
         // Place hexagnomes on platforms and ground level
-        self.entities.push(Entity::new(EntityType::Hexagnome, 300.0, 150.0, 3)); // On first platform
-        self.entities.push(Entity::new(EntityType::Hexagnome, 500.0, 168.0, 3)); // On ground
-        self.entities.push(Entity::new(EntityType::Hexagnome, 900.0, 98.0, 3));  // On high platform
-        self.entities.push(Entity::new(EntityType::Hexagnome, 1300.0, 88.0, 3)); // On higher platform
+        // Every other one wears the rust-red variant palette
+        for (i, (x, y)) in [
+            (300.0, 150.0), // On first platform
+            (500.0, 168.0), // On ground
+            (900.0, 98.0),  // On high platform
+            (1300.0, 88.0), // On higher platform
+        ].into_iter().enumerate() {
+            let mut hexagnome = Entity::new(EntityType::Hexagnome, x, y, 3);
+            if i % 2 == 1 {
+                hexagnome.palette = Some(HEXAGNOME_VARIANT_PALETTE);
+            }
+            self.entities.push(hexagnome);
+        }
     }
 
     fn init_levels(&mut self) {
@@ -304,6 +394,9 @@ impl HambertCartridge {
                 // Handle input
                 self.handle_input(up, down, left, right);
 
+                // Advance animation clips and react to their frame events
+                self.update_animations();
+
                 // Update physics
                 self.update_physics();
 
@@ -329,7 +422,7 @@ impl HambertCartridge {
                 // Handle game over state - restart or quit options
                 if up {
                     // Restart game
-                    self.restart_game();
+                    self.change_scene(SceneChange::Restart, TransitionKind::Fade);
                 }
             },
         }
@@ -362,6 +455,40 @@ impl HambertCartridge {
         player.vel_x = player.vel_x.max(-4.0).min(4.0);  // Increased from 2.8 for faster walking
     }
 
+    fn update_animations(&mut self) {
+        for entity in &mut self.entities {
+            if !entity.active {
+                continue;
+            }
+
+            entity.pending_events.extend(entity.animation.tick(&self.animations));
+            if entity.is_dying {
+                entity.pending_events.clear();
+            }
+
+            // Events wait at the front of the queue until they can happen
+            while let Some(&event) = entity.pending_events.front() {
+                match event {
+                    // Blood goblin hops are timed by its clip, and one that
+                    // comes due in mid-air happens on landing
+                    EVENT_HOP_LEFT | EVENT_HOP_RIGHT if !entity.on_ground => break,
+                    EVENT_HOP_LEFT | EVENT_HOP_RIGHT => {
+                        entity.vel_y = -2.5; // Lower hop than before (was -4.5)
+                        entity.vel_x = if event == EVENT_HOP_LEFT { -0.8 } else { 0.8 };
+                        entity.on_ground = false;
+                    }
+                    _ => {}
+                }
+                entity.pending_events.pop_front();
+            }
+
+            // Collected hamberries are removed once their animation ends
+            if entity.entity_type == EntityType::Collectible && entity.is_dying && entity.animation.is_finished() {
+                entity.active = false;
+            }
+        }
+    }
+
     fn update_physics(&mut self) {
         const GRAVITY: f32 = 0.05;  // Very slow gravity for floaty jumps
         const MAX_FALL_SPEED: f32 = 2.0;  // Very slow terminal velocity
@@ -386,11 +513,15 @@ impl HambertCartridge {
                         entity.death_flash_timer += 1.0;
                         
                         // Death animation lasts 180 frames (3 seconds) to show falling through floor
-                        if entity.death_timer >= 180.0 {
-                            // Death animation complete - reset game
-                            self.game_state = GameState::Intro;
-                            self.lives = 3; // Reset lives for next game
-                            self.init_world(); // Reset world
+                        if entity.death_timer >= 180.0 && self.pending_scene.is_none() {
+                            // Death animation complete - close in on the player, then reset
+                            let center_x = entity.x - self.camera_x + entity.width / 2.0;
+                            let center_y = entity.y - self.camera_y + entity.height / 2.0;
+                            self.pending_scene = Some((SceneChange::LostLife, TransitionKind::Iris));
+                            self.pending_transition = Some(
+                                Transition::new(TRANSITION_SCENE_OUT, TransitionKind::Iris, TransitionDirection::Out, SCENE_TRANSITION_FRAMES)
+                                    .with_center(center_x, center_y),
+                            );
                             return; // Exit early to avoid processing the rest
                         }
                         
@@ -402,25 +533,6 @@ impl HambertCartridge {
                         // No friction when dying (ragdoll effect)
                         entity.x = entity.x.max(0.0).min(self.world_width - entity.width);
                     } else {
-                        // Special hopping behavior for BloodGoblin
-                        if entity.entity_type == EntityType::BloodGoblin {
-                            entity.animation_timer += 1.0;
-                            
-                            // Hop every 120 frames (2 seconds at 60fps) - slower hopping
-                            if entity.on_ground && entity.animation_timer >= 120.0 {
-                                entity.vel_y = -2.5; // Lower hop than before (was -4.5)
-                                entity.on_ground = false;
-                                entity.animation_timer = 0.0;
-                                
-                                // Add random horizontal movement (left to right)
-                                let hop_direction = if (entity.animation_frame % 4) < 2 { -0.8 } else { 0.8 };
-                                entity.vel_x = hop_direction;
-                                
-                                // Increment animation frame for next hop direction
-                                entity.animation_frame = (entity.animation_frame + 1) % 4;
-                            }
-                        }
-                        
                         // Normal physics
                         // Apply gravity
                         if !entity.on_ground {
@@ -455,12 +567,9 @@ impl HambertCartridge {
                     entity.y += entity.vel_y;
                     entity.vel_y += 0.005; // Minimal gravity to maintain range at slow speed
 
-                    // Rotate animation
-                    entity.animation_timer += 1.0;
-                    if entity.animation_timer >= 8.0 {
-                        entity.animation_frame = (entity.animation_frame + 1) % 4;
-                        entity.animation_timer = 0.0;
-                    }
+                    // Spin in the direction of travel
+                    let spin = if entity.vel_x < 0.0 { -0.25 } else { 0.25 };
+                    entity.rotation = (entity.rotation + spin) % std::f32::consts::TAU;
 
                     // Remove if off screen
                     if entity.x < -50.0 || entity.x > self.world_width + 50.0 || entity.y > self.world_height + 50.0 {
@@ -622,7 +731,7 @@ impl HambertCartridge {
 
         // Check collisions with collectibles (hamberries) - with spatial optimization
         for (i, entity) in self.entities.iter().enumerate() {
-            if !entity.active || entity.entity_type != EntityType::Collectible {
+            if !entity.active || entity.is_dying || entity.entity_type != EntityType::Collectible {
                 continue;
             }
 
@@ -652,7 +761,8 @@ impl HambertCartridge {
 
         // Collect hamberries and increase score
         for index in collected_indices {
-            self.entities[index].active = false;
+            self.entities[index].is_dying = true;
+            self.entities[index].animation.play(HAMBERRY_COLLECT);
             self.score += 10; // 10 points per hamberry
             self.pending_sounds.push(SoundEffect::Collect);
         }
@@ -688,7 +798,10 @@ impl HambertCartridge {
         // Only spawn if we have fewer than 3 hexagnomes and it's time to spawn
         if hexagnome_count < 3 && self.frame_count % 600 == 0 {  // Limit to 3 hexagnomes max
             let spawn_x = if self.entities.len() % 2 == 0 { 0.0 } else { self.world_width - 20.0 };
-            let hexagnome = Entity::new(EntityType::Hexagnome, spawn_x, 100.0, 3); // sprite ID 3 for hexagnome
+            let mut hexagnome = Entity::new(EntityType::Hexagnome, spawn_x, 100.0, 3); // sprite ID 3 for hexagnome
+            if spawn_x > 0.0 {
+                hexagnome.palette = Some(HEXAGNOME_VARIANT_PALETTE); // Right-edge spawns are the variant
+            }
             self.entities.push(hexagnome);
         }
 
@@ -755,11 +868,11 @@ impl HambertCartridge {
                     // Spawn blood goblin at the end of the level, on the ground
                     let blood_goblin_x = self.world_width - 100.0; // Near the very end
                     let blood_goblin_y = 168.0 - 38.0; // Ground level - sprite height (38)
-                    let mut blood_goblin = Entity::new(EntityType::BloodGoblin, blood_goblin_x, blood_goblin_y, 7); // sprite ID 7
+                    let mut blood_goblin = Entity::new(EntityType::BloodGoblin, blood_goblin_x, blood_goblin_y, BLOOD_GOBLIN_SPRITE);
                     
                     // Set initial hopping state
                     blood_goblin.vel_y = -3.0; // Start with an upward hop
-                    blood_goblin.animation_timer = 0.0;
+                    blood_goblin.animation.play(BLOOD_GOBLIN_HOP);
                     
                     self.entities.push(blood_goblin);
                     
@@ -796,6 +909,18 @@ impl HambertCartridge {
         }
     }
 
+    // Where the player's sounds sit in the stereo field: follows them across
+    // the screen, but never hard to one side
+    pub fn sound_pan(&self) -> f32 {
+        match self.get_player_position() {
+            Some((player_x, _)) => {
+                let half_width = self.screen_width / 2.0;
+                ((player_x - self.camera_x) / half_width - 1.0).clamp(-1.0, 1.0) * 0.6
+            }
+            None => 0.0,
+        }
+    }
+
     // Public getters for PPU
     pub fn get_camera_x(&self) -> f32 {
         self.camera_x
@@ -820,9 +945,31 @@ impl HambertCartridge {
         js_sys::Reflect::set(&obj, &"x".into(), &entity.x.into()).unwrap();
         js_sys::Reflect::set(&obj, &"y".into(), &entity.y.into()).unwrap();
         js_sys::Reflect::set(&obj, &"sprite_id".into(), &entity.sprite_id.into()).unwrap();
+        if let Some(position) = entity.animation.position() {
+            js_sys::Reflect::set(&obj, &"clip".into(), &position.clip.into()).unwrap();
+            js_sys::Reflect::set(&obj, &"clip_frame".into(), &(position.frame as u32).into()).unwrap();
+        }
         js_sys::Reflect::set(&obj, &"active".into(), &entity.active.into()).unwrap();
         js_sys::Reflect::set(&obj, &"entity_type".into(), &(entity.entity_type as u32).into()).unwrap();
         js_sys::Reflect::set(&obj, &"facing_left".into(), &entity.facing_left.into()).unwrap();
+        if let Some(palette) = entity.palette {
+            js_sys::Reflect::set(&obj, &"palette".into(), &palette.into()).unwrap();
+        }
+        if entity.rotation != 0.0 {
+            js_sys::Reflect::set(&obj, &"rotation".into(), &entity.rotation.into()).unwrap();
+        }
+
+        // Player flashes go through the PPU's color math
+        if index == self.player_id {
+            if self.is_player_dying() {
+                // Alternate white and light red palettes while dying
+                let flash_palette = if self.get_player_death_flash() { FLASH_WHITE_PALETTE } else { FLASH_RED_PALETTE };
+                js_sys::Reflect::set(&obj, &"palette".into(), &flash_palette.into()).unwrap();
+            } else if self.get_player_invul_flash() {
+                // Ghosted at 50% on invulnerability flash frames
+                js_sys::Reflect::set(&obj, &"blend".into(), &(BlendMode::Half as u32).into()).unwrap();
+            }
+        }
 
         Some(obj)
     }
@@ -853,7 +1000,9 @@ impl HambertCartridge {
         ];
 
         for (x, y) in hamberry_positions.iter() {
-            self.entities.push(Entity::new(EntityType::Collectible, *x, *y, 6));
+            let mut hamberry = Entity::new(EntityType::Collectible, *x, *y, HAMBERRY_SPRITE);
+            hamberry.animation.play(HAMBERRY_PULSE);
+            self.entities.push(hamberry);
         }
     }
 
@@ -874,9 +1023,7 @@ impl HambertCartridge {
 
         // Any key to continue to game after text is complete
         if (up || down || left || right) && self.text_index >= intro_text.len() {
-            self.game_state = GameState::Playing;
-            self.text_timer = 0.0;
-            self.text_index = 0;
+            self.change_scene(SceneChange::StartPlaying, TransitionKind::WipeHorizontal);
         }
     }
     
@@ -948,6 +1095,12 @@ impl HambertCartridge {
         self.pending_sounds.clear();
     }
 
+    // Queue a write to an APU register ($4000-$4017) for the console to
+    // apply after this frame's update, the same way as music requests
+    pub fn write_apu_register(&mut self, address: u16, value: u8) {
+        self.pending_apu_writes.push((address, value));
+    }
+
     // Level progression methods
     fn check_level_completion(&mut self) {
         if self.current_level_index >= self.levels.len() {
@@ -987,7 +1140,7 @@ impl HambertCartridge {
         }
 
         if level_complete {
-            self.complete_level();
+            self.change_scene(SceneChange::LevelComplete, TransitionKind::Mosaic);
         }
     }
 
@@ -1006,6 +1159,53 @@ impl HambertCartridge {
         }
     }
 
+    // Cover the screen, then switch scenes once the transition reports back.
+    // Requests while a change is pending are ignored.
+    fn change_scene(&mut self, change: SceneChange, kind: TransitionKind) {
+        if self.pending_scene.is_some() {
+            return;
+        }
+        self.pending_scene = Some((change, kind));
+        self.pending_transition = Some(
+            Transition::new(TRANSITION_SCENE_OUT, kind, TransitionDirection::Out, SCENE_TRANSITION_FRAMES),
+        );
+    }
+
+    fn apply_scene_change(&mut self, change: SceneChange) {
+        match change {
+            SceneChange::StartPlaying => {
+                self.game_state = GameState::Playing;
+                self.text_timer = 0.0;
+                self.text_index = 0;
+                self.play_level_music();
+            }
+            SceneChange::LostLife => {
+                self.game_state = GameState::Intro;
+                self.lives = STARTING_LIVES; // Reset lives for next game
+                self.init_world(); // Reset world
+                self.pending_music.push(MusicCommand::Stop);
+            }
+            SceneChange::LevelComplete => {
+                self.complete_level();
+                self.pending_music.push(MusicCommand::SetVolume(MUSIC_DUCKED_VOLUME));
+            }
+            SceneChange::NextLevel => {
+                self.start_next_level();
+                self.play_level_music();
+            }
+            SceneChange::Restart => {
+                self.restart_game();
+                self.pending_music.push(MusicCommand::Stop);
+            }
+        }
+    }
+
+    // Each level starts the theme over at full volume
+    fn play_level_music(&mut self) {
+        self.pending_music.push(MusicCommand::SetVolume(MUSIC_VOLUME));
+        self.pending_music.push(MusicCommand::Play(HAMBERT_LEVEL_THEME));
+    }
+
     fn start_interlude(&mut self) {
         self.game_state = GameState::Interlude;
         self.text_timer = 0.0;
@@ -1047,7 +1247,7 @@ impl HambertCartridge {
 
             if self.interlude_text_index >= self.interlude_text.len() {
                 // Interlude complete, start next level
-                self.start_next_level();
+                self.change_scene(SceneChange::NextLevel, TransitionKind::Fade);
             }
         }
 
@@ -1055,7 +1255,7 @@ impl HambertCartridge {
         if up || down || left || right {
             self.interlude_text_index += 1;
             if self.interlude_text_index >= self.interlude_text.len() {
-                self.start_next_level();
+                self.change_scene(SceneChange::NextLevel, TransitionKind::Fade);
             }
         }
     }
@@ -1079,7 +1279,9 @@ impl HambertCartridge {
             match level.level_type {
                 LevelType::Boss => {
                     // Spawn boss enemy
-                    self.entities.push(Entity::new(EntityType::BloodGoblin, 1500.0, 100.0, 4));
+                    let mut boss = Entity::new(EntityType::BloodGoblin, 1500.0, 100.0, BLOOD_GOBLIN_SPRITE);
+                    boss.animation.play(BLOOD_GOBLIN_HOP);
+                    self.entities.push(boss);
                 },
                 LevelType::Story => {
                     // Clear entities for story mode
@@ -1096,7 +1298,7 @@ impl HambertCartridge {
         // Reset all game state
         self.game_state = GameState::Intro;
         self.current_level_index = 0;
-        self.lives = 3;
+        self.lives = STARTING_LIVES;
         self.score = 0;
         self.text_timer = 0.0;
         self.text_index = 0;
@@ -1148,6 +1350,121 @@ impl HambertCartridge {
     }
 }
 
+// Console-side interface (not exported to JavaScript)
+impl HambertCartridge {
+    // Pattern data uploaded to the PPU sprite bank when the cartridge is loaded
+    pub fn sprite_bank(&self) -> SpriteBank {
+        hambert_sprite_bank()
+    }
+
+    // Sprite palettes loaded alongside the sprite bank
+    pub fn sprite_palettes(&self) -> &'static [(u8, &'static [u8])] {
+        hambert_sprite_palettes()
+    }
+
+    // Animation clips (.zsa) uploaded into the sprite bank
+    pub fn animation_data(&self) -> Vec<u8> {
+        hambert_animations()
+    }
+
+    // Sprite shown enlarged on the intro and interlude screens, and the
+    // sprite palette it's drawn through
+    pub fn intro_sprite(&self) -> (u32, u8) {
+        (HAMBERT_PORTRAIT_SPRITE, HAMBERT_PALETTE)
+    }
+
+    // Size of the active video mode, for effects placed on screen
+    pub fn set_screen_size(&mut self, width: u32, height: u32) {
+        self.screen_width = width as f32;
+        self.screen_height = height as f32;
+    }
+
+    // Screen transition to start on the PPU, if the game asked for one
+    pub fn take_transition(&mut self) -> Option<Transition> {
+        self.pending_transition.take()
+    }
+
+    // Completion callback for transitions this cartridge started. The scene
+    // switches while the screen is covered, then the same effect reveals it.
+    pub fn on_transition_complete(&mut self, id: u32) {
+        if id != TRANSITION_SCENE_OUT {
+            return;
+        }
+        let (change, kind) = match self.pending_scene.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+        self.apply_scene_change(change);
+        self.pending_transition = Some(
+            Transition::new(TRANSITION_SCENE_IN, kind, TransitionDirection::In, SCENE_TRANSITION_FRAMES)
+                .with_center(self.screen_width / 2.0, self.screen_height / 2.0), // Iris opens from the screen center
+        );
+    }
+
+    // Custom HUD tiles uploaded when the cartridge is loaded
+    pub fn hud_tiles(&self) -> [[u8; 8]; 8] {
+        heart_hud_tiles()
+    }
+
+    // .zsm tracks loaded when the cartridge is, in music ID order
+    pub fn music_tracks(&self) -> Vec<Vec<u8>> {
+        vec![hambert_level_theme()]
+    }
+
+    // .zsx effect table loaded when the cartridge is, indexed by sound ID
+    pub fn sound_effects(&self) -> Vec<u8> {
+        hambert_sound_effects()
+    }
+
+    // How each sound ID is played - the voice sounds sit past the table
+    pub fn sound_source(&self, sound_id: u32) -> SoundSource {
+        match sound_id {
+            id if id == SoundEffect::Laughter as u32 => {
+                // Played a little slow for a retro pitch
+                SoundSource::Sample { sample_id: HAMBERT_LAUGH_SAMPLE, rate: 0.75 }
+            }
+            id if id == SoundEffect::Gasp as u32 => SoundSource::VoiceEffect(1),
+            id if id == SoundEffect::Grunt as u32 => SoundSource::VoiceEffect(2),
+            _ => SoundSource::Table(sound_id),
+        }
+    }
+
+    // PCM samples (data, sample rate) loaded when the cartridge is, in sample
+    // ID order
+    pub fn samples(&self) -> Vec<(&'static [u8], u32)> {
+        vec![(LAUGH_SAMPLE_RETRO_SAMPLE_DATA, LAUGH_SAMPLE_RETRO_SAMPLE_RATE)]
+    }
+
+    pub fn take_music_commands(&mut self) -> Vec<MusicCommand> {
+        std::mem::take(&mut self.pending_music)
+    }
+
+    pub fn take_apu_writes(&mut self) -> Vec<(u16, u8)> {
+        std::mem::take(&mut self.pending_apu_writes)
+    }
+
+    // HUD tile animations set up when the cartridge is loaded
+    pub fn hud_tile_animations(&self) -> Vec<TileAnimation> {
+        heart_tile_animations()
+    }
+
+    // Redraw the HUD - one heart per remaining life along the top-left. Only
+    // the hearts' cells are touched, so text the host writes elsewhere stays.
+    pub fn draw_hud(&self, hud: &mut HudLayer) {
+        for life in 0..STARTING_LIVES as usize {
+            let column = 1 + life * 3;
+            for tile in 0..4 {
+                let cell = if life < self.lives as usize {
+                    HudCell { tile: HudTile::Custom(tile), color: HEART_COLOR }
+                } else {
+                    HudCell::EMPTY
+                };
+                hud.set_cell(column + tile % 2, 1 + tile / 2, cell);
+            }
+        }
+    }
+}
+
 // Piano key data for visualization
 #[derive(Clone)]
 pub struct PianoKey {
@@ -1161,6 +1478,23 @@ pub struct PianoKey {
     pub note: u32,          // MIDI note number
 }
 
+// Z-Synth piano key metasprites, the patterns they're built from, and the
+// sprite palettes for each key state (border, face)
+const WHITE_KEY_SPRITE: u32 = 10;
+const BLACK_KEY_SPRITE: u32 = 12;
+const WHITE_KEY_CAP: u32 = 40; // Cap, then the body piece at 41
+const BLACK_KEY_CAP: u32 = 44;
+const WHITE_KEY_PALETTE: u8 = 0;
+const WHITE_KEY_PRESSED_PALETTE: u8 = 1;
+const BLACK_KEY_PALETTE: u8 = 2;
+const BLACK_KEY_PRESSED_PALETTE: u8 = 3;
+static KEY_PALETTES: [(u8, &[u8]); 4] = [
+    (WHITE_KEY_PALETTE, &[0, 1, 15]),          // Black border, white face
+    (WHITE_KEY_PRESSED_PALETTE, &[0, 16, 20]), // Red border, bright red face
+    (BLACK_KEY_PALETTE, &[0, 3, 1]),           // Dark gray border, black face
+    (BLACK_KEY_PRESSED_PALETTE, &[0, 24, 24]), // Bright yellow
+];
+
 // Z-Synth cartridge - A synthesizer application
 #[wasm_bindgen]
 pub struct ZSynthCartridge {
@@ -1484,4 +1818,68 @@ impl ZSynthCartridge {
 
         Some(obj)
     }
-}
\ No newline at end of file
+}
+
+// Console-side interface (not exported to JavaScript)
+impl ZSynthCartridge {
+    // Piano keys are taller than a hardware sprite, so each key (white and
+    // black) is a metasprite: an end cap, an optional body piece, and the same
+    // end cap flipped for the other end. Pattern pixels are 1 = border,
+    // 2 = key face; pressing a key swaps it to its pressed palette.
+    pub fn sprite_bank(&self) -> SpriteBank {
+        let mut bank = SpriteBank::new();
+        bank.insert(WHITE_KEY_CAP, Self::white_key_pattern(32, true));
+        bank.insert(WHITE_KEY_CAP + 1, Self::white_key_pattern(16, false));
+        bank.insert_metasprite(WHITE_KEY_SPRITE, MetaSprite {
+            width: 25,
+            height: 80,
+            pieces: vec![
+                Self::key_piece(WHITE_KEY_CAP, 0, false),
+                Self::key_piece(WHITE_KEY_CAP + 1, 32, false),
+                Self::key_piece(WHITE_KEY_CAP, 48, true),
+            ],
+        });
+        bank.insert(BLACK_KEY_CAP, Self::black_key_pattern());
+        bank.insert_metasprite(BLACK_KEY_SPRITE, MetaSprite {
+            width: 15,
+            height: 50,
+            pieces: vec![Self::key_piece(BLACK_KEY_CAP, 0, false), Self::key_piece(BLACK_KEY_CAP, 25, true)],
+        });
+        bank
+    }
+
+    // Sprite palettes loaded alongside the sprite bank
+    pub fn sprite_palettes(&self) -> &'static [(u8, &'static [u8])] {
+        &KEY_PALETTES
+    }
+
+    // Metasprite and sprite palette for a key in the given state
+    pub fn key_sprite(is_black: bool, is_pressed: bool) -> (u32, u8) {
+        match (is_black, is_pressed) {
+            (false, false) => (WHITE_KEY_SPRITE, WHITE_KEY_PALETTE),
+            (false, true) => (WHITE_KEY_SPRITE, WHITE_KEY_PRESSED_PALETTE),
+            (true, false) => (BLACK_KEY_SPRITE, BLACK_KEY_PALETTE),
+            (true, true) => (BLACK_KEY_SPRITE, BLACK_KEY_PRESSED_PALETTE),
+        }
+    }
+
+    fn key_piece(pattern_id: u32, offset_y: i32, flip_vertical: bool) -> MetaSpritePiece {
+        MetaSpritePiece { pattern_id, offset_x: 0, offset_y, flip_horizontal: false, flip_vertical }
+    }
+
+    // A slice of a white key; `is_cap` puts the key's end border on the top row
+    fn white_key_pattern(height: u32, is_cap: bool) -> SpritePattern {
+        SpritePattern::from_fn(25, height, |x, y| {
+            let is_border = !(1..24).contains(&x) || (is_cap && y == 0);
+            if is_border { 1 } else { 2 }
+        })
+    }
+
+    // Top half of a black key
+    fn black_key_pattern() -> SpritePattern {
+        SpritePattern::from_fn(15, 25, |x, y| {
+            let is_border = !(1..14).contains(&x) || y == 0;
+            if is_border { 1 } else { 2 }
+        })
+    }
+}
@@ -0,0 +1,173 @@
+// Color math hardware - blending between layers, sprite tints and the master
+// brightness register used for screen fades.
+
+pub const MAX_BRIGHTNESS: u8 = 15;
+
+// How a layer or sprite combines with what's already on screen
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BlendMode {
+    Opaque,   // Replace the pixel underneath
+    Half,     // 50% blend (ghosts, glass, shadows)
+    Add,      // Additive, clamped (lights, fire, glows)
+    Subtract, // Subtractive, clamped (darkness, shadows)
+}
+
+impl BlendMode {
+    pub fn from_u32(mode: u32) -> BlendMode {
+        match mode {
+            1 => BlendMode::Half,
+            2 => BlendMode::Add,
+            3 => BlendMode::Subtract,
+            _ => BlendMode::Opaque,
+        }
+    }
+
+    pub fn apply(self, dst: (u8, u8, u8), src: (u8, u8, u8)) -> (u8, u8, u8) {
+        match self {
+            BlendMode::Opaque => src,
+            BlendMode::Half => (
+                ((dst.0 as u16 + src.0 as u16) / 2) as u8,
+                ((dst.1 as u16 + src.1 as u16) / 2) as u8,
+                ((dst.2 as u16 + src.2 as u16) / 2) as u8,
+            ),
+            BlendMode::Add => (
+                dst.0.saturating_add(src.0),
+                dst.1.saturating_add(src.1),
+                dst.2.saturating_add(src.2),
+            ),
+            BlendMode::Subtract => (
+                dst.0.saturating_sub(src.0),
+                dst.1.saturating_sub(src.1),
+                dst.2.saturating_sub(src.2),
+            ),
+        }
+    }
+}
+
+// Mix a color toward a tint color - strength 255 replaces the color entirely
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Tint {
+    pub color: (u8, u8, u8),
+    pub strength: u8,
+}
+
+impl Tint {
+    pub fn apply(self, color: (u8, u8, u8)) -> (u8, u8, u8) {
+        let mix = |from: u8, to: u8| -> u8 {
+            let strength = self.strength as u16;
+            ((from as u16 * (255 - strength) + to as u16 * strength) / 255) as u8
+        };
+        (
+            mix(color.0, self.color.0),
+            mix(color.1, self.color.1),
+            mix(color.2, self.color.2),
+        )
+    }
+}
+
+// Screen-wide color math applied after the frame is composed
+pub struct ColorMath {
+    brightness: u8,
+    fade_from: u8,
+    fade_target: u8,
+    fade_frames: u32,
+    fade_elapsed: u32,
+    pub tint: Option<Tint>, // Screen tint/emphasis (night, underwater, damage)
+}
+
+impl ColorMath {
+    pub fn new() -> ColorMath {
+        ColorMath {
+            brightness: MAX_BRIGHTNESS,
+            fade_from: MAX_BRIGHTNESS,
+            fade_target: MAX_BRIGHTNESS,
+            fade_frames: 0,
+            fade_elapsed: 0,
+            tint: None,
+        }
+    }
+
+    // Set brightness immediately (0 = black, 15 = full), cancelling any fade
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness.min(MAX_BRIGHTNESS);
+        self.fade_target = self.brightness;
+        self.fade_frames = 0;
+    }
+
+    pub fn brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    // Fade brightness to `target` over `frames` rendered frames
+    pub fn fade_to(&mut self, target: u8, frames: u32) {
+        if frames == 0 {
+            self.set_brightness(target);
+            return;
+        }
+        self.fade_from = self.brightness;
+        self.fade_target = target.min(MAX_BRIGHTNESS);
+        self.fade_frames = frames;
+        self.fade_elapsed = 0;
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.fade_frames > 0
+    }
+
+    // Advance an active fade by one frame
+    pub fn step_fade(&mut self) {
+        if self.fade_frames == 0 {
+            return;
+        }
+        self.fade_elapsed += 1;
+        let from = self.fade_from as i32;
+        let delta = self.fade_target as i32 - from;
+        self.brightness = (from + delta * self.fade_elapsed as i32 / self.fade_frames as i32) as u8;
+        if self.fade_elapsed >= self.fade_frames {
+            self.brightness = self.fade_target;
+            self.fade_frames = 0;
+        }
+    }
+
+    // True when the final pass wouldn't change any pixel
+    pub fn is_identity(&self) -> bool {
+        self.brightness == MAX_BRIGHTNESS && self.tint.is_none()
+    }
+
+    pub fn apply(&self, color: (u8, u8, u8)) -> (u8, u8, u8) {
+        let color = match self.tint {
+            Some(tint) => tint.apply(color),
+            None => color,
+        };
+        let scale = |c: u8| -> u8 { (c as u16 * self.brightness as u16 / MAX_BRIGHTNESS as u16) as u8 };
+        (scale(color.0), scale(color.1), scale(color.2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blend_modes() {
+        let dst = (200, 100, 10);
+        let src = (100, 200, 20);
+        assert_eq!(BlendMode::Opaque.apply(dst, src), src);
+        assert_eq!(BlendMode::Half.apply(dst, src), (150, 150, 15));
+        assert_eq!(BlendMode::Add.apply(dst, src), (255, 255, 30));
+        assert_eq!(BlendMode::Subtract.apply(dst, src), (100, 0, 0));
+    }
+
+    #[test]
+    fn test_fade() {
+        let mut color_math = ColorMath::new();
+        color_math.fade_to(0, 3);
+        color_math.step_fade();
+        assert_eq!(color_math.brightness(), 10);
+        color_math.step_fade();
+        color_math.step_fade();
+        assert_eq!(color_math.brightness(), 0);
+        assert!(!color_math.is_fading());
+        assert_eq!(color_math.apply((255, 255, 255)), (0, 0, 0));
+    }
+}
@@ -0,0 +1,284 @@
+use crate::font_system::get_font_data;
+use crate::palette::{PaletteRam, BG_PALETTE_COUNT, MASTER_PALETTE, PALETTE_COUNT, SUB_PALETTE_SIZE};
+use crate::sprite_bank::SpriteBank;
+use crate::ppu_clean::Layer;
+
+// PPU debug views - inspection images rendered into their own RGBA buffer on
+// request, separate from the main output: the pattern sheet, palette RAM,
+// the OAM table, and single layers of the current frame.
+
+const BACKGROUND: (u8, u8, u8) = (24, 24, 32);
+const TEXT_COLOR: (u8, u8, u8) = (230, 230, 230);
+const DIM_TEXT_COLOR: (u8, u8, u8) = (120, 120, 130);
+const SHEET_WIDTH: usize = 256;
+const LINE_HEIGHT: usize = 10;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DebugView {
+    PatternSheet(Option<u8>), // Patterns drawn with sub-palette 0-15 (8-15 are sprite palettes), None = master palette
+    Palettes,
+    Oam,
+    Layer(Layer), // One layer of the frame alone (Sprites = sprite-only view)
+}
+
+impl DebugView {
+    // 0 = pattern sheet, 1 = palettes, 2 = OAM, 3-6 = background, mode-7,
+    // sprite and HUD layers
+    pub fn from_u32(view: u32, palette: Option<u8>) -> Option<DebugView> {
+        match view {
+            0 => Some(DebugView::PatternSheet(palette)),
+            1 => Some(DebugView::Palettes),
+            2 => Some(DebugView::Oam),
+            _ => Layer::from_u32(view - 3).map(DebugView::Layer),
+        }
+    }
+}
+
+// One hardware sprite as the PPU sees it this frame
+pub struct OamEntry {
+    pub x: i32,
+    pub y: i32,
+    pub pattern_id: u32,
+    pub width: u32,
+    pub height: u32,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    pub palette: Option<u8>,
+    pub affine: bool,
+    pub blended: bool, // Non-opaque blend mode or tint
+}
+
+pub struct DebugImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl DebugImage {
+    pub fn new(width: usize, height: usize) -> DebugImage {
+        let mut image = DebugImage { width, height, pixels: vec![255; width * height * 4] };
+        image.fill_rect(0, 0, width, height, BACKGROUND);
+        image
+    }
+
+    pub fn put_pixel(&mut self, x: usize, y: usize, color: (u8, u8, u8)) {
+        if x < self.width && y < self.height {
+            let index = (y * self.width + x) * 4;
+            self.pixels[index] = color.0;
+            self.pixels[index + 1] = color.1;
+            self.pixels[index + 2] = color.2;
+            self.pixels[index + 3] = 255;
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: (u8, u8, u8)) {
+        for py in y..y + height {
+            for px in x..x + width {
+                self.put_pixel(px, py, color);
+            }
+        }
+    }
+
+    // ASCII text in the 8x8 font
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, color: (u8, u8, u8)) {
+        for (i, character) in text.bytes().enumerate() {
+            let glyph = match get_font_data(character.saturating_sub(32) as usize) {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..8 {
+                    if (bits >> col) & 1 != 0 {
+                        self.put_pixel(x + i * 8 + col, y + row, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Every pattern in the bank, packed left to right in ID order with its ID
+// above it. `palette` is a palette RAM slot, so tiles can be previewed with
+// background palettes (0-7) as well as sprite palettes (8-15).
+pub fn render_pattern_sheet(bank: &SpriteBank, palette_ram: &PaletteRam, palette: Option<u8>) -> DebugImage {
+    let patterns: Vec<_> = bank.pattern_ids().into_iter().filter_map(|id| Some((id, bank.get(id)?))).collect();
+    let cell_size = |width: u32, height: u32| ((width as usize).max(24) + 4, height as usize + LINE_HEIGHT + 4);
+    let width = patterns.iter().map(|(_, p)| cell_size(p.width, p.height).0).max().unwrap_or(0).max(SHEET_WIDTH);
+
+    // Shelf layout: (id, x, y) per pattern
+    let mut placements = Vec::new();
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    for (id, pattern) in &patterns {
+        let (cell_width, cell_height) = cell_size(pattern.width, pattern.height);
+        if x + cell_width > width {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+        placements.push((*id, x, y));
+        x += cell_width;
+        row_height = row_height.max(cell_height);
+    }
+
+    let mut image = DebugImage::new(width, (y + row_height).max(LINE_HEIGHT));
+    for (id, x, y) in placements {
+        let pattern = match bank.get(id) {
+            Some(pattern) => pattern,
+            None => continue,
+        };
+        image.draw_text(x + 2, y + 1, &id.to_string(), DIM_TEXT_COLOR);
+        for py in 0..pattern.height {
+            for px in 0..pattern.width {
+                let index = pattern.pixel(px, py);
+                let color = match palette {
+                    Some(palette) => palette_ram.resolve(palette as usize, index),
+                    None if index != 0 => Some(MASTER_PALETTE[index as usize]),
+                    None => None,
+                };
+                // Transparent pixels show as a checkerboard
+                let color = color.unwrap_or(if (px / 4 + py / 4) % 2 == 0 { (60, 60, 70) } else { (44, 44, 52) });
+                image.put_pixel(x + 2 + px as usize, y + LINE_HEIGHT + 2 + py as usize, color);
+            }
+        }
+    }
+    image
+}
+
+// Palette RAM: one row of swatches per sub-palette. Entries past the
+// palette's size are drawn as small dimmed squares.
+pub fn render_palettes(palette_ram: &PaletteRam) -> DebugImage {
+    const SWATCH: usize = 12;
+    const LABEL_WIDTH: usize = 32;
+    let mut image = DebugImage::new(LABEL_WIDTH + SUB_PALETTE_SIZE * (SWATCH + 2), PALETTE_COUNT * (SWATCH + 2) + 2);
+
+    for palette in 0..PALETTE_COUNT {
+        let y = 2 + palette * (SWATCH + 2);
+        let label = if palette < BG_PALETTE_COUNT {
+            format!("BG{}", palette)
+        } else {
+            format!("SP{}", palette - BG_PALETTE_COUNT)
+        };
+        image.draw_text(2, y + 2, &label, TEXT_COLOR);
+
+        for index in 0..SUB_PALETTE_SIZE {
+            let x = LABEL_WIDTH + index * (SWATCH + 2);
+            let color = MASTER_PALETTE[palette_ram.read(palette, index) as usize];
+            if index == 0 || palette_ram.resolve(palette, index as u8).is_some() {
+                image.fill_rect(x, y, SWATCH, SWATCH, color);
+            } else {
+                image.fill_rect(x + 3, y + 3, SWATCH - 6, SWATCH - 6, color);
+            }
+        }
+    }
+    image
+}
+
+// One line per hardware sprite: slot, position, pattern, size and flags
+// (H/V flip, palette, A = affine, B = blended). Slots past the per-frame
+// limit are dimmed.
+pub fn render_oam(entries: &[OamEntry], max_sprites: usize) -> DebugImage {
+    let mut image = DebugImage::new(320, (entries.len() + 1) * LINE_HEIGHT + 2);
+    image.draw_text(2, 2, "#    X    Y  PAT   SIZE FLAGS", TEXT_COLOR);
+
+    for (slot, entry) in entries.iter().enumerate() {
+        let flags = format!(
+            "{}{}{}{}{}",
+            if entry.flip_horizontal { 'H' } else { '-' },
+            if entry.flip_vertical { 'V' } else { '-' },
+            entry.palette.map_or('-', |palette| (b'0' + palette % 8) as char),
+            if entry.affine { 'A' } else { '-' },
+            if entry.blended { 'B' } else { '-' },
+        );
+        let size = format!("{}x{}", entry.width, entry.height);
+        let line = format!("{:<3}{:>5}{:>5}{:>5}{:>7} {}", slot, entry.x, entry.y, entry.pattern_id, size, flags);
+        let color = if slot < max_sprites { TEXT_COLOR } else { DIM_TEXT_COLOR };
+        image.draw_text(2, 2 + (slot + 1) * LINE_HEIGHT, &line, color);
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sprite_bank::SpritePattern;
+
+    fn pixel(image: &DebugImage, x: usize, y: usize) -> (u8, u8, u8) {
+        let index = (y * image.width + x) * 4;
+        (image.pixels[index], image.pixels[index + 1], image.pixels[index + 2])
+    }
+
+    #[test]
+    fn test_pattern_sheet_wraps_rows() {
+        let mut bank = SpriteBank::new();
+        for id in 0..12 {
+            bank.insert(id, SpritePattern::from_fn(16, 16, |_, _| 1));
+        }
+        let image = render_pattern_sheet(&bank, &PaletteRam::new(), None);
+
+        // 28-pixel cells: 9 per 256-pixel row, so 12 patterns take two rows
+        assert_eq!(image.width, SHEET_WIDTH);
+        assert_eq!(image.height, 2 * (16 + LINE_HEIGHT + 4));
+        assert_eq!(image.pixels.len(), image.width * image.height * 4);
+    }
+
+    #[test]
+    fn test_pattern_sheet_uses_any_sub_palette() {
+        let mut bank = SpriteBank::new();
+        bank.insert(0, SpritePattern::from_fn(8, 8, |_, _| 1));
+        let mut palette_ram = PaletteRam::new();
+        palette_ram.write(2, 1, 200); // Background palette
+        palette_ram.write(PaletteRam::sprite_palette_slot(2), 1, 40);
+
+        let (x, y) = (2, LINE_HEIGHT + 2);
+        assert_eq!(pixel(&render_pattern_sheet(&bank, &palette_ram, Some(2)), x, y), MASTER_PALETTE[200]);
+        assert_eq!(pixel(&render_pattern_sheet(&bank, &palette_ram, Some(10)), x, y), MASTER_PALETTE[40]);
+        assert_eq!(pixel(&render_pattern_sheet(&bank, &palette_ram, None), x, y), MASTER_PALETTE[1]);
+    }
+
+    #[test]
+    fn test_palette_view() {
+        let mut palette_ram = PaletteRam::new();
+        palette_ram.write(9, 3, 84);
+        palette_ram.set_size(9, 4);
+        palette_ram.write(9, 5, 20);
+        let image = render_palettes(&palette_ram);
+
+        // Rows of 14 pixels per sub-palette after a 32-pixel label column
+        let swatch = |palette: usize, index: usize| (32 + index * 14, 2 + palette * 14);
+        let (x, y) = swatch(9, 3);
+        assert_eq!(pixel(&image, x, y), MASTER_PALETTE[84]);
+        // Past the palette's size: a small inset square
+        let (x, y) = swatch(9, 5);
+        assert_eq!(pixel(&image, x, y), BACKGROUND);
+        assert_eq!(pixel(&image, x + 6, y + 6), MASTER_PALETTE[20]);
+    }
+
+    #[test]
+    fn test_oam_view_dims_slots_past_the_limit() {
+        let entry = |pattern_id| OamEntry {
+            x: 10,
+            y: -4,
+            pattern_id,
+            width: 16,
+            height: 16,
+            flip_horizontal: true,
+            flip_vertical: false,
+            palette: Some(3),
+            affine: false,
+            blended: true,
+        };
+        let image = render_oam(&[entry(0), entry(1), entry(2)], 2);
+        assert_eq!(image.height, 4 * LINE_HEIGHT + 2);
+
+        let line_colors = |slot: usize| {
+            let y = 2 + (slot + 1) * LINE_HEIGHT;
+            (y..y + 8)
+                .flat_map(|y| (0..image.width).map(move |x| (x, y)))
+                .map(|(x, y)| pixel(&image, x, y))
+                .filter(|&color| color != BACKGROUND)
+                .collect::<Vec<_>>()
+        };
+        assert!(!line_colors(1).is_empty() && line_colors(1).iter().all(|&color| color == TEXT_COLOR));
+        assert!(!line_colors(2).is_empty() && line_colors(2).iter().all(|&color| color == DIM_TEXT_COLOR));
+    }
+}
@@ -0,0 +1,93 @@
+// Dirty-region tracking for frame output. After each frame the tracker
+// compares the new frame against the previous one and records which scanlines
+// changed (one bit per line) plus the bounding rectangle of all changes, so
+// frontends can skip uploading or redrawing regions that stayed the same.
+
+pub struct DirtyTracker {
+    previous: Vec<u8>,
+    changed_lines: Vec<u8>, // Bitmap, bit (line % 8) of byte (line / 8)
+    rect: Option<(usize, usize, usize, usize)>, // x, y, width, height in pixels
+}
+
+impl DirtyTracker {
+    pub fn new() -> DirtyTracker {
+        DirtyTracker {
+            previous: Vec::new(),
+            changed_lines: Vec::new(),
+            rect: None,
+        }
+    }
+
+    // Compare a frame of `height` lines, `bytes_per_pixel` bytes per pixel.
+    // The first frame (or a frame of a different size) is entirely dirty.
+    pub fn update(&mut self, frame: &[u8], height: usize, bytes_per_pixel: usize) {
+        let line_bytes = frame.len() / height.max(1);
+        self.changed_lines.clear();
+        self.changed_lines.resize(height.div_ceil(8), 0);
+
+        if self.previous.len() != frame.len() {
+            self.changed_lines.iter_mut().for_each(|bits| *bits = 0xFF);
+            self.rect = Some((0, 0, line_bytes / bytes_per_pixel, height));
+            self.previous = frame.to_vec();
+            return;
+        }
+
+        let mut bounds: Option<(usize, usize, usize, usize)> = None; // min_x, min_y, max_x, max_y
+        for line in 0..height {
+            let start = line * line_bytes;
+            let current = &frame[start..start + line_bytes];
+            let last = &self.previous[start..start + line_bytes];
+            if current == last {
+                continue;
+            }
+
+            self.changed_lines[line / 8] |= 1 << (line % 8);
+            let first = current.iter().zip(last).position(|(a, b)| a != b).unwrap_or(0);
+            let end = current.iter().zip(last).rposition(|(a, b)| a != b).unwrap_or(0);
+            let (min_x, max_x) = (first / bytes_per_pixel, end / bytes_per_pixel);
+            bounds = Some(match bounds {
+                Some((x0, y0, x1, _)) => (x0.min(min_x), y0, x1.max(max_x), line),
+                None => (min_x, line, max_x, line),
+            });
+        }
+
+        self.rect = bounds.map(|(x0, y0, x1, y1)| (x0, y0, x1 - x0 + 1, y1 - y0 + 1));
+        self.previous.copy_from_slice(frame);
+    }
+
+    // Force the next frame to be reported as fully dirty
+    pub fn invalidate(&mut self) {
+        self.previous.clear();
+    }
+
+    pub fn changed_lines(&self) -> &[u8] {
+        &self.changed_lines
+    }
+
+    pub fn rect(&self) -> Option<(usize, usize, usize, usize)> {
+        self.rect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dirty_rect() {
+        let mut tracker = DirtyTracker::new();
+        let mut frame = vec![0u8; 16 * 10];
+        tracker.update(&frame, 10, 1);
+        assert_eq!(tracker.rect(), Some((0, 0, 16, 10)));
+
+        tracker.update(&frame, 10, 1);
+        assert_eq!(tracker.rect(), None);
+        assert_eq!(tracker.changed_lines(), &[0, 0]);
+
+        frame[3 * 16 + 5] = 1;
+        frame[6 * 16 + 2] = 1;
+        tracker.update(&frame, 10, 1);
+        assert_eq!(tracker.rect(), Some((2, 3, 4, 4)));
+        assert_eq!(tracker.changed_lines(), &[0b0100_1000, 0]);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::music::{EFFECT_SET_PAN, EFFECT_VIBRATO, NOTE_OFF};
+
+// Hambert's soundtrack as .zsm data. The level theme is a cheerful four-bar
+// loop over C, Am, F and G: triangle bass in the center, a 25% pulse lead a
+// little to the left and noise hi-hats a little to the right.
+
+pub const HAMBERT_LEVEL_THEME: u32 = 0; // Music ID (first track loaded)
+
+const ROWS: usize = 16;
+const SPEED: u8 = 6; // Ticks per row, so a bar lasts 1.6 seconds
+const TEMPO: u8 = 150;
+
+// Waveform, volume, attack, decay, sustain, release, pulse width, reserved
+const INSTRUMENTS: [[u8; 8]; 3] = [
+    [2, 56, 0, 8, 40, 4, 128, 0], // Triangle bass
+    [0, 40, 1, 10, 32, 6, 64, 0], // Pulse lead
+    [4, 20, 0, 3, 0, 0, 128, 0],  // Noise hi-hat
+];
+const BASS: u8 = 1;
+const LEAD: u8 = 2;
+const HAT: u8 = 3;
+
+const BASS_ROOTS: [u8; 4] = [48, 45, 41, 43]; // C3, A2, F2, G2
+
+const MELODY: [[u8; ROWS]; 4] = [
+    [72, 0, 76, 0, 79, 0, 76, 0, 84, 0, 79, 0, 76, 0, 74, 0],
+    [76, 0, 72, 0, 69, 0, 72, 0, 76, 0, 81, 0, 79, 0, 76, 0],
+    [77, 0, 81, 0, 84, 0, 81, 0, 77, 0, 76, 0, 74, 0, 72, 0],
+    [74, 0, 79, 0, 83, 0, 79, 0, 79, 0, 0, 0, 0, 0, 0, NOTE_OFF], // Held G with vibrato
+];
+
+pub fn hambert_level_theme() -> Vec<u8> {
+    let bars = MELODY.len() as u8;
+    let mut data = vec![b'Z', b'S', b'M', 1, 3, ROWS as u8, SPEED, TEMPO, INSTRUMENTS.len() as u8, bars * 2 + 1, bars, 0];
+    for instrument in INSTRUMENTS {
+        data.extend_from_slice(&instrument);
+    }
+
+    // Each bar: its bass pattern, its melody pattern, the shared hi-hats
+    for bar in 0..bars {
+        data.extend_from_slice(&[bar, bars + bar, bars * 2]);
+    }
+
+    // Bass alternates root and octave on the beat
+    for root in BASS_ROOTS {
+        for row in 0..ROWS {
+            match row % 4 {
+                0 => data.extend_from_slice(&[root, BASS, 0, 0]),
+                2 => data.extend_from_slice(&[root + 12, BASS, 0, 0]),
+                _ => data.extend_from_slice(&[0, 0, 0, 0]),
+            }
+        }
+    }
+
+    for (bar, notes) in MELODY.iter().enumerate() {
+        for (row, &note) in notes.iter().enumerate() {
+            let instrument = if note != 0 && note != NOTE_OFF { LEAD } else { 0 };
+            let (effect, param) = match row {
+                0 => (EFFECT_SET_PAN, 0x60),
+                8 if bar == 3 => (EFFECT_VIBRATO, 0x43),
+                9..=14 if bar == 3 => (EFFECT_VIBRATO, 0), // Keeps the last speed and depth
+                _ => (0, 0),
+            };
+            data.extend_from_slice(&[note, instrument, effect, param]);
+        }
+    }
+
+    for row in 0..ROWS {
+        if row == 0 {
+            data.extend_from_slice(&[60, HAT, EFFECT_SET_PAN, 0xA8]);
+        } else if row % 2 == 0 {
+            data.extend_from_slice(&[60, HAT, 0, 0]);
+        } else {
+            data.extend_from_slice(&[0, 0, 0, 0]);
+        }
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::Song;
+
+    #[test]
+    fn test_level_theme_is_valid_zsm() {
+        let theme = hambert_level_theme();
+        assert!(Song::from_bytes(&theme).is_ok());
+        assert_eq!(theme.len(), 12 + 3 * 8 + 4 * 3 + 9 * ROWS * 4);
+    }
+}
@@ -0,0 +1,44 @@
+// Hambert's sound effects as a .zsx table, indexed by the cartridge's sound
+// IDs (see SoundEffect in cartridge.rs). Laughter, gasp and grunt are a
+// voice clip and voice effects the cartridge maps past the end of the table.
+
+const DUCK: u8 = 0x80;
+
+// Waveform, note, slide, delta slide, vibrato depth, vibrato speed, arpeggio
+// jump, arpeggio frame, pulse width, duty sweep, attack, sustain, decay,
+// punch, volume, priority
+const EFFECTS: [[u8; 16]; 6] = [
+    // Jump - rising pulse sweep that slows towards the top, duty widening
+    [0, 60, 24, -2i8 as u8, 0, 0, 0, 0, 96, 4, 0, 6, 8, 0, 200, 0],
+    // Land - short downward thud
+    [0, 55, -40i8 as u8, 0, 0, 0, 0, 0, 128, 0, 0, 2, 4, 128, 180, 0],
+    // Collect - coin chime jumping up a fifth
+    [0, 72, 0, 0, 0, 0, 7, 4, 64, 0, 0, 4, 10, 160, 200, 1],
+    // Enemy hit - falling noise burst
+    [4, 60, -24i8 as u8, 0, 0, 0, 0, 0, 128, 0, 0, 2, 6, 200, 220, 1],
+    // Shuriken throw - noise whoosh
+    [4, 80, -16i8 as u8, 0, 0, 0, 0, 0, 128, 0, 2, 2, 6, 0, 140, 0],
+    // Death - four-octave sawtooth fall with a wobble, ducking the music
+    [1, 84, -16i8 as u8, 0, 16, 8, 0, 0, 128, 0, 0, 24, 24, 0, 200, 2 | DUCK],
+];
+
+pub fn hambert_sound_effects() -> Vec<u8> {
+    let mut data = vec![b'Z', b'S', b'X', 1, EFFECTS.len() as u8];
+    for effect in EFFECTS {
+        data.extend_from_slice(&effect);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sfx::table_from_bytes;
+
+    #[test]
+    fn test_sound_effects_are_valid_zsx() {
+        let effects = table_from_bytes(&hambert_sound_effects()).unwrap();
+        assert_eq!(effects.len(), 6);
+        assert!(effects.iter().all(|effect| effect.frames() > 0));
+    }
+}
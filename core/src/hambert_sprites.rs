@@ -0,0 +1,521 @@
+use crate::sprite_bank::{SpriteBank, SpritePattern};
+use crate::animation::NO_EVENT;
+use crate::metasprite::{MetaSprite, MetaSpritePiece};
+use crate::tile_animation::TileAnimation;
+use crate::palette::MASTER_PALETTE;
+
+// Hambert cartridge sprite assets. These are uploaded into the PPU sprite bank
+// when the cartridge is loaded. Pattern sizes match the entity hitboxes in
+// cartridge.rs; art drawn at a different size is resampled at upload time.
+// Every pattern is drawn through one of the cartridge's sprite palettes.
+// Art taller than a hardware sprite (32x32) is drawn as a metasprite.
+
+pub const HAMBERT_SPRITE: u32 = 0;
+pub const PLATFORM_SPRITE: u32 = 1;
+pub const ENEMY_SPRITE: u32 = 2;
+pub const HEXAGNOME_SPRITE: u32 = 3;
+pub const SHURIKEN_SPRITE: u32 = 4;
+pub const HAMBERT_HEAD_SPRITE: u32 = 5;
+pub const HAMBERRY_SPRITE: u32 = 6;
+pub const BLOOD_GOBLIN_SPRITE: u32 = 7;
+pub const HAMBERT_PORTRAIT_SPRITE: u32 = 8;
+pub const BLOOD_GOBLIN_CROUCH_SPRITE: u32 = 9;
+pub const HAMBERRY_SHRINK_SPRITES: [u32; 4] = [10, 11, 12, 13];
+
+// Hardware sprite pieces of the blood goblin metasprites (top, bottom)
+const BLOOD_GOBLIN_PIECES: [u32; 2] = [14, 15];
+const BLOOD_GOBLIN_CROUCH_PIECES: [u32; 2] = [16, 17];
+
+// Sprite sub-palettes, loaded into sprite palettes 0-7. The art below is drawn
+// in master palette colors and converted to sub-palette indices when the bank
+// is built; a color missing from its sub-palette maps to the nearest entry.
+pub const HAMBERT_PALETTE: u8 = 0;
+pub const HEXAGNOME_PALETTE: u8 = 1;
+pub const HEXAGNOME_VARIANT_PALETTE: u8 = 2; // Rust-red hexagnomes
+pub const BLOOD_GOBLIN_PALETTE: u8 = 3;
+pub const HAMBERRY_PALETTE: u8 = 4;
+pub const PROPS_PALETTE: u8 = 5; // Platforms, shuriken, plain enemies
+pub const FLASH_WHITE_PALETTE: u8 = 6; // Damage flash swaps
+pub const FLASH_RED_PALETTE: u8 = 7;
+
+static SPRITE_PALETTES: [(u8, &[u8]); 8] = [
+    (HAMBERT_PALETTE, &[0, 1, 4, 5, 6, 7, 8, 9, 10, 11, 12, 14, 15, 18, 27]),
+    (HEXAGNOME_PALETTE, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13]),
+    // Same ramp as the hexagnome's grays, in colors already on screen
+    (HEXAGNOME_VARIANT_PALETTE, &[0, 1, 16, 28, 16, 17, 44, 29, 45, 18, 41, 30, 46, 47]),
+    // The goblin art's 15 most used colors; the rest map to their nearest
+    (BLOOD_GOBLIN_PALETTE, &[0, 4, 5, 6, 7, 8, 9, 16, 17, 18, 28, 29, 41, 44, 45, 110]),
+    (HAMBERRY_PALETTE, &[0, 1, 2, 3, 4, 16, 17, 18, 21, 22, 28, 60, 61, 62, 63]),
+    (PROPS_PALETTE, &[0, 1, 7, 8, 16, 17, 32, 33, 34, 49, 52]),
+    (FLASH_WHITE_PALETTE, &[0, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15]),
+    (FLASH_RED_PALETTE, &[0, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24]),
+];
+
+// Hambert idle sprite data (30x32) - version 2 with better outlines
+static HAMBERT_PIXEL_DATA: [[u8; 30]; 32] = [
+    [0,0,0,0,0,0,0,0,0,0,0,0,0,1,1,1,1,1,0,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,0,0,1,1,1,9,8,8,1,1,1,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,0,0,0,9,9,9,9,9,10,9,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,0,0,9,9,9,8,8,9,10,8,8,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,0,10,9,9,10,9,7,9,10,9,9,8,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,9,10,10,10,10,9,9,9,9,11,11,9,9,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,9,9,10,9,9,9,7,8,7,7,9,8,9,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,8,9,7,7,7,7,7,7,7,7,7,7,7,8,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,9,7,10,11,10,10,10,10,10,10,10,10,10,10,8,8,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,10,9,7,10,10,10,10,10,10,10,10,10,10,10,10,9,8,0,0,0,0,0,0],
+    [0,0,0,0,0,0,10,10,9,7,10,10,8,8,8,10,10,10,8,8,8,10,10,8,9,0,0,0,0,0],
+    [0,0,0,0,0,11,10,11,7,7,10,8,8,10,8,8,10,10,8,10,8,8,10,7,9,11,0,0,0,0],
+    [0,0,0,0,9,10,10,10,7,10,10,0,0,0,0,8,9,8,0,0,0,0,10,7,7,11,11,0,0,0],
+    [0,0,0,0,10,10,11,7,7,10,10,9,14,0,15,11,8,12,15,0,15,8,10,9,7,10,11,0,0,0],
+    [0,0,0,10,10,11,10,7,10,10,10,10,15,15,15,11,10,10,15,15,15,10,10,7,7,9,11,0,0,0],
+    [0,0,0,10,10,10,11,7,10,10,10,10,10,9,10,10,12,10,9,9,9,10,10,7,7,10,11,0,0,0],
+    [0,0,0,0,11,9,8,7,10,10,10,10,10,10,9,8,0,9,9,9,10,10,11,7,6,9,0,0,0,0],
+    [0,0,0,0,0,0,0,5,10,10,10,10,10,9,9,0,0,0,10,9,10,10,10,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,7,10,9,9,10,10,10,12,0,0,0,11,10,11,10,10,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,7,10,10,10,10,10,10,0,7,5,10,0,10,10,9,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,7,10,10,10,10,10,0,10,12,12,9,10,0,10,9,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,7,7,10,11,10,10,10,7,12,12,9,10,10,10,10,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,7,10,10,10,10,11,8,27,12,9,10,10,10,10,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,7,7,7,7,10,10,10,10,10,10,10,10,9,8,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,18,18,18,18,10,10,10,10,10,10,8,7,18,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,18,18,18,18,6,5,4,6,7,6,7,7,18,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,18,18,18,18,10,8,7,6,6,6,18,18,18,18,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,18,18,18,18,18,18,18,18,0,18,18,18,18,18,18,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,18,18,18,18,18,18,18,18,18,18,0,18,18,18,18,18,18,18,0,0,0,0],
+    [0,0,0,0,0,0,0,18,18,18,18,18,18,18,18,18,18,18,18,0,18,18,18,18,18,18,18,0,0,0],
+    [0,0,0,0,0,0,0,18,18,18,18,18,18,18,18,18,18,18,18,18,0,18,18,18,18,18,18,0,0,0],
+    [0,0,0,0,0,0,0,18,18,18,18,18,18,18,18,18,18,18,18,18,0,18,18,18,18,18,18,0,0,0],
+];
+
+// Original hexagnome pixel data (26x32), shown at 20x28
+static HEXAGNOME_PIXEL_DATA: [[u8; 26]; 32] = [
+    [0,0,0,0,0,0,0,0,1,3,3,4,4,3,3,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,3,0,3,3,4,4,3,3,2,1,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,1,6,5,2,3,3,3,2,2,2,4,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,8,13,7,2,2,2,2,2,4,4,4,3,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,13,11,11,4,2,2,3,4,5,4,4,3,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,11,10,10,9,3,0,2,3,2,2,2,4,5,4,0,0,0,0,0,0],
+    [0,0,0,0,0,9,12,9,3,1,1,1,1,2,1,2,5,4,5,5,3,0,0,0,0,0],
+    [0,0,0,0,0,0,4,1,1,1,1,1,2,2,3,5,6,5,4,5,4,4,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,0,1,1,2,3,5,5,6,5,4,5,4,3,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,0,0,1,2,4,5,5,6,4,4,6,5,2,2,0,0,0],
+    [0,0,0,0,0,0,0,0,0,3,1,1,3,4,5,6,6,4,4,5,6,2,2,0,0,0],
+    [0,0,0,0,0,0,0,0,3,1,1,1,3,5,5,6,5,3,4,5,5,3,3,0,0,0],
+    [0,0,0,0,0,0,3,3,3,1,0,1,3,5,5,6,5,3,4,4,5,3,3,0,0,0],
+    [0,3,3,3,3,3,3,3,3,1,2,3,3,4,5,6,3,3,4,4,6,3,2,0,0,0],
+    [0,3,13,13,12,3,3,3,2,3,3,3,4,4,4,4,3,3,4,5,4,1,0,1,0,0],
+    [0,0,3,13,12,3,3,3,3,3,3,3,4,3,3,4,4,4,4,5,5,3,2,1,0,0],
+    [0,0,3,3,3,0,0,3,3,3,3,4,3,2,3,5,4,4,4,5,4,3,5,3,0,0],
+    [0,0,0,0,0,0,3,3,3,3,2,2,1,1,4,5,4,4,4,4,4,4,2,3,3,0],
+    [0,0,0,0,0,2,3,3,2,2,1,1,1,1,2,4,4,4,4,4,4,4,2,2,3,0],
+    [0,0,0,0,0,5,2,2,1,0,1,2,3,3,3,3,4,4,4,4,4,3,2,2,3,0],
+    [0,0,0,0,3,12,12,8,1,2,2,3,4,4,3,3,4,4,4,4,4,3,2,2,3,0],
+    [0,0,0,0,3,13,13,7,3,3,3,4,4,4,3,3,3,4,4,4,3,3,2,3,2,0],
+    [0,0,0,0,3,3,3,3,3,3,3,3,4,5,4,4,4,4,4,3,3,2,2,3,3,0],
+    [0,0,0,0,0,0,0,0,3,3,3,2,3,4,5,5,4,4,3,3,3,2,1,3,0,0],
+    [0,0,0,0,0,0,0,0,3,3,4,3,2,2,3,4,4,3,3,3,3,2,1,3,0,0],
+    [0,0,0,0,0,0,0,4,3,3,4,5,4,3,3,3,4,3,3,3,2,2,3,3,0,0],
+    [0,0,0,0,0,0,0,3,3,3,4,4,4,4,4,3,3,3,3,3,2,2,3,3,0,0],
+    [0,0,0,0,0,0,0,3,3,2,3,4,4,4,4,3,3,3,3,3,2,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,0,3,3,3,3,3,3,3,3,3,3,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,3,3,3,3,3,2,3,3,3,3,3,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,3,3,3,3,3,2,3,3,3,3,3,3,3,0,0,0,0,0],
+    [0,0,0,0,0,0,3,3,3,3,3,3,2,3,3,3,3,3,3,3,3,0,0,0,0,0],
+];
+
+// Blood goblin pixel data (20x38) - converted from blood-gobline.png
+static BLOOD_GOBLIN_PIXEL_DATA: [[u8; 20]; 38] = [
+    [0,0,0,0,0,0,122,122,0,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,45,5,0,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,5,30,18,17,17,6,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,8,42,45,19,18,18,45,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,7,45,9,8,45,18,45,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,4,41,41,45,45,30,6,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,4,28,28,4,5,8,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,44,44,28,4,4,44,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,28,45,17,17,18,44,4,17,45,8,0,0,0,0,0,0],
+    [0,0,47,9,44,30,7,18,18,45,45,45,8,8,0,0,0,0,0,0],
+    [0,47,42,3,45,46,45,18,45,45,45,44,5,42,8,0,0,0,0,0],
+    [47,41,4,0,8,45,45,45,45,29,18,110,6,45,41,0,0,0,0,0],
+    [47,6,0,0,110,30,45,45,18,18,17,0,0,0,41,9,0,0,0,0],
+    [9,6,0,0,110,45,45,45,45,18,45,0,0,0,41,9,0,0,0,0],
+    [0,41,6,0,8,45,45,18,18,45,29,0,0,3,8,0,0,0,0,0],
+    [0,7,8,0,110,18,18,29,18,29,23,0,4,41,0,0,0,0,0,0],
+    [0,0,8,7,45,18,29,18,18,18,23,8,3,0,0,0,0,0,0,0],
+    [0,0,10,7,6,28,18,18,18,18,44,4,8,0,0,0,0,0,0,0],
+    [0,0,0,11,28,17,18,18,18,44,4,8,0,0,0,0,0,0,0,0],
+    [0,0,0,0,18,18,18,18,18,16,8,0,0,0,0,0,0,0,0,0],
+    [0,0,0,111,18,18,18,18,18,18,25,0,0,0,0,0,0,0,0,0],
+    [0,0,0,45,18,18,18,18,18,18,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,17,18,18,18,18,17,45,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,45,17,18,18,18,17,25,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,18,17,18,17,18,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,17,17,17,17,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,110,17,45,17,17,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,45,17,111,25,17,45,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,17,17,0,0,0,45,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,17,45,0,0,0,10,110,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,17,9,0,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,45,17,0,0,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,16,46,0,0,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,16,0,0,0,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,45,16,0,0,0,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,16,17,0,0,0,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],
+    [0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],
+];
+
+// Small 12x12 version of Hambert's head for the lives counter
+static HAMBERT_HEAD_PIXEL_DATA: [[u8; 12]; 12] = [
+    [0,0,0,1,1,1,1,1,1,0,0,0],  // Top of head outline
+    [0,0,1,9,8,8,1,1,9,1,0,0],  // Head with some facial features
+    [0,1,9,9,9,9,9,10,9,9,1,0], // More head detail
+    [0,1,9,8,8,9,10,8,8,9,1,0], // Eyes area
+    [1,9,9,10,9,7,9,10,9,9,8,1], // More facial features
+    [9,10,10,10,9,9,9,9,11,11,9,0], // Face shading
+    [9,9,10,9,9,7,8,7,7,9,8,0], // Nose/mouth area
+    [8,9,7,7,7,7,7,7,7,7,7,8], // Lower face
+    [7,10,11,10,10,10,10,10,10,8,8,0], // Chin area
+    [10,9,7,10,10,10,10,10,10,10,9,8], // Lower head
+    [10,9,7,10,8,8,8,10,10,8,9,0], // Jaw line
+    [11,7,7,10,8,10,8,8,10,7,9,11], // Bottom of head
+];
+
+// 16x16 hamberry collectible
+static HAMBERRY_PIXEL_DATA: [[u8; 16]; 16] = [
+    [0,0,0,0,1,1,0,0,0,0,1,1,0,0,0,0],
+    [0,0,1,1,1,61,1,1,0,1,61,61,1,0,0,0],
+    [0,0,1,62,63,4,62,4,60,62,4,63,1,1,0,0],
+    [0,0,0,3,63,63,60,0,0,60,63,63,1,0,0,0],
+    [0,0,0,0,2,61,3,1,1,3,60,1,1,0,0,0],
+    [0,0,0,0,16,16,1,21,21,1,16,16,0,0,0,0],
+    [0,0,0,16,16,1,1,17,17,1,2,16,0,0,0,0],
+    [0,0,0,16,0,28,17,0,0,18,28,0,16,16,0,0],
+    [0,0,16,16,16,21,22,2,16,22,21,16,28,16,0,0],
+    [0,0,16,17,2,21,21,0,0,21,21,1,17,16,0,0],
+    [0,0,16,2,1,1,1,28,28,1,1,1,16,16,0,0],
+    [0,0,16,16,18,1,28,22,22,16,1,18,16,0,0,0],
+    [0,0,0,16,16,18,1,17,17,1,18,16,16,0,0,0],
+    [0,0,0,0,16,16,0,2,2,0,16,16,0,0,0,0],
+    [0,0,0,0,0,16,16,16,21,16,16,0,0,0,0,0],
+    [0,0,0,0,0,0,0,16,16,16,0,0,0,0,0,0],
+];
+
+fn platform_pixel(x: u32, y: u32) -> u8 {
+    // Detailed platform texture like original
+    if y < 2 {
+        // Top grass layer with variation
+        if (x + y).is_multiple_of(3) {
+            52 // Bright green grass
+        } else {
+            49 // Medium green grass
+        }
+    } else if y < 6 {
+        // Dirt layer with some texture
+        if (x + y).is_multiple_of(4) {
+            34 // Lighter brown dirt
+        } else {
+            33 // Medium brown dirt
+        }
+    } else if y < 10 {
+        // Deeper dirt
+        if (x + y).is_multiple_of(5) {
+            32 // Dark brown
+        } else {
+            17 // Very dark brown
+        }
+    } else {
+        // Rock layer at bottom
+        if (x + y).is_multiple_of(6) {
+            8 // Dark gray rock
+        } else {
+            1 // Very dark gray/black rock
+        }
+    }
+}
+
+fn enemy_pixel(x: u32, y: u32) -> u8 {
+    // Simple 24x24 enemy sprite (red) with a transparent border
+    if (2..22).contains(&x) && (2..22).contains(&y) {
+        16 // Red from palette
+    } else {
+        0
+    }
+}
+
+fn shuriken_pixel(x: u32, y: u32) -> u8 {
+    // 12x12 shuriken - light gray ring
+    let dx = x as f32 - 6.0;
+    let dy = y as f32 - 6.0;
+    let distance = (dx * dx + dy * dy).sqrt();
+
+    if (1.0..=2.8).contains(&distance) {
+        7 // Light gray center
+    } else {
+        0 // Transparent
+    }
+}
+
+// Sprite palettes the Hambert cartridge loads, as (sprite palette, colors)
+pub fn hambert_sprite_palettes() -> &'static [(u8, &'static [u8])] {
+    &SPRITE_PALETTES
+}
+
+// Index of a master color in a sub-palette, or of the entry nearest to it
+fn palette_index(colors: &[u8], color: u8) -> u8 {
+    if color == 0 {
+        return 0; // Transparent
+    }
+    if let Some(index) = colors.iter().position(|&entry| entry == color) {
+        return index as u8;
+    }
+
+    let (r, g, b) = MASTER_PALETTE[color as usize];
+    colors
+        .iter()
+        .enumerate()
+        .skip(1)
+        .min_by_key(|(_, &entry)| {
+            let (er, eg, eb) = MASTER_PALETTE[entry as usize];
+            let (dr, dg, db) = (er as i32 - r as i32, eg as i32 - g as i32, eb as i32 - b as i32);
+            dr * dr + dg * dg + db * db
+        })
+        .map_or(0, |(index, _)| index as u8)
+}
+
+// Convert master-color art to indices into one of the sprite palettes
+fn in_palette<F: Fn(u32, u32) -> u8>(palette: u8, pixel: F) -> impl Fn(u32, u32) -> u8 {
+    let colors = SPRITE_PALETTES[palette as usize].1;
+    move |x, y| palette_index(colors, pixel(x, y))
+}
+
+// Cut art taller than a hardware sprite into equal horizontal strips, one
+// pattern per piece, and register the stack as a metasprite
+fn insert_column_metasprite<F: Fn(u32, u32) -> u8>(bank: &mut SpriteBank, sprite_id: u32, piece_ids: &[u32], width: u32, height: u32, pixel: F) {
+    let piece_height = height.div_ceil(piece_ids.len() as u32);
+    let mut pieces = Vec::new();
+    for (i, &pattern_id) in piece_ids.iter().enumerate() {
+        let top = i as u32 * piece_height;
+        bank.insert(pattern_id, SpritePattern::from_fn(width, piece_height.min(height - top), |x, y| pixel(x, top + y)));
+        pieces.push(MetaSpritePiece {
+            pattern_id,
+            offset_x: 0,
+            offset_y: top as i32,
+            flip_horizontal: false,
+            flip_vertical: false,
+        });
+    }
+    bank.insert_metasprite(sprite_id, MetaSprite { width, height, pieces });
+}
+
+// Build the sprite bank the Hambert cartridge uploads to the PPU
+pub fn hambert_sprite_bank() -> SpriteBank {
+    let mut bank = SpriteBank::new();
+
+    // In-game Hambert is drawn inside his 32x28 hitbox
+    bank.insert(HAMBERT_SPRITE, SpritePattern::from_fn(32, 28, in_palette(HAMBERT_PALETTE, |x, y| {
+        if x < 30 { HAMBERT_PIXEL_DATA[y as usize][x as usize] } else { 0 }
+    })));
+    bank.insert(PLATFORM_SPRITE, SpritePattern::from_fn(32, 16, in_palette(PROPS_PALETTE, platform_pixel)));
+    bank.insert(ENEMY_SPRITE, SpritePattern::from_fn(24, 24, in_palette(PROPS_PALETTE, enemy_pixel)));
+
+    // Hexagnome art is 26x32, scaled down to 20x28 for performance
+    bank.insert(HEXAGNOME_SPRITE, SpritePattern::from_fn(20, 28, in_palette(HEXAGNOME_PALETTE, |x, y| {
+        HEXAGNOME_PIXEL_DATA[((y * 32) / 28) as usize][((x * 26) / 20) as usize]
+    })));
+    bank.insert(SHURIKEN_SPRITE, SpritePattern::from_fn(12, 12, in_palette(PROPS_PALETTE, shuriken_pixel)));
+    bank.insert(HAMBERT_HEAD_SPRITE, SpritePattern::from_fn(12, 12, in_palette(HAMBERT_PALETTE, |x, y| {
+        HAMBERT_HEAD_PIXEL_DATA[y as usize][x as usize]
+    })));
+    bank.insert(HAMBERRY_SPRITE, SpritePattern::from_fn(16, 16, in_palette(HAMBERRY_PALETTE, |x, y| {
+        HAMBERRY_PIXEL_DATA[y as usize][x as usize]
+    })));
+    // Smaller hamberries (14, 12, 8 and 4 pixels) for its pulse and collect animations
+    for (&sprite_id, size) in HAMBERRY_SHRINK_SPRITES.iter().zip([14, 12, 8, 4]) {
+        let offset = (16 - size) / 2;
+        bank.insert(sprite_id, SpritePattern::from_fn(16, 16, in_palette(HAMBERRY_PALETTE, |x, y| {
+            if (offset..offset + size).contains(&x) && (offset..offset + size).contains(&y) {
+                HAMBERRY_PIXEL_DATA[((y - offset) * 16 / size) as usize][((x - offset) * 16 / size) as usize]
+            } else {
+                0
+            }
+        })));
+    }
+    insert_column_metasprite(&mut bank, BLOOD_GOBLIN_SPRITE, &BLOOD_GOBLIN_PIECES, 20, 38, in_palette(BLOOD_GOBLIN_PALETTE, |x, y| {
+        BLOOD_GOBLIN_PIXEL_DATA[y as usize][x as usize]
+    }));
+
+    // Crouch before a hop - the goblin squashed to 32 rows, feet kept on the ground
+    insert_column_metasprite(&mut bank, BLOOD_GOBLIN_CROUCH_SPRITE, &BLOOD_GOBLIN_CROUCH_PIECES, 20, 38, in_palette(BLOOD_GOBLIN_PALETTE, |x, y| {
+        if y < 6 { 0 } else { BLOOD_GOBLIN_PIXEL_DATA[((y - 6) * 37 / 31) as usize][x as usize] }
+    }));
+
+    // Full-size Hambert for the title screen
+    bank.insert(HAMBERT_PORTRAIT_SPRITE, SpritePattern::from_fn(30, 32, in_palette(HAMBERT_PALETTE, |x, y| {
+        HAMBERT_PIXEL_DATA[y as usize][x as usize]
+    })));
+
+    bank
+}
+
+// Animation clip names and the frame events the game reacts to
+pub const BLOOD_GOBLIN_HOP: &str = "blood_goblin_hop";
+pub const HAMBERRY_PULSE: &str = "hamberry_pulse";
+pub const HAMBERRY_COLLECT: &str = "hamberry_collect";
+pub const EVENT_HOP_LEFT: u32 = 0;
+pub const EVENT_HOP_RIGHT: u32 = 1;
+
+// Pattern ID, duration in frames, event ID ($FF for none)
+type Frame = (u32, u8, u8);
+const HOP_LEFT: u8 = EVENT_HOP_LEFT as u8;
+const HOP_RIGHT: u8 = EVENT_HOP_RIGHT as u8;
+
+// The blood goblin crouches, then hops every two seconds: left, left, right, right
+const BLOOD_GOBLIN_HOP_FRAMES: [Frame; 8] = [
+    (BLOOD_GOBLIN_SPRITE, 112, HOP_RIGHT),
+    (BLOOD_GOBLIN_CROUCH_SPRITE, 8, NO_EVENT),
+    (BLOOD_GOBLIN_SPRITE, 112, HOP_LEFT),
+    (BLOOD_GOBLIN_CROUCH_SPRITE, 8, NO_EVENT),
+    (BLOOD_GOBLIN_SPRITE, 112, HOP_LEFT),
+    (BLOOD_GOBLIN_CROUCH_SPRITE, 8, NO_EVENT),
+    (BLOOD_GOBLIN_SPRITE, 112, HOP_RIGHT),
+    (BLOOD_GOBLIN_CROUCH_SPRITE, 8, NO_EVENT),
+];
+
+// Hamberries throb gently so they stand out from the scenery
+const HAMBERRY_PULSE_FRAMES: [Frame; 3] = [
+    (HAMBERRY_SPRITE, 40, NO_EVENT),
+    (HAMBERRY_SHRINK_SPRITES[0], 6, NO_EVENT),
+    (HAMBERRY_SHRINK_SPRITES[1], 6, NO_EVENT),
+];
+
+// A collected hamberry shrinks away before it's removed
+const HAMBERRY_COLLECT_FRAMES: [Frame; 3] = [
+    (HAMBERRY_SHRINK_SPRITES[1], 4, NO_EVENT),
+    (HAMBERRY_SHRINK_SPRITES[2], 4, NO_EVENT),
+    (HAMBERRY_SHRINK_SPRITES[3], 4, NO_EVENT),
+];
+
+// Name, playback mode (0 loop, 1 ping-pong, 2 once), frames
+const CLIPS: [(&str, u8, &[Frame]); 3] = [
+    (BLOOD_GOBLIN_HOP, 0, &BLOOD_GOBLIN_HOP_FRAMES),
+    (HAMBERRY_PULSE, 1, &HAMBERRY_PULSE_FRAMES),
+    (HAMBERRY_COLLECT, 2, &HAMBERRY_COLLECT_FRAMES),
+];
+
+// The animation clips the Hambert cartridge's entities play, as .zsa data
+// (see animation.rs) - uploaded into the sprite bank for the renderer and
+// parsed by the cartridge for frame timing and events
+pub fn hambert_animations() -> Vec<u8> {
+    let mut data = vec![b'Z', b'S', b'A', 1, CLIPS.len() as u8];
+    for (name, mode, frames) in CLIPS {
+        data.push(name.len() as u8);
+        data.extend_from_slice(name.as_bytes());
+        data.push(mode);
+        data.push(frames.len() as u8);
+        for &(pattern_id, duration, event) in frames {
+            data.extend_from_slice(&(pattern_id as u16).to_le_bytes());
+            data.push(duration);
+            data.push(event);
+        }
+    }
+    data
+}
+
+// Life heart for the HUD - 16x16, drawn as 2x2 HUD tiles
+pub const HEART_COLOR: u8 = 104;
+
+static HEART_PATTERN: [u16; 16] = [
+    0b0000000000000000, // ................
+    0b0000000000000000, // ................
+    0b0011110001111000, // ..####...####...
+    0b0111111011111100, // .######.######..
+    0b1111111111111110, // ##############..
+    0b1111111111111110, // ##############..
+    0b1111111111111110, // ##############..
+    0b1111111111111110, // ##############..
+    0b0111111111111100, // .############...
+    0b0011111111111000, // ..##########....
+    0b0001111111110000, // ...########.....
+    0b0000111111100000, // ....######......
+    0b0000011111000000, // .....####.......
+    0b0000001110000000, // ......##........
+    0b0000000100000000, // .......#........
+    0b0000000000000000, // ................
+];
+
+// Contracted heart for the beat frame of the HUD heart animation
+static HEART_BEAT_PATTERN: [u16; 16] = [
+    0b0000000000000000, // ................
+    0b0000000000000000, // ................
+    0b0000000000000000, // ................
+    0b0001110001110000, // ...###...###....
+    0b0011111011111000, // ..#####.#####...
+    0b0011111111111000, // ..###########...
+    0b0011111111111000, // ..###########...
+    0b0001111111110000, // ...#########....
+    0b0000111111100000, // ....#######.....
+    0b0000011111000000, // .....#####......
+    0b0000001110000000, // ......###.......
+    0b0000000100000000, // .......#........
+    0b0000000000000000, // ................
+    0b0000000000000000, // ................
+    0b0000000000000000, // ................
+    0b0000000000000000, // ................
+];
+
+// Frames the heart is shown at full size and contracted per beat
+const HEART_BEAT_FRAMES: u32 = 12;
+
+// Split a 16x16 pattern into top-left, top-right, bottom-left, bottom-right
+// tiles (1bpp rows, bit 0 = leftmost pixel)
+fn split_hud_tiles(pattern: &[u16; 16]) -> [[u8; 8]; 4] {
+    let mut tiles = [[0u8; 8]; 4];
+    for (tile_index, tile) in tiles.iter_mut().enumerate() {
+        let tile_x = (tile_index % 2) * 8;
+        let tile_y = (tile_index / 2) * 8;
+        for (row, bits) in tile.iter_mut().enumerate() {
+            let pattern = pattern[tile_y + row];
+            for col in 0..8 {
+                if pattern & (1 << (15 - (tile_x + col))) != 0 {
+                    *bits |= 1 << col;
+                }
+            }
+        }
+    }
+    tiles
+}
+
+// Heart tiles 0-3, then the beat frame's tiles 4-7
+pub fn heart_hud_tiles() -> [[u8; 8]; 8] {
+    let mut tiles = [[0u8; 8]; 8];
+    tiles[..4].copy_from_slice(&split_hud_tiles(&HEART_PATTERN));
+    tiles[4..].copy_from_slice(&split_hud_tiles(&HEART_BEAT_PATTERN));
+    tiles
+}
+
+// The heart beats: three steps at full size, one contracted
+pub fn heart_tile_animations() -> Vec<TileAnimation> {
+    (0..4)
+        .map(|tile| TileAnimation {
+            base_id: tile,
+            frames: vec![tile, tile, tile, tile + 4],
+            frame_duration: HEART_BEAT_FRAMES,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{AnimationLibrary, PlaybackMode};
+
+    #[test]
+    fn test_animations_are_valid_zsa() {
+        let library = AnimationLibrary::from_bytes(&hambert_animations()).unwrap();
+        let hop = library.get(BLOOD_GOBLIN_HOP).unwrap();
+        assert_eq!(hop.mode, PlaybackMode::Loop);
+        assert_eq!(hop.frames[0].event, Some(EVENT_HOP_RIGHT));
+        assert_eq!(library.get(HAMBERRY_COLLECT).unwrap().mode, PlaybackMode::Once);
+    }
+}
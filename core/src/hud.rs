@@ -0,0 +1,183 @@
+use crate::font_system::{get_font_data, FontSystem};
+use crate::tile_animation::TileAnimator;
+
+// HUD/window layer - a grid of 8x8 cells covering the screen (40x30 in the
+// standard video mode), drawn on top of the game world without scrolling.
+// Cells hold either a font glyph (so the font system can write score, lives,
+// timers and menus into it) or one of the cartridge's custom 1bpp tiles
+// (icons, borders, meters).
+
+pub const HUD_TILE_SIZE: usize = 8;
+pub const MAX_CUSTOM_TILES: usize = 256;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum HudTile {
+    Empty,
+    Glyph(usize),  // Font system glyph index
+    Custom(usize), // Cartridge-uploaded tile
+}
+
+#[derive(Clone, Copy)]
+pub struct HudCell {
+    pub tile: HudTile,
+    pub color: u8, // Index into the HUD palette, or the master palette when it has none
+}
+
+impl HudCell {
+    pub const EMPTY: HudCell = HudCell { tile: HudTile::Empty, color: 0 };
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum HudPriority {
+    AboveSprites,
+    BelowSprites,
+}
+
+pub struct HudLayer {
+    cells: Vec<HudCell>,
+    columns: usize,
+    rows: usize,
+    custom_tiles: Vec<[u8; 8]>,
+    pub enabled: bool,
+    pub palette: Option<u8>, // Background palette 0-7 the cell colors index into
+    pub priority: HudPriority,
+    pub window_top: usize,   // Rows drawn by the layer (a status bar is a short window)
+    pub window_bottom: usize,
+    pub fill: Option<u8>,    // Color behind empty cells inside the window, None = see-through
+    pub tile_animations: TileAnimator, // Animated custom tiles, stepped by the PPU
+}
+
+impl HudLayer {
+    // A layer covering a screen of the given size in pixels
+    pub fn for_screen(width: usize, height: usize) -> HudLayer {
+        let (columns, rows) = (width / HUD_TILE_SIZE, height / HUD_TILE_SIZE);
+        HudLayer {
+            cells: vec![HudCell::EMPTY; columns * rows],
+            columns,
+            rows,
+            custom_tiles: Vec::new(),
+            enabled: true,
+            palette: None,
+            priority: HudPriority::AboveSprites,
+            window_top: 0,
+            window_bottom: rows - 1,
+            fill: None,
+            tile_animations: TileAnimator::new(),
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    // Follow a video mode change. Cells keep their position; ones off the new
+    // grid are dropped. A window reaching the bottom row still does.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let (columns, rows) = (width / HUD_TILE_SIZE, height / HUD_TILE_SIZE);
+        let mut cells = vec![HudCell::EMPTY; columns * rows];
+        for row in 0..rows.min(self.rows) {
+            for column in 0..columns.min(self.columns) {
+                cells[row * columns + column] = self.cells[row * self.columns + column];
+            }
+        }
+        if self.window_bottom >= self.rows - 1 {
+            self.window_bottom = rows - 1;
+        }
+        self.cells = cells;
+        self.columns = columns;
+        self.rows = rows;
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.iter_mut().for_each(|cell| *cell = HudCell::EMPTY);
+    }
+
+    pub fn set_cell(&mut self, column: usize, row: usize, cell: HudCell) {
+        if column < self.columns && row < self.rows {
+            self.cells[row * self.columns + column] = cell;
+        }
+    }
+
+    pub fn cell(&self, column: usize, row: usize) -> HudCell {
+        if column < self.columns && row < self.rows {
+            self.cells[row * self.columns + column]
+        } else {
+            HudCell::EMPTY
+        }
+    }
+
+    // Write text with the font system's current language, clipped at the
+    // right edge. Returns the number of cells written.
+    pub fn write_text(&mut self, font_system: &FontSystem, column: usize, row: usize, text: &str, color: u8) -> usize {
+        let characters = font_system.encode_text(text);
+        let mut written = 0;
+        for (i, character) in characters.iter().enumerate() {
+            if column + i >= self.columns {
+                break;
+            }
+            self.set_cell(column + i, row, HudCell { tile: HudTile::Glyph(character.glyph_index), color });
+            written += 1;
+        }
+        written
+    }
+
+    pub fn upload_tile(&mut self, index: usize, data: [u8; 8]) -> bool {
+        if index >= MAX_CUSTOM_TILES {
+            return false;
+        }
+        if index >= self.custom_tiles.len() {
+            self.custom_tiles.resize(index + 1, [0; 8]);
+        }
+        self.custom_tiles[index] = data;
+        true
+    }
+
+    // 1bpp rows for a tile, bit 0 = leftmost pixel (same layout as the font).
+    // Animated custom tiles give their current frame.
+    pub fn tile_data(&self, tile: HudTile) -> Option<&[u8; 8]> {
+        match tile {
+            HudTile::Empty => None,
+            HudTile::Glyph(glyph_index) => get_font_data(glyph_index),
+            HudTile::Custom(index) => self.custom_tiles.get(self.tile_animations.resolve(index as u32) as usize),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_text_clips_at_edge() {
+        let mut hud = HudLayer::for_screen(320, 240);
+        let font_system = FontSystem::new();
+
+        assert_eq!(hud.write_text(&font_system, 36, 0, "SCORE", 15), 4);
+        assert!(hud.cell(36, 0).tile == HudTile::Glyph((b'S' - 32) as usize));
+        assert!(hud.cell(39, 0).tile == HudTile::Glyph((b'R' - 32) as usize));
+        assert!(hud.cell(0, 1).tile == HudTile::Empty);
+    }
+
+    #[test]
+    fn test_resize_follows_video_mode() {
+        let mut hud = HudLayer::for_screen(320, 240);
+        let font_system = FontSystem::new();
+        hud.write_text(&font_system, 0, 29, "HI", 15);
+        hud.write_text(&font_system, 36, 0, "MAP", 15);
+
+        // 256x224: 32x28 cells, the bottom row and right edge are cut off
+        hud.resize(256, 224);
+        assert_eq!((hud.columns(), hud.rows(), hud.window_bottom), (32, 28, 27));
+        assert!(hud.cell(36, 0).tile == HudTile::Empty);
+        assert_eq!(hud.write_text(&font_system, 30, 0, "MAP", 15), 2);
+
+        // 320x480: twice the rows, cells keep their place
+        hud.resize(320, 480);
+        assert_eq!((hud.columns(), hud.rows(), hud.window_bottom), (40, 60, 59));
+        assert!(hud.cell(30, 0).tile == HudTile::Glyph((b'M' - 32) as usize));
+    }
+}
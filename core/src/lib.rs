@@ -3,6 +3,16 @@ mod cpu;
 mod ppu;
 mod ppu_clean;
 mod apu;
+mod apu_channels;
+mod wavetable;
+mod modulation;
+mod sid;
+mod music;
+mod hambert_music;
+mod hambert_sfx;
+mod sample_bank;
+mod sfx;
+mod mixer;
 mod memory;
 mod system;
 mod system_cartridge;
@@ -10,6 +20,20 @@ mod cartridge;
 mod sprite_converter;
 mod laugh_sample;
 mod font_system;
+mod palette;
+mod sprite_bank;
+mod metasprite;
+mod hambert_sprites;
+mod animation;
+mod mode7;
+mod tile_animation;
+mod color_math;
+mod hud;
+mod dirty;
+mod post_process;
+mod video_mode;
+mod transition;
+mod debug_view;
 
 use wasm_bindgen::prelude::*;
 
@@ -34,4 +58,7 @@ pub use system::ZebratronSystem;
 pub use system_cartridge::ZebratronCartridgeSystem;
 pub use cartridge::HambertCartridge;
 pub use cartridge::ZSynthCartridge;
-pub use sprite_converter::{SpriteConverter, SpriteData};
\ No newline at end of file
+pub use sprite_converter::{SpriteConverter, SpriteData};
+
+// .zsm cell values, for the MIDI converter
+pub use music::{EFFECT_SET_SPEED, EFFECT_SET_VOLUME, NOTE_OFF};
\ No newline at end of file
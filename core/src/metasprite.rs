@@ -0,0 +1,127 @@
+// Metasprites - large characters built from several hardware sprites. Each
+// piece is a sprite bank pattern at an offset inside the group's box, with its
+// own flips; the group has one position and one flip, and flipping the group
+// mirrors the piece offsets as well as the pieces.
+//
+// Hardware sprite rules: a sprite shows at most a 32x32 pattern, 128 sprites
+// can be shown per frame, and only 16 sprites can cover any one scanline.
+// Every metasprite piece counts as a sprite against those limits.
+
+pub const MAX_SPRITE_SIZE: u32 = 32;
+pub const MAX_HARDWARE_SPRITES: usize = 128;
+pub const SPRITES_PER_SCANLINE: usize = 16;
+
+#[derive(Clone, Copy)]
+pub struct MetaSpritePiece {
+    pub pattern_id: u32,
+    pub offset_x: i32, // Top-left of the piece inside the unflipped group
+    pub offset_y: i32,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+}
+
+#[derive(Clone)]
+pub struct MetaSprite {
+    pub width: u32, // Group box the flips mirror within
+    pub height: u32,
+    pub pieces: Vec<MetaSpritePiece>,
+}
+
+impl MetaSprite {
+    // Pieces as placed for a group flip. `piece_size` gives a pattern's size.
+    pub fn placed_pieces<F: Fn(u32) -> Option<(u32, u32)>>(&self, flip_horizontal: bool, flip_vertical: bool, piece_size: F) -> Vec<MetaSpritePiece> {
+        self.pieces
+            .iter()
+            .filter_map(|piece| {
+                let (width, height) = piece_size(piece.pattern_id)?;
+                let mut placed = *piece;
+                if flip_horizontal {
+                    placed.offset_x = self.width as i32 - piece.offset_x - width as i32;
+                    placed.flip_horizontal = !piece.flip_horizontal;
+                }
+                if flip_vertical {
+                    placed.offset_y = self.height as i32 - piece.offset_y - height as i32;
+                    placed.flip_vertical = !piece.flip_vertical;
+                }
+                Some(placed)
+            })
+            .collect()
+    }
+}
+
+// Per-scanline sprite counts for one frame. Sprites claim lines in priority
+// order; once a line has 16 sprites, later sprites drop out on that line.
+pub struct ScanlineSpriteCounter {
+    counts: Vec<u8>,
+    overflow: bool,
+}
+
+impl ScanlineSpriteCounter {
+    pub fn new(height: usize) -> ScanlineSpriteCounter {
+        ScanlineSpriteCounter {
+            counts: vec![0; height],
+            overflow: false,
+        }
+    }
+
+    // Claim lines top..bottom (exclusive, clipped to the screen). `visible`
+    // is set per screen line to whether the sprite won a slot there.
+    pub fn claim(&mut self, top: i32, bottom: i32, visible: &mut [bool]) {
+        visible.iter_mut().for_each(|line| *line = false);
+        let top = top.max(0) as usize;
+        let bottom = bottom.clamp(0, self.counts.len() as i32) as usize;
+        if top >= bottom {
+            return;
+        }
+        for (count, line_visible) in self.counts[top..bottom].iter_mut().zip(&mut visible[top..bottom]) {
+            if (*count as usize) < SPRITES_PER_SCANLINE {
+                *count += 1;
+                *line_visible = true;
+            } else {
+                self.overflow = true;
+            }
+        }
+    }
+
+    // Some line had more sprites than the limit
+    pub fn overflowed(&self) -> bool {
+        self.overflow
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_flip_mirrors_pieces() {
+        let meta = MetaSprite {
+            width: 24,
+            height: 16,
+            pieces: vec![
+                MetaSpritePiece { pattern_id: 1, offset_x: 0, offset_y: 0, flip_horizontal: false, flip_vertical: false },
+                MetaSpritePiece { pattern_id: 2, offset_x: 16, offset_y: 0, flip_horizontal: true, flip_vertical: false },
+            ],
+        };
+        let sizes = |pattern_id| if pattern_id == 1 { Some((16, 16)) } else { Some((8, 16)) };
+
+        let placed = meta.placed_pieces(true, false, sizes);
+        assert_eq!((placed[0].offset_x, placed[0].flip_horizontal), (8, true));
+        assert_eq!((placed[1].offset_x, placed[1].flip_horizontal), (0, false));
+    }
+
+    #[test]
+    fn test_scanline_limit() {
+        let mut counter = ScanlineSpriteCounter::new(8);
+        let mut visible = vec![false; 8];
+        for _ in 0..SPRITES_PER_SCANLINE {
+            counter.claim(0, 4, &mut visible);
+        }
+        assert!(!counter.overflowed());
+
+        // Lines 0-3 are full, 4-5 still have room
+        counter.claim(2, 6, &mut visible);
+        assert_eq!(visible, [false, false, false, false, true, true, false, false]);
+        assert!(counter.overflowed());
+    }
+}
@@ -0,0 +1,28 @@
+// Stereo mixing. Every channel and voice has a pan position from -1.0 (hard
+// left) to 1.0 (hard right). The pan law keeps a centered source at full
+// level on both sides and turns the far side down as it moves off center, so
+// centered sounds are as loud as they were on the old mono mixer.
+
+pub fn pan_gains(pan: f32) -> (f32, f32) {
+    let pan = pan.clamp(-1.0, 1.0);
+    ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0))
+}
+
+// A mono level placed in the stereo field
+pub fn pan(level: f32, pan: f32) -> (f32, f32) {
+    let (left, right) = pan_gains(pan);
+    (level * left, level * right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pan_law() {
+        assert_eq!(pan(0.5, 0.0), (0.5, 0.5));
+        assert_eq!(pan(0.5, -1.0), (0.5, 0.0));
+        assert_eq!(pan(0.5, 0.5), (0.25, 0.5));
+        assert_eq!(pan_gains(3.0), (0.0, 1.0));
+    }
+}
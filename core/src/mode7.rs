@@ -0,0 +1,122 @@
+// Mode-7 style affine background layer. A pattern from the sprite bank is
+// used as the layer's texture and sampled through a 2x2 matrix around an
+// origin point. The matrix can be overridden per scanline, which is how
+// pseudo-3D floors are built: each line below the horizon gets its own scale.
+
+// 2x2 transform from screen space to texture space
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mode7Matrix {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+}
+
+impl Mode7Matrix {
+    pub const IDENTITY: Mode7Matrix = Mode7Matrix { a: 1.0, b: 0.0, c: 0.0, d: 1.0 };
+
+    // Matrix that shows the texture rotated by `angle` radians and zoomed by
+    // `scale` (2.0 = twice as big on screen)
+    pub fn rotate_scale(angle: f32, scale_x: f32, scale_y: f32) -> Mode7Matrix {
+        let (sin, cos) = angle.sin_cos();
+        let inv_x = if scale_x != 0.0 { 1.0 / scale_x } else { 0.0 };
+        let inv_y = if scale_y != 0.0 { 1.0 / scale_y } else { 0.0 };
+        Mode7Matrix {
+            a: cos * inv_x,
+            b: sin * inv_x,
+            c: -sin * inv_y,
+            d: cos * inv_y,
+        }
+    }
+}
+
+pub struct Mode7Layer {
+    pub enabled: bool,
+    pub pattern_id: u32,     // Sprite bank pattern used as the layer texture
+    pub palette: Option<u8>, // Background palette 0-7, None = master palette
+    pub wrap: bool,          // Repeat the texture instead of leaving the outside transparent
+    pub matrix: Mode7Matrix,
+    pub origin_x: f32,       // Center of rotation/scaling in texture space
+    pub origin_y: f32,
+    pub scroll_x: f32,
+    pub scroll_y: f32,
+    pub first_line: usize,   // Scanline window the layer is drawn in (e.g. below a horizon)
+    pub last_line: usize,
+    line_matrices: Vec<Option<Mode7Matrix>>,
+}
+
+impl Mode7Layer {
+    pub fn new(screen_height: usize) -> Mode7Layer {
+        Mode7Layer {
+            enabled: false,
+            pattern_id: 0,
+            palette: None,
+            wrap: true,
+            matrix: Mode7Matrix::IDENTITY,
+            origin_x: 0.0,
+            origin_y: 0.0,
+            scroll_x: 0.0,
+            scroll_y: 0.0,
+            first_line: 0,
+            last_line: screen_height - 1,
+            line_matrices: vec![None; screen_height],
+        }
+    }
+
+    // Match a new framebuffer height (video mode change)
+    pub fn resize(&mut self, screen_height: usize) {
+        self.line_matrices.resize(screen_height, None);
+        self.first_line = self.first_line.min(screen_height - 1);
+        self.last_line = screen_height - 1;
+    }
+
+    // Override the matrix for one scanline; stays in effect until cleared
+    pub fn set_line_matrix(&mut self, line: usize, matrix: Mode7Matrix) {
+        if let Some(entry) = self.line_matrices.get_mut(line) {
+            *entry = Some(matrix);
+        }
+    }
+
+    pub fn clear_line_matrices(&mut self) {
+        self.line_matrices.iter_mut().for_each(|entry| *entry = None);
+    }
+
+    pub fn matrix_for_line(&self, line: usize) -> Mode7Matrix {
+        self.line_matrices.get(line).copied().flatten().unwrap_or(self.matrix)
+    }
+
+    // Texture coordinate seen by screen pixel (x, line)
+    pub fn texture_coords(&self, x: usize, line: usize) -> (f32, f32) {
+        let m = self.matrix_for_line(line);
+        let dx = x as f32 + self.scroll_x - self.origin_x;
+        let dy = line as f32 + self.scroll_y - self.origin_y;
+        (
+            m.a * dx + m.b * dy + self.origin_x,
+            m.c * dx + m.d * dy + self.origin_y,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_texture_coords() {
+        let mut layer = Mode7Layer::new(240);
+        layer.scroll_x = 10.0;
+        assert_eq!(layer.texture_coords(5, 7), (15.0, 7.0));
+
+        // Zooming in 2x around the origin halves texture distances
+        layer.scroll_x = 0.0;
+        layer.origin_x = 100.0;
+        layer.origin_y = 100.0;
+        layer.matrix = Mode7Matrix::rotate_scale(0.0, 2.0, 2.0);
+        assert_eq!(layer.texture_coords(120, 80), (110.0, 90.0));
+
+        // Per-scanline override only affects its own line
+        layer.set_line_matrix(80, Mode7Matrix::IDENTITY);
+        assert_eq!(layer.texture_coords(120, 80), (120.0, 80.0));
+        assert_eq!(layer.texture_coords(120, 81), (110.0, 90.5));
+    }
+}
@@ -0,0 +1,249 @@
+// Voice modulation - ADSR envelopes and LFOs for the APU's digital
+// oscillators. The envelope shapes a note's volume from note-on through
+// release; each LFO is a slow oscillator routed to one parameter of the voice
+// (vibrato, PWM, tremolo or filter sweeps). Times are in seconds and
+// everything advances once per output sample.
+
+pub const LFOS_PER_VOICE: usize = 2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum EnvelopeStage {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    Off,
+}
+
+#[derive(Clone, Copy)]
+pub struct Adsr {
+    pub attack: f32,  // Seconds from silence to full level
+    pub decay: f32,   // Seconds from full level down to sustain
+    pub sustain: f32, // Level held while the note is down, 0.0 to 1.0
+    pub release: f32, // Seconds from full level to silence after note-off
+    stage: EnvelopeStage,
+    level: f32,
+}
+
+impl Adsr {
+    pub fn new(attack: f32, decay: f32, sustain: f32, release: f32) -> Adsr {
+        Adsr {
+            attack: attack.max(0.0),
+            decay: decay.max(0.0),
+            sustain: sustain.clamp(0.0, 1.0),
+            release: release.max(0.0),
+            stage: EnvelopeStage::Off,
+            level: 0.0,
+        }
+    }
+
+    // Plain on/off gate already holding at full level, so voices that are
+    // switched on and off directly sound exactly as they did without one
+    pub fn gate() -> Adsr {
+        Adsr {
+            stage: EnvelopeStage::Sustain,
+            level: 1.0,
+            ..Adsr::new(0.0, 0.0, 1.0, 0.0)
+        }
+    }
+
+    // Change the shape without restarting the note
+    pub fn set_shape(&mut self, shape: &Adsr) {
+        self.attack = shape.attack;
+        self.decay = shape.decay;
+        self.sustain = shape.sustain;
+        self.release = shape.release;
+    }
+
+    // Note-on: attack from the current level, so retriggering doesn't click
+    pub fn trigger(&mut self) {
+        self.stage = EnvelopeStage::Attack;
+    }
+
+    // Note-off
+    pub fn release(&mut self) {
+        if self.stage != EnvelopeStage::Off {
+            self.stage = EnvelopeStage::Release;
+        }
+    }
+
+    // Cut to silence at once, without a release
+    pub fn reset(&mut self) {
+        self.stage = EnvelopeStage::Off;
+        self.level = 0.0;
+    }
+
+    // True from note-on until note-off
+    pub fn is_held(&self) -> bool {
+        matches!(self.stage, EnvelopeStage::Attack | EnvelopeStage::Decay | EnvelopeStage::Sustain)
+    }
+
+    // True once the release has faded out
+    pub fn is_finished(&self) -> bool {
+        self.stage == EnvelopeStage::Off
+    }
+
+    // Advance one sample and return the level
+    pub fn next_level(&mut self, sample_rate: f32) -> f32 {
+        match self.stage {
+            EnvelopeStage::Attack => {
+                self.level += step(1.0, self.attack, sample_rate);
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = EnvelopeStage::Decay;
+                }
+            }
+            EnvelopeStage::Decay => {
+                self.level -= step(1.0 - self.sustain, self.decay, sample_rate);
+                if self.level <= self.sustain {
+                    self.level = self.sustain;
+                    self.stage = EnvelopeStage::Sustain;
+                }
+            }
+            EnvelopeStage::Sustain => {}
+            EnvelopeStage::Release => {
+                self.level -= step(1.0, self.release, sample_rate);
+                if self.level <= 0.0 {
+                    self.level = 0.0;
+                    self.stage = EnvelopeStage::Off;
+                }
+            }
+            EnvelopeStage::Off => self.level = 0.0,
+        }
+        self.level
+    }
+}
+
+// Per-sample change to cover `distance` in `seconds` (instant for zero)
+fn step(distance: f32, seconds: f32, sample_rate: f32) -> f32 {
+    if seconds <= 0.0 {
+        f32::MAX
+    } else {
+        distance / (seconds * sample_rate)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LfoTarget {
+    Off,
+    Pitch,        // Vibrato, depth in semitones
+    PulseWidth,   // PWM, depth as a fraction of the pulse width range
+    Volume,       // Tremolo, depth 0.0 to 1.0
+    FilterCutoff, // Filter sweep, depth in normalized cutoff
+}
+
+impl LfoTarget {
+    pub fn from_u8(target: u8) -> Option<LfoTarget> {
+        match target {
+            0 => Some(LfoTarget::Off),
+            1 => Some(LfoTarget::Pitch),
+            2 => Some(LfoTarget::PulseWidth),
+            3 => Some(LfoTarget::Volume),
+            4 => Some(LfoTarget::FilterCutoff),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Lfo {
+    pub target: LfoTarget,
+    pub waveform: u8, // 0=sine, 1=triangle, 2=square, 3=saw
+    pub rate: f32,    // Hz
+    pub depth: f32,
+    phase: f32,
+}
+
+impl Lfo {
+    pub const OFF: Lfo = Lfo {
+        target: LfoTarget::Off,
+        waveform: 0,
+        rate: 5.0,
+        depth: 0.0,
+        phase: 0.0,
+    };
+
+    pub fn new(target: LfoTarget, waveform: u8, rate: f32, depth: f32) -> Lfo {
+        // Past full depth tremolo would swing the gain below zero
+        let depth = if target == LfoTarget::Volume { depth.clamp(-1.0, 1.0) } else { depth };
+        Lfo {
+            target,
+            waveform: waveform.clamp(0, 3),
+            rate: rate.max(0.0),
+            depth,
+            phase: 0.0,
+        }
+    }
+
+    // Advance one sample and return the scaled output, -depth to depth
+    pub fn next_value(&mut self, sample_rate: f32) -> f32 {
+        if self.target == LfoTarget::Off {
+            return 0.0;
+        }
+        let value = match self.waveform {
+            0 => (self.phase * std::f32::consts::TAU).sin(),
+            1 => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+            2 => if self.phase < 0.5 { 1.0 } else { -1.0 },
+            _ => 2.0 * self.phase - 1.0,
+        };
+        self.phase = (self.phase + self.rate / sample_rate).fract();
+        value * self.depth
+    }
+}
+
+// Parameter offsets from a voice's LFOs for one sample
+#[derive(Default)]
+pub struct Modulation {
+    pub pitch: f32, // Semitones
+    pub pulse_width: f32,
+    pub volume: f32, // Gain, 1.0 = unchanged
+    pub cutoff: f32,
+}
+
+impl Modulation {
+    pub fn from_lfos(lfos: &mut [Lfo], sample_rate: f32) -> Modulation {
+        let mut modulation = Modulation { volume: 1.0, ..Modulation::default() };
+        for lfo in lfos.iter_mut() {
+            let value = lfo.next_value(sample_rate);
+            match lfo.target {
+                LfoTarget::Off => {}
+                LfoTarget::Pitch => modulation.pitch += value,
+                LfoTarget::PulseWidth => modulation.pulse_width += value * 0.5,
+                // Tremolo dips below full volume rather than boosting above it
+                LfoTarget::Volume => modulation.volume *= 1.0 - (lfo.depth.abs() - value) * 0.5,
+                LfoTarget::FilterCutoff => modulation.cutoff += value,
+            }
+        }
+        modulation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_stages() {
+        // 10 Hz sample rate keeps the step counts small
+        let mut envelope = Adsr::new(0.2, 0.2, 0.5, 0.5);
+        assert_eq!(envelope.next_level(10.0), 0.0);
+        envelope.trigger();
+        let levels: Vec<f32> = (0..5).map(|_| envelope.next_level(10.0)).collect();
+        assert_eq!(levels, [0.5, 1.0, 0.75, 0.5, 0.5]);
+
+        envelope.release();
+        for _ in 0..5 {
+            envelope.next_level(10.0);
+        }
+        assert!(envelope.is_finished());
+        assert!(!Adsr::gate().is_finished());
+    }
+
+    #[test]
+    fn test_tremolo_never_inverts_the_volume() {
+        let mut lfos = [Lfo::new(LfoTarget::Volume, 2, 1.0, 4.0), Lfo::OFF];
+        // Square wave at 4 Hz: first half high, second half low
+        let volumes: Vec<f32> = (0..4).map(|_| Modulation::from_lfos(&mut lfos, 4.0).volume).collect();
+        assert_eq!(volumes, [1.0, 1.0, 0.0, 0.0]);
+    }
+}
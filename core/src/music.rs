@@ -0,0 +1,484 @@
+// Tracker music - the .zsm song format and the driver that plays it. The
+// driver runs once per video frame (like a driver called from VBlank) and
+// turns the song into per-channel note, pitch and volume changes that the APU
+// applies to its music voices.
+//
+// .zsm layout (all values are bytes):
+//
+//   Header, 12 bytes
+//     0-3   "ZSM" and format version 1
+//     4     channel count, 1-8
+//     5     rows per pattern, 1-255
+//     6     speed (ticks per row)
+//     7     tempo in BPM; 150 runs one tick per frame
+//     8     instrument count
+//     9     pattern count
+//     10    order count, at least 1
+//     11    restart order (where the song loops back to)
+//   Instruments, 8 bytes each
+//     waveform (0-4 as for the SID voices, or SID waveform bits $10-$F0),
+//     volume 0-64, attack, decay (frames), sustain 0-64, release (frames),
+//     pulse width (0-255 of the period), reserved
+//   Order list, order count x channel count pattern numbers. Each channel
+//     plays its own pattern in every order.
+//   Patterns, pattern count x rows cells of 4 bytes:
+//     note    0 = none, 1-127 = MIDI note, $FF = note off
+//     instr   0 = none, 1+ = instrument number
+//     effect, parameter (see the EFFECT_* constants; 0 with 0 = no effect)
+
+use crate::modulation::Adsr;
+
+pub const MAX_MUSIC_CHANNELS: usize = 8;
+pub const NOTE_OFF: u8 = 0xFF;
+
+const HEADER_SIZE: usize = 12;
+const INSTRUMENT_SIZE: usize = 8;
+const CELL_SIZE: usize = 4;
+const FRAMES_PER_SECOND: f32 = 60.0;
+const FRAME_TEMPO: f32 = 150.0; // BPM that gives one tick per frame
+
+pub const EFFECT_ARPEGGIO: u8 = 0x0;     // xy: cycle note, +x, +y semitones each tick
+pub const EFFECT_SLIDE_UP: u8 = 0x1;     // xx: 1/16 semitones per tick
+pub const EFFECT_SLIDE_DOWN: u8 = 0x2;   // xx: 1/16 semitones per tick
+pub const EFFECT_PORTAMENTO: u8 = 0x3;   // xx: slide to the row's note at 1/16 semitones per tick
+pub const EFFECT_VIBRATO: u8 = 0x4;      // xy: speed x, depth y/16 semitones
+pub const EFFECT_SET_PAN: u8 = 0x8;      // xx: $00 left, $80 center, $FF right
+pub const EFFECT_VOLUME_SLIDE: u8 = 0xA; // xy: volume up x or down y per tick
+pub const EFFECT_JUMP: u8 = 0xB;         // xx: continue from order xx
+pub const EFFECT_SET_VOLUME: u8 = 0xC;   // xx: volume 0-64
+pub const EFFECT_BREAK: u8 = 0xD;        // xx: continue from row xx of the next order
+pub const EFFECT_SET_SPEED: u8 = 0xF;    // xx: below $20 ticks per row, otherwise tempo
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Instrument {
+    pub waveform: u8,
+    pub volume: u8, // 0-64
+    pub attack: u8, // Frames
+    pub decay: u8,
+    pub sustain: u8, // 0-64
+    pub release: u8,
+    pub pulse_width: u8,
+}
+
+impl Instrument {
+    pub fn envelope(&self) -> Adsr {
+        Adsr::new(
+            self.attack as f32 / FRAMES_PER_SECOND,
+            self.decay as f32 / FRAMES_PER_SECOND,
+            self.sustain as f32 / 64.0,
+            self.release as f32 / FRAMES_PER_SECOND,
+        )
+    }
+
+    pub fn pulse_width(&self) -> f32 {
+        self.pulse_width as f32 / 256.0
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Cell {
+    note: u8,
+    instrument: u8,
+    effect: u8,
+    param: u8,
+}
+
+pub struct Song {
+    channels: usize,
+    rows: usize,
+    speed: u8,
+    tempo: u8,
+    restart: usize,
+    instruments: Vec<Instrument>,
+    orders: Vec<Vec<usize>>, // Pattern per channel for each order
+    patterns: Vec<Vec<Cell>>,
+}
+
+impl Song {
+    pub fn from_bytes(data: &[u8]) -> Result<Song, String> {
+        if data.len() < HEADER_SIZE || &data[0..3] != b"ZSM" {
+            return Err(String::from("Not a .zsm file"));
+        }
+        if data[3] != 1 {
+            return Err(format!("Unsupported .zsm version {}", data[3]));
+        }
+
+        let channels = data[4] as usize;
+        let rows = data[5] as usize;
+        let (instrument_count, pattern_count, order_count) = (data[8] as usize, data[9] as usize, data[10] as usize);
+        if channels == 0 || channels > MAX_MUSIC_CHANNELS || rows == 0 || order_count == 0 {
+            return Err(String::from("Invalid .zsm header"));
+        }
+
+        let orders_start = HEADER_SIZE + instrument_count * INSTRUMENT_SIZE;
+        let patterns_start = orders_start + order_count * channels;
+        let expected = patterns_start + pattern_count * rows * CELL_SIZE;
+        if data.len() < expected {
+            return Err(format!(".zsm data is {} bytes, expected {}", data.len(), expected));
+        }
+
+        let instruments = data[HEADER_SIZE..orders_start]
+            .chunks_exact(INSTRUMENT_SIZE)
+            .map(|bytes| Instrument {
+                waveform: bytes[0],
+                volume: bytes[1].min(64),
+                attack: bytes[2],
+                decay: bytes[3],
+                sustain: bytes[4].min(64),
+                release: bytes[5],
+                pulse_width: bytes[6],
+            })
+            .collect();
+
+        let orders: Vec<Vec<usize>> = data[orders_start..patterns_start]
+            .chunks_exact(channels)
+            .map(|order| order.iter().map(|&pattern| pattern as usize).collect())
+            .collect();
+        if orders.iter().flatten().any(|&pattern| pattern >= pattern_count) {
+            return Err(String::from("Order list refers to a missing pattern"));
+        }
+
+        let patterns = data[patterns_start..expected]
+            .chunks_exact(rows * CELL_SIZE)
+            .map(|pattern| {
+                pattern
+                    .chunks_exact(CELL_SIZE)
+                    .map(|cell| Cell { note: cell[0], instrument: cell[1], effect: cell[2], param: cell[3] })
+                    .collect()
+            })
+            .collect();
+
+        Ok(Song {
+            channels,
+            rows,
+            speed: data[6].max(1),
+            tempo: data[7].max(32),
+            restart: (data[11] as usize).min(order_count - 1),
+            instruments,
+            orders,
+            patterns,
+        })
+    }
+
+    fn cell(&self, order: usize, channel: usize, row: usize) -> Cell {
+        self.patterns[self.orders[order][channel]][row]
+    }
+}
+
+// What a music voice should do after this frame
+#[derive(Clone, Copy, Default)]
+pub struct ChannelOutput {
+    pub trigger: Option<Instrument>, // Start a note with this instrument
+    pub release: bool,               // Release the playing note
+    pub frequency: f32,
+    pub volume: f32, // Including music volume and fade, 0.0 to 1.0
+    pub pan: f32,    // -1.0 (left) to 1.0 (right)
+}
+
+#[derive(Clone, Copy, Default)]
+struct Channel {
+    instrument: Option<Instrument>,
+    pitch: f32,        // Current note in (fractional) semitones
+    target_pitch: f32, // Portamento destination
+    has_note: bool,    // A note has played, so portamento has a pitch to slide from
+    volume: u8,        // 0-64
+    effect: u8,
+    param: u8,
+    vibrato_speed: u8,
+    vibrato_depth: u8,
+    vibrato_phase: u8,
+    offset: f32, // Arpeggio/vibrato offset this tick, semitones
+    output: ChannelOutput,
+}
+
+#[derive(Default)]
+pub struct MusicDriver {
+    songs: Vec<Song>,
+    current: Option<usize>,
+    order: usize,
+    row: usize,
+    tick: u8,
+    speed: u8,
+    tempo: u8,
+    tick_accumulator: f32,
+    jump: Option<(usize, usize)>, // Order and row to continue from after this row
+    channels: Vec<Channel>,
+    volume: f32,
+    fade_step: f32, // Volume lost per frame while fading out
+    fade_level: f32,
+}
+
+impl MusicDriver {
+    pub fn new() -> MusicDriver {
+        MusicDriver {
+            volume: 0.7,
+            fade_level: 1.0,
+            ..MusicDriver::default()
+        }
+    }
+
+    // Returns the new song's ID
+    pub fn load(&mut self, song: Song) -> u32 {
+        self.songs.push(song);
+        (self.songs.len() - 1) as u32
+    }
+
+    // Forget every loaded song, so IDs start from 0 again
+    pub fn clear(&mut self) {
+        self.stop();
+        self.songs.clear();
+    }
+
+    pub fn play(&mut self, id: u32) -> bool {
+        let Some(song) = self.songs.get(id as usize) else {
+            return false;
+        };
+        self.current = Some(id as usize);
+        self.order = 0;
+        self.row = 0;
+        self.tick = 0;
+        self.speed = song.speed;
+        self.tempo = song.tempo;
+        self.tick_accumulator = 0.0;
+        self.jump = None;
+        self.channels = vec![Channel { volume: 64, ..Channel::default() }; song.channels];
+        self.fade_step = 0.0;
+        self.fade_level = 1.0;
+        true
+    }
+
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.channels.clear();
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.current.is_some()
+    }
+
+    pub fn fade_out(&mut self, frames: u32) {
+        if frames == 0 {
+            self.stop();
+        } else {
+            self.fade_step = self.fade_level / frames as f32;
+        }
+    }
+
+    pub fn set_tempo(&mut self, tempo: u8) {
+        self.tempo = tempo.max(32);
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    // Advance one video frame. Returns false once nothing is playing.
+    pub fn step_frame(&mut self) -> bool {
+        let Some(current) = self.current else {
+            return false;
+        };
+        for channel in &mut self.channels {
+            channel.output.trigger = None;
+            channel.output.release = false;
+        }
+
+        self.tick_accumulator += self.tempo as f32 / FRAME_TEMPO;
+        while self.tick_accumulator >= 1.0 {
+            self.tick_accumulator -= 1.0;
+            self.tick(current);
+        }
+
+        if self.fade_step > 0.0 {
+            self.fade_level -= self.fade_step;
+            if self.fade_level <= 0.0 {
+                self.stop();
+                return false;
+            }
+        }
+
+        let volume = self.volume * self.fade_level;
+        for channel in &mut self.channels {
+            channel.output.frequency = 440.0 * 2.0_f32.powf((channel.pitch + channel.offset - 69.0) / 12.0);
+            channel.output.volume = channel.volume as f32 / 64.0 * volume;
+        }
+        true
+    }
+
+    pub fn channel_outputs(&self) -> impl Iterator<Item = &ChannelOutput> {
+        self.channels.iter().map(|channel| &channel.output)
+    }
+
+    fn tick(&mut self, current: usize) {
+        if self.tick == 0 {
+            self.play_row(current);
+        } else {
+            self.apply_tick_effects();
+        }
+
+        self.tick += 1;
+        if self.tick >= self.speed {
+            self.tick = 0;
+            self.advance_row(current);
+        }
+    }
+
+    fn play_row(&mut self, current: usize) {
+        let song = &self.songs[current];
+        for (index, channel) in self.channels.iter_mut().enumerate() {
+            let cell = song.cell(self.order, index, self.row);
+            channel.effect = cell.effect;
+            channel.param = cell.param;
+            channel.offset = 0.0;
+
+            if let Some(&instrument) = (cell.instrument as usize).checked_sub(1).and_then(|i| song.instruments.get(i)) {
+                channel.instrument = Some(instrument);
+                channel.volume = instrument.volume;
+            }
+
+            match cell.note {
+                0 => {}
+                NOTE_OFF => channel.output.release = true,
+                note if cell.effect == EFFECT_PORTAMENTO && channel.has_note => channel.target_pitch = note as f32,
+                note => {
+                    // Also where portamento starts when the channel has
+                    // nothing to slide from yet
+                    channel.pitch = note as f32;
+                    channel.target_pitch = note as f32;
+                    channel.has_note = true;
+                    channel.vibrato_phase = 0;
+                    channel.output.trigger = channel.instrument.or(song.instruments.first().copied());
+                }
+            }
+
+            match cell.effect {
+                EFFECT_VIBRATO if cell.param != 0 => {
+                    channel.vibrato_speed = cell.param >> 4;
+                    channel.vibrato_depth = cell.param & 0x0F;
+                }
+                EFFECT_JUMP => self.jump = Some((cell.param as usize, 0)),
+                EFFECT_SET_PAN => channel.output.pan = ((cell.param as f32 - 128.0) / 127.0).max(-1.0),
+                EFFECT_SET_VOLUME => channel.volume = cell.param.min(64),
+                EFFECT_BREAK => self.jump = Some((self.order + 1, cell.param as usize)),
+                EFFECT_SET_SPEED if cell.param >= 0x20 => self.tempo = cell.param,
+                EFFECT_SET_SPEED if cell.param > 0 => self.speed = cell.param,
+                _ => {}
+            }
+        }
+    }
+
+    fn apply_tick_effects(&mut self) {
+        let tick = self.tick;
+        for channel in &mut self.channels {
+            let param = channel.param as f32;
+            let (x, y) = (channel.param >> 4, channel.param & 0x0F);
+            match channel.effect {
+                EFFECT_ARPEGGIO if channel.param != 0 => {
+                    channel.offset = [0, x, y][tick as usize % 3] as f32;
+                }
+                EFFECT_SLIDE_UP => channel.pitch += param / 16.0,
+                EFFECT_SLIDE_DOWN => channel.pitch -= param / 16.0,
+                EFFECT_PORTAMENTO => {
+                    let step = param / 16.0;
+                    let distance = channel.target_pitch - channel.pitch;
+                    channel.pitch += distance.clamp(-step, step);
+                }
+                EFFECT_VIBRATO => {
+                    channel.vibrato_phase = channel.vibrato_phase.wrapping_add(channel.vibrato_speed * 4);
+                    let angle = channel.vibrato_phase as f32 / 256.0 * std::f32::consts::TAU;
+                    channel.offset = angle.sin() * channel.vibrato_depth as f32 / 16.0;
+                }
+                EFFECT_VOLUME_SLIDE => {
+                    channel.volume = (channel.volume as i16 + x as i16 - y as i16).clamp(0, 64) as u8;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn advance_row(&mut self, current: usize) {
+        let song = &self.songs[current];
+        let (order, row) = match self.jump.take() {
+            Some(jump) => jump,
+            None if self.row + 1 < song.rows => (self.order, self.row + 1),
+            None => (self.order + 1, 0),
+        };
+        // Past the end of the order list loops to the restart order
+        self.order = if order < song.orders.len() { order } else { song.restart };
+        self.row = row.min(song.rows - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One channel, 4 rows, speed 2: C4, pan left, note off, jump back to order 0
+    fn test_song() -> Vec<u8> {
+        let mut data = vec![b'Z', b'S', b'M', 1, 1, 4, 2, 150, 1, 1, 1, 0];
+        data.extend_from_slice(&[1, 48, 0, 0, 64, 0, 128, 0]);
+        data.push(0);
+        data.extend_from_slice(&[60, 1, EFFECT_SLIDE_UP, 16]);
+        data.extend_from_slice(&[0, 0, EFFECT_SET_PAN, 0x00]);
+        data.extend_from_slice(&[NOTE_OFF, 0, 0, 0]);
+        data.extend_from_slice(&[0, 0, EFFECT_JUMP, 0]);
+        data
+    }
+
+    #[test]
+    fn test_driver_plays_rows_and_effects() {
+        assert!(Song::from_bytes(&test_song()[..20]).is_err());
+
+        let mut driver = MusicDriver::new();
+        let id = driver.load(Song::from_bytes(&test_song()).unwrap());
+        assert!(driver.play(id));
+        driver.set_volume(1.0);
+
+        // Row 0: note triggers at instrument volume, then slides up a semitone
+        driver.step_frame();
+        let output = *driver.channel_outputs().next().unwrap();
+        assert!(output.trigger.is_some());
+        assert_eq!(output.volume, 0.75);
+        driver.step_frame();
+        let slid = driver.channel_outputs().next().unwrap().frequency;
+        assert!((slid - 440.0 * 2.0_f32.powf((61.0 - 69.0) / 12.0)).abs() < 0.01);
+
+        // Rows 1-2: pan hard left, then note off on the row's first frame
+        driver.step_frame();
+        assert_eq!(driver.channel_outputs().next().unwrap().pan, -1.0);
+        driver.step_frame();
+        driver.step_frame();
+        assert!(driver.channel_outputs().next().unwrap().release);
+
+        // Row 3 jumps back to the start and retriggers
+        driver.step_frame();
+        driver.step_frame();
+        driver.step_frame();
+        driver.step_frame();
+        assert!(driver.channel_outputs().next().unwrap().trigger.is_some());
+
+        driver.fade_out(2);
+        driver.step_frame();
+        assert!(!driver.step_frame());
+        assert!(!driver.is_playing());
+    }
+
+    #[test]
+    fn test_portamento_without_a_note_starts_at_its_target() {
+        let mut data = test_song();
+        data[5] = 2; // Two rows
+        data.truncate(21);
+        data.extend_from_slice(&[60, 1, EFFECT_PORTAMENTO, 0x10]);
+        data.extend_from_slice(&[0, 0, EFFECT_PORTAMENTO, 0x10]);
+
+        let mut driver = MusicDriver::new();
+        let id = driver.load(Song::from_bytes(&data).unwrap());
+        driver.play(id);
+        let c4 = 440.0 * 2.0_f32.powf((60.0 - 69.0) / 12.0);
+        driver.step_frame();
+        assert!(driver.channel_outputs().next().unwrap().trigger.is_some());
+        for _ in 0..3 {
+            driver.step_frame();
+            assert!((driver.channel_outputs().next().unwrap().frequency - c4).abs() < 0.01);
+        }
+    }
+}
@@ -0,0 +1,357 @@
+// Palette hardware: the 256-color master palette and the palette RAM that
+// tiles and sprites index through. Palette RAM holds 16 sub-palettes
+// (8 background, 8 sprite) of up to 16 entries, each entry being an index
+// into the master palette. The hardware only allows 64 distinct master
+// colors to be referenced at once; writes that would exceed that are refused.
+
+pub const MASTER_PALETTE_SIZE: usize = 256;
+pub const SUB_PALETTE_SIZE: usize = 16;
+pub const BG_PALETTE_COUNT: usize = 8;
+pub const SPRITE_PALETTE_COUNT: usize = 8;
+pub const PALETTE_COUNT: usize = BG_PALETTE_COUNT + SPRITE_PALETTE_COUNT;
+pub const MAX_ON_SCREEN_COLORS: usize = 64;
+
+// 256-color master palette - the original 128 artist colors plus an
+// extended set for effects (water, lava, night skies, pastels)
+pub const MASTER_PALETTE: [(u8, u8, u8); MASTER_PALETTE_SIZE] = [
+    // Grayscale ramp (0-15)
+    (0, 0, 0), (17, 17, 17), (34, 34, 34), (51, 51, 51),
+    (68, 68, 68), (85, 85, 85), (102, 102, 102), (119, 119, 119),
+    (136, 136, 136), (153, 153, 153), (170, 170, 170), (187, 187, 187),
+    (204, 204, 204), (221, 221, 221), (238, 238, 238), (255, 255, 255),
+
+    // Reds (16-31)
+    (128, 0, 0), (160, 0, 0), (192, 0, 0), (224, 0, 0),
+    (255, 0, 0), (255, 32, 32), (255, 64, 64), (255, 96, 96),
+    (255, 128, 128), (255, 160, 160), (255, 192, 192), (255, 224, 224),
+    (128, 32, 0), (160, 64, 0), (192, 96, 32), (224, 128, 64),
+
+    // Oranges/Browns (32-47)
+    (255, 128, 0), (255, 160, 0), (255, 192, 0), (255, 224, 0),
+    (255, 255, 0), (224, 224, 0), (192, 192, 0), (160, 160, 0),
+    (128, 128, 0), (160, 128, 64), (192, 160, 96), (224, 192, 128),
+    (139, 69, 19), (160, 82, 45), (205, 133, 63), (222, 184, 135),
+
+    // Greens (48-63)
+    (0, 128, 0), (0, 160, 0), (0, 192, 0), (0, 224, 0),
+    (0, 255, 0), (32, 255, 32), (64, 255, 64), (96, 255, 96),
+    (128, 255, 128), (160, 255, 160), (192, 255, 192), (224, 255, 224),
+    (0, 128, 64), (0, 160, 96), (32, 192, 128), (64, 224, 160),
+
+    // Cyans (64-79)
+    (0, 255, 255), (0, 224, 224), (0, 192, 192), (0, 160, 160),
+    (0, 128, 128), (32, 160, 160), (64, 192, 192), (96, 224, 224),
+    (128, 255, 255), (160, 255, 255), (192, 255, 255), (224, 255, 255),
+    (0, 128, 96), (0, 160, 128), (32, 192, 160), (64, 224, 192),
+
+    // Blues (80-95)
+    (0, 0, 128), (0, 0, 160), (0, 0, 192), (0, 0, 224),
+    (0, 0, 255), (32, 32, 255), (64, 64, 255), (96, 96, 255),
+    (128, 128, 255), (160, 160, 255), (192, 192, 255), (224, 224, 255),
+    (0, 64, 128), (32, 96, 160), (64, 128, 192), (96, 160, 224),
+
+    // Purples/Magentas (96-111)
+    (128, 0, 128), (160, 0, 160), (192, 0, 192), (224, 0, 224),
+    (255, 0, 255), (255, 32, 255), (255, 64, 255), (255, 96, 255),
+    (255, 128, 255), (255, 160, 255), (255, 192, 255), (255, 224, 255),
+    (128, 0, 64), (160, 32, 96), (192, 64, 128), (224, 96, 160),
+
+    // Skin tones & earth tones (112-127)
+    (255, 220, 177), (255, 206, 158), (238, 180, 120), (210, 150, 95),
+    (180, 120, 80), (150, 100, 70), (120, 80, 60), (100, 70, 50),
+    (139, 115, 85), (160, 130, 98), (205, 175, 149), (222, 196, 176),
+    (245, 222, 179), (255, 228, 196), (255, 235, 205), (255, 248, 220),
+
+    // RGB cube, 4 levels per channel (128-191)
+    (36, 36, 36), (36, 36, 100), (36, 36, 164), (36, 36, 228),
+    (36, 100, 36), (36, 100, 100), (36, 100, 164), (36, 100, 228),
+    (36, 164, 36), (36, 164, 100), (36, 164, 164), (36, 164, 228),
+    (36, 228, 36), (36, 228, 100), (36, 228, 164), (36, 228, 228),
+    (100, 36, 36), (100, 36, 100), (100, 36, 164), (100, 36, 228),
+    (100, 100, 36), (100, 100, 100), (100, 100, 164), (100, 100, 228),
+    (100, 164, 36), (100, 164, 100), (100, 164, 164), (100, 164, 228),
+    (100, 228, 36), (100, 228, 100), (100, 228, 164), (100, 228, 228),
+    (164, 36, 36), (164, 36, 100), (164, 36, 164), (164, 36, 228),
+    (164, 100, 36), (164, 100, 100), (164, 100, 164), (164, 100, 228),
+    (164, 164, 36), (164, 164, 100), (164, 164, 164), (164, 164, 228),
+    (164, 228, 36), (164, 228, 100), (164, 228, 164), (164, 228, 228),
+    (228, 36, 36), (228, 36, 100), (228, 36, 164), (228, 36, 228),
+    (228, 100, 36), (228, 100, 100), (228, 100, 164), (228, 100, 228),
+    (228, 164, 36), (228, 164, 100), (228, 164, 164), (228, 164, 228),
+    (228, 228, 36), (228, 228, 100), (228, 228, 164), (228, 228, 228),
+
+    // Water ramp (192-207)
+    (0, 24, 48), (0, 30, 58), (0, 36, 68), (0, 42, 78),
+    (0, 48, 88), (0, 54, 98), (0, 60, 108), (0, 66, 118),
+    (0, 72, 128), (20, 90, 144), (40, 108, 160), (60, 126, 176),
+    (80, 144, 192), (100, 162, 208), (120, 180, 224), (140, 198, 240),
+
+    // Lava/fire ramp (208-223)
+    (40, 0, 0), (53, 4, 0), (66, 8, 0), (79, 12, 0),
+    (92, 16, 0), (105, 20, 0), (118, 24, 0), (131, 28, 0),
+    (255, 40, 0), (255, 66, 12), (255, 92, 24), (255, 118, 36),
+    (255, 144, 48), (255, 170, 60), (255, 196, 72), (255, 222, 84),
+
+    // Night/dusk ramp (224-239)
+    (8, 8, 24), (12, 11, 32), (16, 14, 40), (20, 17, 48),
+    (24, 20, 56), (28, 23, 64), (32, 26, 72), (36, 29, 80),
+    (40, 30, 80), (50, 36, 92), (60, 42, 104), (70, 48, 116),
+    (80, 54, 128), (90, 60, 140), (100, 66, 152), (110, 72, 164),
+
+    // Pastels (240-255)
+    (240, 200, 210), (240, 220, 200), (240, 240, 200), (210, 240, 200),
+    (200, 240, 220), (200, 240, 240), (200, 220, 240), (210, 200, 240),
+    (230, 200, 240), (240, 200, 230), (225, 225, 225), (200, 180, 160),
+    (180, 200, 160), (160, 180, 200), (200, 160, 180), (180, 160, 200),
+];
+
+// Palette rotation range the PPU steps by itself: every `frames_per_step`
+// frames entries start..start+length rotate by one (water, lava, torches).
+pub const MAX_PALETTE_CYCLES: usize = 8;
+
+#[derive(Clone, Copy)]
+pub struct PaletteCycle {
+    pub palette: usize,
+    pub start: usize,
+    pub length: usize,
+    pub frames_per_step: u32,
+}
+
+impl PaletteCycle {
+    // A real palette, a range of at least two entries inside it, and a rate
+    pub fn is_valid(&self) -> bool {
+        self.palette < PALETTE_COUNT
+            && self.length >= 2
+            && self.start < SUB_PALETTE_SIZE
+            && self.length <= SUB_PALETTE_SIZE - self.start
+            && self.frames_per_step > 0
+    }
+}
+
+// Palette RAM - 16 sub-palettes of master palette indices.
+// Palettes 0-7 are background palettes, 8-15 are sprite palettes.
+// Entry 0 of every sub-palette is transparent for sprites and tiles; entry 0
+// of background palette 0 doubles as the backdrop color.
+#[derive(Clone)]
+pub struct PaletteRam {
+    entries: [[u8; SUB_PALETTE_SIZE]; PALETTE_COUNT],
+    sizes: [u8; PALETTE_COUNT],
+}
+
+impl PaletteRam {
+    pub fn new() -> PaletteRam {
+        PaletteRam {
+            entries: [[0; SUB_PALETTE_SIZE]; PALETTE_COUNT],
+            sizes: [SUB_PALETTE_SIZE as u8; PALETTE_COUNT],
+        }
+    }
+
+    // Background palette n lives at palette RAM slot n
+    pub fn bg_palette_slot(palette: u8) -> usize {
+        palette as usize % BG_PALETTE_COUNT
+    }
+
+    // Sprite palette n lives at palette RAM slot BG_PALETTE_COUNT + n
+    pub fn sprite_palette_slot(palette: u8) -> usize {
+        BG_PALETTE_COUNT + (palette as usize % SPRITE_PALETTE_COUNT)
+    }
+
+    // Sub-palettes can be 4, 8 or 16 colors wide
+    pub fn set_size(&mut self, palette: usize, size: usize) -> bool {
+        if !matches!(size, 4 | 8 | 16) {
+            return false;
+        }
+
+        let palette = palette % PALETTE_COUNT;
+        let previous = self.sizes[palette];
+        self.sizes[palette] = size as u8;
+
+        // Growing a palette may bring new colors on screen
+        if self.distinct_color_count() > MAX_ON_SCREEN_COLORS {
+            self.sizes[palette] = previous;
+            return false;
+        }
+        true
+    }
+
+    pub fn size(&self, palette: usize) -> usize {
+        self.sizes[palette % PALETTE_COUNT] as usize
+    }
+
+    pub fn read(&self, palette: usize, index: usize) -> u8 {
+        self.entries[palette % PALETTE_COUNT][index % SUB_PALETTE_SIZE]
+    }
+
+    // Write a master palette index into palette RAM. Returns false (and leaves
+    // the entry untouched) if the write would put more than 64 colors on screen.
+    pub fn write(&mut self, palette: usize, index: usize, master_index: u8) -> bool {
+        let palette = palette % PALETTE_COUNT;
+        let index = index % SUB_PALETTE_SIZE;
+        let previous = self.entries[palette][index];
+        self.entries[palette][index] = master_index;
+
+        if self.distinct_color_count() > MAX_ON_SCREEN_COLORS {
+            self.entries[palette][index] = previous;
+            return false;
+        }
+        true
+    }
+
+    // Load a whole sub-palette at once (e.g. swapping in an enemy variant)
+    pub fn load(&mut self, palette: usize, colors: &[u8]) -> bool {
+        let palette = palette % PALETTE_COUNT;
+        let previous = self.entries[palette];
+        for (index, &color) in colors.iter().take(SUB_PALETTE_SIZE).enumerate() {
+            self.entries[palette][index] = color;
+        }
+
+        if self.distinct_color_count() > MAX_ON_SCREEN_COLORS {
+            self.entries[palette] = previous;
+            return false;
+        }
+        true
+    }
+
+    pub fn copy(&mut self, source: usize, destination: usize) -> bool {
+        let colors = self.entries[source % PALETTE_COUNT];
+        self.load(destination, &colors)
+    }
+
+    // Rotate entries start..start+length by one step (water, lava, etc.)
+    // The range is clipped to the palette's visible entries 1..size, so
+    // colors outside the limit's count never rotate into view. Returns false
+    // if fewer than two entries are left to rotate.
+    pub fn rotate(&mut self, palette: usize, start: usize, length: usize) -> bool {
+        let palette = palette % PALETTE_COUNT;
+        let start = start.max(1);
+        let end = start.saturating_add(length).min(self.sizes[palette] as usize);
+        if start >= end || end - start < 2 {
+            return false;
+        }
+        self.entries[palette][start..end].rotate_right(1);
+        true
+    }
+
+    // Number of distinct master colors currently reachable through palette RAM
+    pub fn distinct_color_count(&self) -> usize {
+        let mut used = [false; MASTER_PALETTE_SIZE];
+
+        // Backdrop color
+        used[self.entries[0][0] as usize] = true;
+
+        for (palette, colors) in self.entries.iter().enumerate() {
+            for &color in colors.iter().take(self.sizes[palette] as usize).skip(1) {
+                used[color as usize] = true;
+            }
+        }
+
+        used.iter().filter(|&&u| u).count()
+    }
+
+    // Look up the master palette index for a palette entry. Returns None for
+    // transparent.
+    pub fn resolve_index(&self, palette: usize, index: u8) -> Option<u8> {
+        let palette = palette % PALETTE_COUNT;
+        let index = index as usize;
+        if index == 0 || index >= self.sizes[palette] as usize {
+            return None;
+        }
+        Some(self.entries[palette][index])
+    }
+
+    // Look up the RGB color for a palette entry. Returns None for transparent.
+    pub fn resolve(&self, palette: usize, index: u8) -> Option<(u8, u8, u8)> {
+        self.resolve_index(palette, index).map(|master_index| MASTER_PALETTE[master_index as usize])
+    }
+
+    pub fn backdrop_index(&self) -> u8 {
+        self.entries[0][0]
+    }
+
+    pub fn backdrop(&self) -> (u8, u8, u8) {
+        MASTER_PALETTE[self.backdrop_index() as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_on_screen_color_limit() {
+        let mut palette_ram = PaletteRam::new();
+
+        // Fill entries with distinct colors until the 64-color limit is hit
+        let mut accepted = 0;
+        for palette in 0..PALETTE_COUNT {
+            for index in 1..SUB_PALETTE_SIZE {
+                let color = (palette * SUB_PALETTE_SIZE + index) as u8;
+                if palette_ram.write(palette, index, color) {
+                    accepted += 1;
+                }
+            }
+        }
+
+        // Backdrop (color 0) counts as one of the 64
+        assert_eq!(accepted, MAX_ON_SCREEN_COLORS - 1);
+        assert_eq!(palette_ram.distinct_color_count(), MAX_ON_SCREEN_COLORS);
+
+        // Reusing a color that's already on screen is always allowed
+        assert!(palette_ram.write(15, 15, 1));
+    }
+
+    #[test]
+    fn test_rotate_and_resolve() {
+        let mut palette_ram = PaletteRam::new();
+        palette_ram.load(3, &[0, 192, 193, 194, 195]);
+        assert!(palette_ram.rotate(3, 1, 4));
+
+        assert_eq!(palette_ram.read(3, 1), 195);
+        assert_eq!(palette_ram.read(3, 2), 192);
+        assert_eq!(palette_ram.resolve(3, 0), None);
+        assert_eq!(palette_ram.resolve_index(3, 2), Some(192));
+        assert_eq!(palette_ram.resolve(3, 2), Some(MASTER_PALETTE[192]));
+
+        // Out-of-range rotations are clipped, not overflowed
+        assert!(!palette_ram.rotate(3, 15, usize::MAX));
+        assert_eq!(palette_ram.read(3, 1), 195);
+    }
+
+    #[test]
+    fn test_rotate_stays_inside_a_shrunk_palette() {
+        let mut palette_ram = PaletteRam::new();
+        let colors: Vec<u8> = (100..116).collect();
+        assert!(palette_ram.load(9, &colors));
+        assert!(palette_ram.set_size(9, 4));
+        let count = palette_ram.distinct_color_count();
+
+        // Only entries 1-3 are visible; the hidden ones stay put
+        assert!(palette_ram.rotate(9, 0, 16));
+        assert_eq!((1..5).map(|index| palette_ram.read(9, index)).collect::<Vec<_>>(), [103, 101, 102, 104]);
+        assert_eq!(palette_ram.read(9, 0), 100);
+        assert_eq!(palette_ram.distinct_color_count(), count);
+        assert!(!palette_ram.rotate(9, 3, 8));
+    }
+
+    #[test]
+    fn test_rotate_rejects_a_start_past_the_palette() {
+        let mut palette_ram = PaletteRam::new();
+        palette_ram.load(3, &[0, 192, 193, 194, 195]);
+
+        assert!(!palette_ram.rotate(3, usize::MAX, 4));
+        assert!(!palette_ram.rotate(3, usize::MAX, usize::MAX));
+        assert_eq!(palette_ram.read(3, 1), 192);
+    }
+
+    #[test]
+    fn test_palette_cycle_validation() {
+        let cycle = PaletteCycle { palette: 15, start: 12, length: 4, frames_per_step: 8 };
+        assert!(cycle.is_valid());
+        assert!(!PaletteCycle { palette: 16, ..cycle }.is_valid());
+        assert!(!PaletteCycle { length: 5, ..cycle }.is_valid());
+        assert!(!PaletteCycle { length: 1, ..cycle }.is_valid());
+        assert!(!PaletteCycle { start: 16, length: 0, ..cycle }.is_valid());
+        assert!(!PaletteCycle { start: usize::MAX, length: usize::MAX, ..cycle }.is_valid());
+        assert!(!PaletteCycle { frames_per_step: 0, ..cycle }.is_valid());
+    }
+}
@@ -0,0 +1,406 @@
+// CPU post-processing - CRT-style filters applied to the finished RGBA frame.
+// The result goes to a separate, usually larger, output buffer so native and
+// headless builds get the same look as the browser without needing a GPU.
+//
+// Order: NTSC color bleed (source resolution) -> upscale -> bloom ->
+// scanlines + aperture grille mask.
+
+pub const MAX_SCALE: f32 = 4.0;
+pub const MAX_BLOOM_RADIUS: usize = 16;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum UpscaleFilter {
+    Nearest,       // Hard pixel edges; best at integer scales
+    SharpBilinear, // Integer prescale then bilinear, for clean non-integer scales
+}
+
+#[derive(Clone, Copy)]
+pub struct PostProcessSettings {
+    pub scale: f32,
+    pub filter: UpscaleFilter,
+    pub scanlines: u8,       // 0-255 darkening between source lines
+    pub aperture_grille: u8, // 0-255 strength of the RGB stripe mask
+    pub bloom: u8,           // 0-255 amount of glow added around bright pixels
+    pub bloom_threshold: u8, // Luma above which pixels glow
+    pub bloom_radius: usize, // Blur radius in output pixels, 1-16
+    pub ntsc_bleed: u8,      // 0-255 horizontal chroma smear of composite video
+}
+
+impl PostProcessSettings {
+    pub fn new() -> PostProcessSettings {
+        PostProcessSettings {
+            scale: 2.0,
+            filter: UpscaleFilter::Nearest,
+            scanlines: 96,
+            aperture_grille: 0,
+            bloom: 0,
+            bloom_threshold: 180,
+            bloom_radius: 2,
+            ntsc_bleed: 0,
+        }
+    }
+}
+
+pub struct PostProcessor {
+    pub settings: PostProcessSettings,
+    output: Vec<u8>,
+    output_width: usize,
+    output_height: usize,
+    source: Vec<[f32; 3]>,
+    image: Vec<[f32; 3]>,
+    glow: Vec<[f32; 3]>,
+    glow_scratch: Vec<[f32; 3]>,
+}
+
+impl PostProcessor {
+    pub fn new(settings: PostProcessSettings) -> PostProcessor {
+        PostProcessor {
+            settings,
+            output: Vec::new(),
+            output_width: 0,
+            output_height: 0,
+            source: Vec::new(),
+            image: Vec::new(),
+            glow: Vec::new(),
+            glow_scratch: Vec::new(),
+        }
+    }
+
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    pub fn output_width(&self) -> usize {
+        self.output_width
+    }
+
+    pub fn output_height(&self) -> usize {
+        self.output_height
+    }
+
+    // Filter an RGBA frame into the output buffer
+    pub fn process(&mut self, frame: &[u8], width: usize, height: usize) {
+        let scale = self.settings.scale.clamp(1.0, MAX_SCALE);
+        self.output_width = (width as f32 * scale).round() as usize;
+        self.output_height = (height as f32 * scale).round() as usize;
+
+        self.source.clear();
+        self.source.extend(frame.chunks_exact(4).map(|p| [p[0] as f32, p[1] as f32, p[2] as f32]));
+
+        if self.settings.ntsc_bleed > 0 {
+            self.apply_ntsc_bleed(width, height);
+        }
+        self.upscale(width, height);
+        if self.settings.bloom > 0 {
+            self.apply_bloom();
+        }
+        self.write_output(scale);
+    }
+
+    // Composite video carries color at lower bandwidth than brightness: keep
+    // luma sharp and smear chroma (YIQ I/Q) horizontally
+    fn apply_ntsc_bleed(&mut self, width: usize, height: usize) {
+        const KERNEL: [f32; 5] = [1.0, 2.0, 3.0, 2.0, 1.0];
+        let amount = self.settings.ntsc_bleed as f32 / 255.0;
+        let mut yiq = vec![[0.0f32; 3]; width];
+
+        for y in 0..height {
+            let row = &mut self.source[y * width..(y + 1) * width];
+            for (out, &[r, g, b]) in yiq.iter_mut().zip(row.iter()) {
+                *out = [
+                    0.299 * r + 0.587 * g + 0.114 * b,
+                    0.596 * r - 0.274 * g - 0.322 * b,
+                    0.211 * r - 0.523 * g + 0.312 * b,
+                ];
+            }
+
+            for x in 0..width {
+                let (mut i_sum, mut q_sum, mut weight) = (0.0, 0.0, 0.0);
+                for (k, &w) in KERNEL.iter().enumerate() {
+                    let sx = x as i32 + k as i32 - 2;
+                    if sx >= 0 && (sx as usize) < width {
+                        i_sum += yiq[sx as usize][1] * w;
+                        q_sum += yiq[sx as usize][2] * w;
+                        weight += w;
+                    }
+                }
+                let luma = yiq[x][0];
+                let i = yiq[x][1] + (i_sum / weight - yiq[x][1]) * amount;
+                let q = yiq[x][2] + (q_sum / weight - yiq[x][2]) * amount;
+                row[x] = [
+                    luma + 0.956 * i + 0.621 * q,
+                    luma - 0.272 * i - 0.647 * q,
+                    luma - 1.106 * i + 1.703 * q,
+                ];
+            }
+        }
+    }
+
+    fn upscale(&mut self, width: usize, height: usize) {
+        let (out_w, out_h) = (self.output_width, self.output_height);
+        let scale_x = out_w as f32 / width as f32;
+        let scale_y = out_h as f32 / height as f32;
+        self.image.clear();
+        self.image.resize(out_w * out_h, [0.0; 3]);
+
+        match self.settings.filter {
+            UpscaleFilter::Nearest => {
+                for y in 0..out_h {
+                    let sy = ((y as f32 / scale_y) as usize).min(height - 1);
+                    for x in 0..out_w {
+                        let sx = ((x as f32 / scale_x) as usize).min(width - 1);
+                        self.image[y * out_w + x] = self.source[sy * width + sx];
+                    }
+                }
+            }
+            UpscaleFilter::SharpBilinear => {
+                // Only blend within the band between prescaled pixels
+                let prescale_x = scale_x.floor().max(1.0);
+                let prescale_y = scale_y.floor().max(1.0);
+                for y in 0..out_h {
+                    let ty = sharpen((y as f32 + 0.5) / scale_y, prescale_y);
+                    for x in 0..out_w {
+                        let tx = sharpen((x as f32 + 0.5) / scale_x, prescale_x);
+                        self.image[y * out_w + x] = sample_bilinear(&self.source, width, height, tx, ty);
+                    }
+                }
+            }
+        }
+    }
+
+    fn apply_bloom(&mut self) {
+        let (w, h) = (self.output_width, self.output_height);
+        let threshold = self.settings.bloom_threshold as f32;
+        let amount = self.settings.bloom as f32 / 255.0;
+        let radius = self.settings.bloom_radius.clamp(1, MAX_BLOOM_RADIUS);
+
+        // Bright pass
+        self.glow.clear();
+        self.glow.extend(self.image.iter().map(|&[r, g, b]| {
+            let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+            let factor = ((luma - threshold) / (255.0 - threshold).max(1.0)).max(0.0);
+            [r * factor, g * factor, b * factor]
+        }));
+
+        // Separable box blur
+        self.glow_scratch.clear();
+        self.glow_scratch.resize(w * h, [0.0; 3]);
+        box_blur(&self.glow, &mut self.glow_scratch, w, h, radius, 1, w);
+        box_blur(&self.glow_scratch, &mut self.glow, h, w, radius, w, 1);
+
+        for (pixel, glow) in self.image.iter_mut().zip(self.glow.iter()) {
+            for (value, glow) in pixel.iter_mut().zip(glow) {
+                *value += glow * amount;
+            }
+        }
+    }
+
+    fn write_output(&mut self, scale: f32) {
+        let (w, h) = (self.output_width, self.output_height);
+        let scanlines = self.settings.scanlines as f32 / 255.0;
+        let grille = self.settings.aperture_grille as f32 / 255.0;
+        self.output.resize(w * h * 4, 0);
+
+        for y in 0..h {
+            // Darken toward the bottom of each source line; at 1x every other line
+            let weight = if scale >= 2.0 {
+                let v = ((y as f32 + 0.5) / scale).fract();
+                ((v - 0.5) * 2.0).max(0.0)
+            } else {
+                (y % 2) as f32
+            };
+            let line_factor = 1.0 - scanlines * weight;
+
+            for x in 0..w {
+                let pixel = self.image[y * w + x];
+                let out = (y * w + x) * 4;
+                for (c, value) in pixel.iter().enumerate() {
+                    // Each column lets through mostly one phosphor color
+                    let mask = if x % 3 == c { 1.0 } else { 1.0 - grille };
+                    self.output[out + c] = (value * line_factor * mask).clamp(0.0, 255.0) as u8;
+                }
+                self.output[out + 3] = 255;
+            }
+        }
+    }
+}
+
+// Sharp-bilinear texel coordinate: snap to the texel center except within a
+// 1/prescale wide band at texel edges
+fn sharpen(texel: f32, prescale: f32) -> f32 {
+    let floor = texel.floor();
+    let center_dist = texel - floor - 0.5;
+    let region = 0.5 - 0.5 / prescale;
+    floor + (center_dist - center_dist.clamp(-region, region)) * prescale + 0.5
+}
+
+fn sample_bilinear(source: &[[f32; 3]], width: usize, height: usize, x: f32, y: f32) -> [f32; 3] {
+    let fx = (x - 0.5).max(0.0);
+    let fy = (y - 0.5).max(0.0);
+    let x0 = (fx as usize).min(width - 1);
+    let y0 = (fy as usize).min(height - 1);
+    let x1 = (x0 + 1).min(width - 1);
+    let y1 = (y0 + 1).min(height - 1);
+    let (tx, ty) = (fx.fract(), fy.fract());
+
+    let mut color = [0.0; 3];
+    for (c, value) in color.iter_mut().enumerate() {
+        let top = source[y0 * width + x0][c] * (1.0 - tx) + source[y0 * width + x1][c] * tx;
+        let bottom = source[y1 * width + x0][c] * (1.0 - tx) + source[y1 * width + x1][c] * tx;
+        *value = top * (1.0 - ty) + bottom * ty;
+    }
+    color
+}
+
+// 1D box blur along `length` samples `step` apart, for each of `lines` lines
+// starting `stride` apart. A running sum slides along the line, so the cost
+// doesn't grow with the radius; windows are cut short at the line ends.
+fn box_blur(input: &[[f32; 3]], output: &mut [[f32; 3]], length: usize, lines: usize, radius: usize, step: usize, stride: usize) {
+    if length == 0 {
+        return;
+    }
+    let radius = radius.min(length - 1);
+    for line in 0..lines {
+        let base = line * stride;
+        let sample = |j: usize| input[base + j * step];
+        let mut sum = [0.0; 3];
+        for j in 0..=radius {
+            let entering = sample(j);
+            for c in 0..3 {
+                sum[c] += entering[c];
+            }
+        }
+
+        for i in 0..length {
+            let start = i.saturating_sub(radius);
+            let end = (i + radius).min(length - 1);
+            let count = (end - start + 1) as f32;
+            output[base + i * step] = [sum[0] / count, sum[1] / count, sum[2] / count];
+
+            // Slide the window one sample along
+            if end + 1 < length && end == i + radius {
+                let entering = sample(end + 1);
+                for c in 0..3 {
+                    sum[c] += entering[c];
+                }
+            }
+            if i >= radius {
+                let leaving = sample(start);
+                for c in 0..3 {
+                    sum[c] -= leaving[c];
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Processed output pixel as RGB
+    fn rgb(post: &PostProcessor, x: usize, y: usize) -> [u8; 3] {
+        let index = (y * post.output_width() + x) * 4;
+        let output = post.output();
+        [output[index], output[index + 1], output[index + 2]]
+    }
+
+    fn unfiltered() -> PostProcessSettings {
+        let mut settings = PostProcessSettings::new();
+        settings.scale = 1.0;
+        settings.scanlines = 0;
+        settings
+    }
+
+    #[test]
+    fn test_nearest_upscale_with_scanlines() {
+        let mut settings = PostProcessSettings::new();
+        settings.scanlines = 255;
+        let mut post = PostProcessor::new(settings);
+
+        // 2x1 frame: white, red
+        let frame = [255, 255, 255, 255, 255, 0, 0, 255];
+        post.process(&frame, 2, 1);
+        assert_eq!((post.output_width(), post.output_height()), (4, 2));
+
+        // Top output row is the source line, bottom row is the dark gap
+        let output = post.output();
+        assert_eq!(&output[0..4], &[255, 255, 255, 255]);
+        assert_eq!(&output[8..12], &[255, 0, 0, 255]);
+        assert_eq!(&output[16..20], &[127, 127, 127, 255]);
+    }
+
+    #[test]
+    fn test_aperture_grille_stripes() {
+        let mut settings = unfiltered();
+        settings.aperture_grille = 255;
+        let mut post = PostProcessor::new(settings);
+
+        // Each column lets through one phosphor color, repeating every 3
+        post.process(&[255; 4 * 4], 4, 1);
+        assert_eq!(rgb(&post, 0, 0), [255, 0, 0]);
+        assert_eq!(rgb(&post, 1, 0), [0, 255, 0]);
+        assert_eq!(rgb(&post, 2, 0), [0, 0, 255]);
+        assert_eq!(rgb(&post, 3, 0), [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_bloom_spreads_within_its_radius() {
+        let mut settings = unfiltered();
+        settings.bloom = 255;
+        settings.bloom_threshold = 0;
+        settings.bloom_radius = 1;
+        let mut post = PostProcessor::new(settings);
+
+        // One white pixel in the middle of seven
+        let mut frame = [0, 0, 0, 255].repeat(7);
+        frame[12..15].copy_from_slice(&[255, 255, 255]);
+        post.process(&frame, 7, 1);
+        assert_eq!(rgb(&post, 3, 0), [255, 255, 255]);
+        assert_eq!(rgb(&post, 2, 0), [85, 85, 85]);
+        assert_eq!(rgb(&post, 1, 0), [0, 0, 0]);
+
+        // Huge radii are capped rather than overflowing
+        post.settings.bloom_radius = usize::MAX;
+        post.process(&frame, 7, 1);
+        assert!(rgb(&post, 0, 0)[0] > 0);
+    }
+
+    #[test]
+    fn test_ntsc_bleed_smears_color_but_not_brightness() {
+        let mut settings = unfiltered();
+        settings.ntsc_bleed = 255;
+        let mut post = PostProcessor::new(settings);
+
+        // Red then blue: the colors bleed across the edge, the far pixels stay
+        let mut frame = [255, 0, 0, 255].repeat(4);
+        frame.extend([0, 0, 255, 255].repeat(4));
+        post.process(&frame, 8, 1);
+        let luma = |[r, g, b]: [u8; 3]| 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+        assert!(rgb(&post, 0, 0)[2] < 2);
+        assert!(rgb(&post, 3, 0)[2] > 20);
+        assert!((luma(rgb(&post, 3, 0)) - luma([255, 0, 0])).abs() < 3.0);
+    }
+
+    #[test]
+    fn test_sharp_bilinear_only_blends_at_texel_edges() {
+        let mut settings = unfiltered();
+        settings.filter = UpscaleFilter::SharpBilinear;
+        settings.scale = 3.0;
+        let mut post = PostProcessor::new(settings);
+
+        // At an integer scale it matches nearest (to rounding)
+        let frame = [0, 0, 0, 255, 255, 255, 255, 255];
+        post.process(&frame, 2, 1);
+        let row: Vec<u8> = (0..6).map(|x| rgb(&post, x, 0)[0]).collect();
+        assert!(row.iter().zip([0, 0, 0, 255, 255, 255]).all(|(&value, nearest)| value.abs_diff(nearest) <= 1));
+
+        // At 2.5x only the output pixel straddling the edge is blended
+        post.settings.scale = 2.5;
+        post.process(&frame, 2, 1);
+        let row: Vec<u8> = (0..5).map(|x| rgb(&post, x, 0)[0]).collect();
+        assert_eq!(&row[..2], &[0, 0]);
+        assert!(row[2] > 0 && row[2] < 255);
+        assert_eq!(&row[3..], &[255, 255]);
+    }
+}
@@ -1,8 +1,7 @@
 use wasm_bindgen::prelude::*;
 use crate::memory::Memory;
 
-const SCREEN_WIDTH: usize = 320;
-const SCREEN_HEIGHT: usize = 240;
+use crate::video_mode::{SCREEN_WIDTH, SCREEN_HEIGHT};
 
 // 8x8 bitmap font data for printable ASCII characters (32-126)
 const FONT_8X8: [[u8; 8]; 95] = [
@@ -1496,6 +1495,17 @@ impl Ppu {
         js_sys::Uint8Array::from(&self.screen_buffer[..])
     }
 
+    // Pointer into wasm memory. This PPU has a single fixed-size mode, so the
+    // buffer is allocated once in new() and the pointer doesn't change; the
+    // cartridge system's PPU reallocates on video mode changes instead.
+    pub fn get_screen_buffer_ptr(&self) -> *const u8 {
+        self.screen_buffer.as_ptr()
+    }
+
+    pub fn get_screen_buffer_len(&self) -> usize {
+        self.screen_buffer.len()
+    }
+
     pub fn get_screen_width(&self) -> u32 {
         SCREEN_WIDTH as u32
     }
@@ -1,9 +1,20 @@
 use wasm_bindgen::prelude::*;
 use crate::memory::Memory;
+use crate::video_mode::VideoMode;
 use crate::font_system::{FontSystem, Language, get_font_data};
+use crate::palette::{MASTER_PALETTE, MAX_PALETTE_CYCLES, PaletteCycle, PaletteRam, SPRITE_PALETTE_COUNT, SUB_PALETTE_SIZE};
+use crate::sprite_bank::{SpriteBank, SpritePattern};
+use crate::animation::ClipFrame;
+use crate::metasprite::{ScanlineSpriteCounter, MAX_HARDWARE_SPRITES};
+use crate::mode7::{Mode7Layer, Mode7Matrix};
+use crate::tile_animation::{TileAnimation, TileAnimator};
+use crate::transition::{Transition, TransitionDirection, TransitionOwner};
+use crate::color_math::{BlendMode, ColorMath, Tint};
+use crate::dirty::DirtyTracker;
+use crate::post_process::{PostProcessor, PostProcessSettings};
+use crate::hud::{HudLayer, HudPriority, HUD_TILE_SIZE};
+use crate::debug_view::{self, DebugImage, DebugView, OamEntry};
 
-const SCREEN_WIDTH: usize = 320;
-const SCREEN_HEIGHT: usize = 240;
 
 // 8x8 bitmap font data for printable ASCII characters (32-126)
 const FONT_8X8: [[u8; 8]; 95] = [
@@ -199,57 +210,6 @@ const FONT_8X8: [[u8; 8]; 95] = [
     [0x6E, 0x3B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
 ];
 
-// 128-color master palette - artist-friendly with good range
-const MASTER_PALETTE: [(u8, u8, u8); 128] = [
-    // Grayscale ramp (0-15)
-    (0, 0, 0), (17, 17, 17), (34, 34, 34), (51, 51, 51),
-    (68, 68, 68), (85, 85, 85), (102, 102, 102), (119, 119, 119),
-    (136, 136, 136), (153, 153, 153), (170, 170, 170), (187, 187, 187),
-    (204, 204, 204), (221, 221, 221), (238, 238, 238), (255, 255, 255),
-
-    // Reds (16-31)
-    (128, 0, 0), (160, 0, 0), (192, 0, 0), (224, 0, 0),
-    (255, 0, 0), (255, 32, 32), (255, 64, 64), (255, 96, 96),
-    (255, 128, 128), (255, 160, 160), (255, 192, 192), (255, 224, 224),
-    (128, 32, 0), (160, 64, 0), (192, 96, 32), (224, 128, 64),
-
-    // Oranges/Browns (32-47)
-    (255, 128, 0), (255, 160, 0), (255, 192, 0), (255, 224, 0),
-    (255, 255, 0), (224, 224, 0), (192, 192, 0), (160, 160, 0),
-    (128, 128, 0), (160, 128, 64), (192, 160, 96), (224, 192, 128),
-    (139, 69, 19), (160, 82, 45), (205, 133, 63), (222, 184, 135),
-
-    // Greens (48-63)
-    (0, 128, 0), (0, 160, 0), (0, 192, 0), (0, 224, 0),
-    (0, 255, 0), (32, 255, 32), (64, 255, 64), (96, 255, 96),
-    (128, 255, 128), (160, 255, 160), (192, 255, 192), (224, 255, 224),
-    (0, 128, 64), (0, 160, 96), (32, 192, 128), (64, 224, 160),
-
-    // Cyans (64-79)
-    (0, 255, 255), (0, 224, 224), (0, 192, 192), (0, 160, 160),
-    (0, 128, 128), (32, 160, 160), (64, 192, 192), (96, 224, 224),
-    (128, 255, 255), (160, 255, 255), (192, 255, 255), (224, 255, 255),
-    (0, 128, 96), (0, 160, 128), (32, 192, 160), (64, 224, 192),
-
-    // Blues (80-95)
-    (0, 0, 128), (0, 0, 160), (0, 0, 192), (0, 0, 224),
-    (0, 0, 255), (32, 32, 255), (64, 64, 255), (96, 96, 255),
-    (128, 128, 255), (160, 160, 255), (192, 192, 255), (224, 224, 255),
-    (0, 64, 128), (32, 96, 160), (64, 128, 192), (96, 160, 224),
-
-    // Purples/Magentas (96-111)
-    (128, 0, 128), (160, 0, 160), (192, 0, 192), (224, 0, 224),
-    (255, 0, 255), (255, 32, 255), (255, 64, 255), (255, 96, 255),
-    (255, 128, 255), (255, 160, 255), (255, 192, 255), (255, 224, 255),
-    (128, 0, 64), (160, 32, 96), (192, 64, 128), (224, 96, 160),
-
-    // Skin tones & earth tones (112-127)
-    (255, 220, 177), (255, 206, 158), (238, 180, 120), (210, 150, 95),
-    (180, 120, 80), (150, 100, 70), (120, 80, 60), (100, 70, 50),
-    (139, 115, 85), (160, 130, 98), (205, 175, 149), (222, 196, 176),
-    (245, 222, 179), (255, 228, 196), (255, 235, 205), (255, 248, 220),
-];
-
 // Sprite data structure - what cartridges provide to PPU
 #[derive(Clone)]
 pub struct SpriteData {
@@ -258,12 +218,124 @@ pub struct SpriteData {
     pub sprite_id: u32,
     pub active: bool,
     pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    pub palette: Option<u8>, // Sprite sub-palette, None = pixels index the master palette directly
+    pub affine: Option<SpriteAffine>, // Rotation/scale about the sprite's center
+    pub blend: BlendMode,             // Opaque uses the sprite layer's blend mode
+    pub tint: Option<Tint>,           // Flash/tint color mixed into every pixel
+    pub clip: Option<ClipFrame>,      // Animation clip frame, drawn instead of sprite_id
+}
+
+// Screen layers, bottom to top
+#[derive(Clone, Copy, PartialEq)]
+pub enum Layer {
+    Background = 0,
+    Mode7 = 1,
+    Sprites = 2,
+    Hud = 3,
+}
+
+const LAYER_COUNT: usize = 4;
+const ALL_LAYERS: [bool; LAYER_COUNT] = [true; LAYER_COUNT];
+
+// Background palettes the built-in scenery draws through. They're loaded at
+// power-on with the scenery's original colors, so writes to them recolor the
+// sky, hills and ground like any other background.
+const SKY_PALETTE: u8 = 0;    // Backdrop, then the gradient top to bottom
+const HILLS_PALETTE: u8 = 1;  // Far, mid and near mountains, tree trunks
+const GROUND_PALETTE: u8 = 2; // Grass, dirt, rock
+const SCENERY_PALETTES: [(u8, &[u8]); 3] = [
+    (SKY_PALETTE, &[0, 84, 85, 86, 87]),
+    (HILLS_PALETTE, &[0, 96, 80, 48, 32]),
+    (GROUND_PALETTE, &[0, 49, 33, 17]),
+];
+
+fn power_on_palette_ram() -> PaletteRam {
+    let mut palette_ram = PaletteRam::new();
+    for (palette, colors) in SCENERY_PALETTES {
+        palette_ram.load(PaletteRam::bg_palette_slot(palette), colors);
+    }
+    palette_ram
+}
+
+impl Layer {
+    pub fn from_u32(layer: u32) -> Option<Layer> {
+        match layer {
+            0 => Some(Layer::Background),
+            1 => Some(Layer::Mode7),
+            2 => Some(Layer::Sprites),
+            3 => Some(Layer::Hud),
+            _ => None,
+        }
+    }
+}
+
+// Per-sprite affine parameters. The sprite keeps its center; the drawn area
+// grows to fit the rotated, scaled pattern.
+#[derive(Clone, Copy)]
+pub struct SpriteAffine {
+    pub rotation: f32, // Radians, clockwise on screen
+    pub scale_x: f32,
+    pub scale_y: f32,
+}
+
+// Palette RAM write timed to a scanline (raster effects such as water lines)
+#[derive(Clone, Copy)]
+struct PaletteWrite {
+    scanline: u16,
+    palette: usize,
+    index: usize,
+    color: u8,
+}
+
+// PPU status register bits
+pub const STATUS_VBLANK: u8 = 0x80;    // Beam is in vertical blanking
+pub const STATUS_HBLANK: u8 = 0x40;    // Beam is past the visible part of the line
+pub const STATUS_ODD_FIELD: u8 = 0x20; // Interlaced field being drawn
+pub const STATUS_SPRITE_OVERFLOW: u8 = 0x10; // A line this frame had more sprites than the limit
+
+// Frame output format handed to the frontend
+#[derive(Clone, Copy, PartialEq)]
+pub enum OutputMode {
+    Rgba,    // 320x240x4 RGBA buffer, color math already applied
+    Indexed, // Master palette indices plus a 256-entry RGBA palette
 }
 
 pub struct Ppu {
     // Screen buffer - RGBA format
     screen_buffer: Vec<u8>,
 
+    // Layers are composed here and copied to the screen buffer one line at a
+    // time as the beam finishes each line. A VRAM write mid-frame marks the
+    // composition stale so the remaining lines pick it up; only lines from
+    // compose_from down are drawn again.
+    line_buffer: Vec<u8>,
+    composed: bool,
+    compose_from: usize,
+
+    // Video mode register and the framebuffer size it selects
+    video_mode: VideoMode,
+    width: usize,
+    height: usize,
+    odd_field: bool, // Interlaced modes alternate even/odd fields
+    buffer_generation: u32, // Bumped whenever an output buffer is reallocated
+
+    // Indexed output - one master palette index per pixel, and the palette
+    // to display it with. The indices are composed alongside the line buffer;
+    // color math is applied to the palette, not pixels.
+    output_mode: OutputMode,
+    index_buffer: Vec<u8>,
+    active_palette: Vec<u8>,
+    composed_indices: Vec<u8>, // Empty unless the output mode is Indexed
+
+    // Optional per-frame change tracking of the output buffer
+    dirty_tracker: Option<DirtyTracker>,
+    tracked_palette: Vec<u8>, // Active palette the tracked index frame was shown with
+
+    // Optional CRT-style post-processing into a separate output buffer
+    post_processor: PostProcessor,
+    post_process_enabled: bool,
+
     // PPU registers (authentic 8-bit hardware)
     control: u8,
     mask: u8,
@@ -283,6 +355,21 @@ pub struct Ppu {
     // Sprite data provided by cartridge
     sprites: Vec<SpriteData>,
 
+    // Pattern data uploaded by the cartridge, indexed by sprite ID
+    sprite_bank: SpriteBank,
+    sprite_lines: Vec<bool>, // Lines the sprite being drawn won a scanline slot on
+    pattern_animations: TileAnimator, // Animated bank patterns, remapped each frame
+
+    // Affine background layer drawn between the background and sprites
+    mode7: Mode7Layer,
+
+    // Palette RAM plus writes scheduled for specific scanlines this frame
+    palette_ram: PaletteRam,
+    palette_writes: Vec<PaletteWrite>,
+    line_palettes: Vec<PaletteRam>, // Per-scanline snapshots while rendering a frame with raster writes
+    palette_cycles: Vec<(PaletteCycle, u64)>, // With the frame count it was added on
+    cycled_frame: Option<u64>, // Frame the palette cycles last stepped on
+
     // Demo mode toggle
     color_test_mode: bool,
 
@@ -292,23 +379,51 @@ pub struct Ppu {
     // Intro/interlude screen mode
     intro_mode: bool,
     intro_text: String,
+    intro_sprite: Option<u32>, // Sprite shown enlarged on the intro screen
+    intro_palette: Option<u8>, // Sprite palette it's drawn through
     // Z-Synth piano mode
     zsynth_mode: bool,
     
-    // HUD/UI data
-    hud_lives: u8,
-    player_dying: bool,
-    player_death_flash: bool,
-    player_invulnerable: bool,
-    player_invul_flash: bool,
+    // Color math - per-layer blending plus master brightness/tint
+    layer_blend: [BlendMode; LAYER_COUNT],
+    color_math: ColorMath,
+
+    // Screen transition applied to output lines, and the ones finished since
+    // they were last collected
+    transition: Option<Transition>,
+    completed_transitions: Vec<(TransitionOwner, u32)>,
+
+    // Debug: layers shown in the main output, and the last debug view image
+    layers_enabled: [bool; LAYER_COUNT],
+    debug_image: DebugImage,
+
+    // Fixed HUD/window layer (ignores scroll)
+    hud: HudLayer,
 }
 
 impl Ppu {
     pub fn new() -> Ppu {
-        let screen_buffer = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * 4];
+        let video_mode = VideoMode::Standard;
+        let screen_buffer = vec![0; video_mode.width() * video_mode.height() * 4];
 
         Ppu {
+            line_buffer: screen_buffer.clone(),
+            composed: false,
+            compose_from: 0,
             screen_buffer,
+            video_mode,
+            width: video_mode.width(),
+            height: video_mode.height(),
+            odd_field: false,
+            output_mode: OutputMode::Rgba,
+            buffer_generation: 0,
+            index_buffer: Vec::new(),
+            active_palette: Vec::new(),
+            composed_indices: Vec::new(),
+            dirty_tracker: None,
+            tracked_palette: Vec::new(),
+            post_processor: PostProcessor::new(PostProcessSettings::new()),
+            post_process_enabled: false,
             control: 0,
             mask: 0,
             status: 0,
@@ -318,37 +433,155 @@ impl Ppu {
             cycle: 0,
             frame_count: 0,
             sprites: Vec::new(),
+            sprite_bank: SpriteBank::new(),
+            sprite_lines: Vec::new(),
+            pattern_animations: TileAnimator::new(),
+            mode7: Mode7Layer::new(video_mode.height()),
+            palette_ram: power_on_palette_ram(),
+            palette_writes: Vec::new(),
+            line_palettes: Vec::new(),
+            palette_cycles: Vec::new(),
+            cycled_frame: None,
             color_test_mode: false,
             font_system: FontSystem::new(),
             intro_mode: false,
             intro_text: String::new(),
+            intro_sprite: None,
+            intro_palette: None,
             zsynth_mode: false,
-            hud_lives: 3,
-            player_dying: false,
-            player_death_flash: false,
-            player_invulnerable: false,
-            player_invul_flash: false,
+            layer_blend: [BlendMode::Opaque; LAYER_COUNT],
+            color_math: ColorMath::new(),
+            transition: None,
+            completed_transitions: Vec::new(),
+            layers_enabled: ALL_LAYERS,
+            debug_image: DebugImage::new(0, 0),
+            hud: HudLayer::for_screen(video_mode.width(), video_mode.height()),
         }
     }
 
+    // Advance the beam by one dot. Each line is output when its visible part
+    // ends; returns true when VBlank starts and the frame is complete.
     pub fn step(&mut self, _memory: &Memory) -> bool {
+        // Frames start on their first dot, so writes scheduled before then
+        // (after power-on, a mode switch or in VBlank) land in them
+        if self.scanline == 0 && self.cycle == 0 {
+            self.start_frame();
+        }
         self.cycle += 1;
 
-        // Simple scanline progression
-        if self.cycle >= 341 {
+        if self.cycle == self.width as u16 {
+            self.status |= STATUS_HBLANK;
+            if self.scanline < self.video_mode.visible_lines() {
+                self.output_line(self.framebuffer_line(self.scanline as usize));
+            }
+        }
+
+        if self.cycle >= self.video_mode.dots_per_line() {
             self.cycle = 0;
             self.scanline += 1;
+            self.status &= !STATUS_HBLANK;
 
-            if self.scanline >= 262 {
-                self.scanline = 0;
+            if self.scanline == self.video_mode.visible_lines() {
+                self.status |= STATUS_VBLANK;
+                self.finish_frame();
                 self.frame_count += 1;
                 return true; // Frame complete
             }
+
+            if self.scanline >= self.video_mode.lines_per_field(self.odd_field) {
+                self.scanline = 0;
+                self.status &= !STATUS_VBLANK;
+                if self.video_mode.is_interlaced() {
+                    self.odd_field = !self.odd_field;
+                    self.status ^= STATUS_ODD_FIELD;
+                }
+            }
         }
 
         false
     }
 
+    // Status register - beam position flags, no side effects on read
+    pub fn read_status(&self) -> u8 {
+        self.status
+    }
+
+    pub fn get_scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    pub fn get_cycle(&self) -> u16 {
+        self.cycle
+    }
+
+    // A write that changes what's drawn: lines not yet output are recomposed,
+    // the ones the beam has already passed keep what they showed
+    pub fn invalidate_lines(&mut self) {
+        let next_line = if self.scanline >= self.video_mode.visible_lines() {
+            0 // VBlank - the whole next frame is still to come
+        } else if self.cycle >= self.width as u16 {
+            self.framebuffer_line(self.scanline as usize + 1)
+        } else {
+            self.framebuffer_line(self.scanline as usize)
+        };
+        self.compose_from = if self.composed { next_line } else { self.compose_from.min(next_line) };
+        self.composed = false;
+    }
+
+    // Framebuffer line for a scanline of the current field
+    fn framebuffer_line(&self, scanline: usize) -> usize {
+        if self.video_mode.is_interlaced() {
+            scanline * 2 + self.odd_field as usize
+        } else {
+            scanline
+        }
+    }
+
+    // Video mode register - resizes the framebuffer and restarts the frame
+    pub fn set_video_mode(&mut self, mode: VideoMode) {
+        if mode == self.video_mode {
+            return;
+        }
+        self.video_mode = mode;
+        self.width = mode.width();
+        self.height = mode.height();
+        self.odd_field = false;
+        self.scanline = 0;
+        self.cycle = 0;
+        self.status = 0;
+
+        self.screen_buffer = vec![0; self.width * self.height * 4];
+        self.line_buffer = self.screen_buffer.clone();
+        if self.output_mode == OutputMode::Indexed {
+            self.index_buffer = vec![0; self.width * self.height];
+            self.composed_indices = self.index_buffer.clone();
+        }
+        self.buffer_generation = self.buffer_generation.wrapping_add(1);
+        self.mode7.resize(self.height);
+        self.hud.resize(self.width, self.height);
+
+        // The beam is back at the top; the next step starts a frame in the
+        // new mode
+        self.end_palette_frame();
+    }
+
+    pub fn get_video_mode(&self) -> VideoMode {
+        self.video_mode
+    }
+
+    pub fn get_screen_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_screen_height(&self) -> usize {
+        self.height
+    }
+
+    // Which field of an interlaced mode is being displayed
+    pub fn is_odd_field(&self) -> bool {
+        self.odd_field
+    }
+
     // Hardware register access for cartridges
     pub fn set_scroll(&mut self, x: f32, y: f32) {
         self.scroll_x = x;
@@ -368,16 +601,186 @@ impl Ppu {
         self.sprites.clear();
     }
 
-    pub fn add_sprite(&mut self, x: f32, y: f32, sprite_id: u32, active: bool, flip_horizontal: bool) {
-        self.sprites.push(SpriteData {
+    pub fn add_sprite_with_palette(&mut self, x: f32, y: f32, sprite_id: u32, active: bool, flip_horizontal: bool, palette: Option<u8>) {
+        self.add_sprite_data(SpriteData {
             x,
             y,
             sprite_id,
             active,
             flip_horizontal,
+            flip_vertical: false,
+            palette,
+            affine: None,
+            blend: BlendMode::Opaque,
+            tint: None,
+            clip: None,
         });
     }
 
+    pub fn add_sprite_data(&mut self, sprite: SpriteData) {
+        self.sprites.push(sprite);
+    }
+
+    // Sprite bank - cartridges upload their pattern data at load time
+    pub fn load_sprite_bank(&mut self, bank: SpriteBank) {
+        self.sprite_bank = bank;
+    }
+
+    pub fn upload_sprite(&mut self, sprite_id: u32, width: u32, height: u32, pixels: &[u8]) -> bool {
+        self.sprite_bank.upload(sprite_id, width, height, pixels)
+    }
+
+    // Animation clips (.zsa data) sprites can be drawn from
+    pub fn load_animations(&mut self, data: &[u8]) -> bool {
+        self.sprite_bank.load_animations(data)
+    }
+
+    // Animated tiles - sprites, metasprite pieces and the mode-7 texture drawn
+    // with an animation's base pattern ID show its current frame
+    pub fn add_pattern_animation(&mut self, animation: TileAnimation) -> bool {
+        self.pattern_animations.add(animation)
+    }
+
+    pub fn clear_pattern_animations(&mut self) {
+        self.pattern_animations.clear();
+    }
+
+    // Mode-7 affine background layer
+    pub fn set_mode7_enabled(&mut self, enabled: bool) {
+        self.mode7.enabled = enabled;
+    }
+
+    pub fn set_mode7_pattern(&mut self, pattern_id: u32, palette: Option<u8>, wrap: bool) {
+        self.mode7.pattern_id = pattern_id;
+        self.mode7.palette = palette;
+        self.mode7.wrap = wrap;
+    }
+
+    pub fn set_mode7_matrix(&mut self, matrix: Mode7Matrix) {
+        self.mode7.matrix = matrix;
+    }
+
+    pub fn set_mode7_origin(&mut self, x: f32, y: f32) {
+        self.mode7.origin_x = x;
+        self.mode7.origin_y = y;
+    }
+
+    pub fn set_mode7_scroll(&mut self, x: f32, y: f32) {
+        self.mode7.scroll_x = x;
+        self.mode7.scroll_y = y;
+    }
+
+    pub fn set_mode7_lines(&mut self, first_line: usize, last_line: usize) {
+        self.mode7.first_line = first_line.min(self.height - 1);
+        self.mode7.last_line = last_line.min(self.height - 1);
+    }
+
+    pub fn set_mode7_line_matrix(&mut self, line: usize, matrix: Mode7Matrix) {
+        self.mode7.set_line_matrix(line, matrix);
+    }
+
+    pub fn clear_mode7_line_matrices(&mut self) {
+        self.mode7.clear_line_matrices();
+    }
+
+    // Palette RAM access - returns false when the 64-color limit refuses the write
+    pub fn write_palette(&mut self, palette: usize, index: usize, color: u8) -> bool {
+        self.palette_ram.write(palette, index, color)
+    }
+
+    pub fn read_palette(&self, palette: usize, index: usize) -> u8 {
+        self.palette_ram.read(palette, index)
+    }
+
+    pub fn load_palette(&mut self, palette: usize, colors: &[u8]) -> bool {
+        self.palette_ram.load(palette, colors)
+    }
+
+    // Replace every sprite palette with a cartridge's set of (sprite palette,
+    // colors). Returns false if any of them was refused by the color limit.
+    pub fn load_sprite_palettes(&mut self, palettes: &[(u8, &[u8])]) -> bool {
+        for palette in 0..SPRITE_PALETTE_COUNT as u8 {
+            self.palette_ram.load(PaletteRam::sprite_palette_slot(palette), &[0; SUB_PALETTE_SIZE]);
+        }
+        palettes.iter().fold(true, |loaded, (palette, colors)| {
+            self.palette_ram.load(PaletteRam::sprite_palette_slot(*palette), colors) && loaded
+        })
+    }
+
+    pub fn copy_palette(&mut self, source: usize, destination: usize) -> bool {
+        self.palette_ram.copy(source, destination)
+    }
+
+    pub fn set_palette_size(&mut self, palette: usize, size: usize) -> bool {
+        self.palette_ram.set_size(palette, size)
+    }
+
+    pub fn rotate_palette(&mut self, palette: usize, start: usize, length: usize) -> bool {
+        self.palette_ram.rotate(palette, start, length)
+    }
+
+    // Palette cycling - ranges rotated automatically on the frame count. The
+    // first step comes a full period after the cycle is added. Returns false
+    // for an invalid range or rate, a range outside the palette's visible
+    // entries (1 up to its size), or when all cycles are in use.
+    pub fn add_palette_cycle(&mut self, cycle: PaletteCycle) -> bool {
+        if !cycle.is_valid() || self.palette_cycles.len() >= MAX_PALETTE_CYCLES {
+            return false;
+        }
+        if cycle.start == 0 || cycle.start + cycle.length > self.palette_ram.size(cycle.palette) {
+            return false;
+        }
+        self.palette_cycles.push((cycle, self.frame_count));
+        true
+    }
+
+    pub fn clear_palette_cycles(&mut self) {
+        self.palette_cycles.clear();
+    }
+
+    pub fn get_palette_color_count(&self) -> usize {
+        self.palette_ram.distinct_color_count()
+    }
+
+    // Queue a palette write that takes effect from the given scanline onward
+    // during the next rendered frame. Writes that would break the 64-color
+    // limit are dropped when they are applied.
+    pub fn schedule_palette_write(&mut self, scanline: u16, palette: usize, index: usize, color: u8) {
+        self.palette_writes.push(PaletteWrite { scanline, palette, index, color });
+    }
+
+    // Build per-scanline palette snapshots for this frame's raster writes
+    fn begin_palette_frame(&mut self) {
+        if self.palette_writes.is_empty() {
+            return;
+        }
+
+        self.palette_writes.sort_by_key(|write| write.scanline);
+        let mut next_write = 0;
+        for line in 0..self.height {
+            while next_write < self.palette_writes.len() && self.palette_writes[next_write].scanline as usize <= line {
+                let write = self.palette_writes[next_write];
+                self.palette_ram.write(write.palette, write.index, write.color);
+                next_write += 1;
+            }
+            self.line_palettes.push(self.palette_ram.clone());
+        }
+
+        // Writes scheduled past the visible area land during VBlank
+        for write in &self.palette_writes[next_write..] {
+            self.palette_ram.write(write.palette, write.index, write.color);
+        }
+        self.palette_writes.clear();
+    }
+
+    fn end_palette_frame(&mut self) {
+        self.line_palettes.clear();
+    }
+
+    fn palette_for_line(&self, line: usize) -> &PaletteRam {
+        self.line_palettes.get(line).unwrap_or(&self.palette_ram)
+    }
+
     // Color test mode (debugging)
     pub fn toggle_color_test(&mut self) {
         self.color_test_mode = !self.color_test_mode;
@@ -396,26 +799,133 @@ impl Ppu {
         self.intro_text = text;
     }
 
+    pub fn set_intro_sprite(&mut self, sprite_id: Option<u32>, palette: Option<u8>) {
+        self.intro_sprite = sprite_id;
+        self.intro_palette = palette;
+    }
+
     pub fn set_zsynth_mode(&mut self, zsynth_mode: bool) {
         self.zsynth_mode = zsynth_mode;
     }
 
-    pub fn set_lives(&mut self, lives: u32) {
-        self.hud_lives = lives as u8;
+    // HUD layer - cartridges draw score, lives and menus into its cells
+    pub fn hud(&self) -> &HudLayer {
+        &self.hud
+    }
+
+    pub fn hud_mut(&mut self) -> &mut HudLayer {
+        &mut self.hud
+    }
+
+    pub fn reset_hud(&mut self) {
+        self.hud = HudLayer::for_screen(self.width, self.height);
+    }
+
+    // Color math registers. Each layer blends onto the layers below it; the
+    // background blends onto the backdrop.
+    pub fn set_layer_blend(&mut self, layer: Layer, mode: BlendMode) {
+        self.layer_blend[layer as usize] = mode;
+    }
+
+    // Layer toggles for debugging - a disabled layer is left out of the main
+    // output; the backdrop is always drawn
+    pub fn set_layer_enabled(&mut self, layer: Layer, enabled: bool) {
+        self.layers_enabled[layer as usize] = enabled;
+    }
+
+    pub fn set_master_brightness(&mut self, brightness: u8) {
+        self.color_math.set_brightness(brightness);
+    }
+
+    pub fn get_master_brightness(&self) -> u8 {
+        self.color_math.brightness()
+    }
+
+    pub fn fade_brightness(&mut self, target: u8, frames: u32) {
+        self.color_math.fade_to(target, frames);
     }
 
-    pub fn set_player_death_state(&mut self, is_dying: bool, should_flash: bool) {
-        self.player_dying = is_dying;
-        self.player_death_flash = should_flash;
+    pub fn is_fading(&self) -> bool {
+        self.color_math.is_fading()
     }
 
-    pub fn set_player_invulnerability_state(&mut self, is_invulnerable: bool, should_flash: bool) {
-        self.player_invulnerable = is_invulnerable;
-        self.player_invul_flash = should_flash;
+    pub fn set_screen_tint(&mut self, tint: Option<Tint>) {
+        self.color_math.tint = tint;
+    }
+
+    // Screen transitions - starting one replaces the current one. A replaced
+    // transition that was still running is reported as completed, so whoever
+    // started it isn't left waiting for it.
+    pub fn start_transition(&mut self, transition: Transition) {
+        if let Some(replaced) = self.transition.filter(|transition| !transition.is_finished()) {
+            self.completed_transitions.push((replaced.owner, replaced.id));
+        }
+        self.transition = Some(transition);
+    }
+
+    pub fn is_transition_running(&self) -> bool {
+        self.transition.is_some_and(|transition| !transition.is_finished())
+    }
+
+    // Transitions finished (or replaced) since the last call, oldest first
+    pub fn take_completed_transitions(&mut self) -> Vec<(TransitionOwner, u32)> {
+        std::mem::take(&mut self.completed_transitions)
+    }
+
+    fn step_transition(&mut self) {
+        let transition = match self.transition.as_mut() {
+            Some(transition) => transition,
+            None => return,
+        };
+        if transition.step() {
+            self.completed_transitions.push((transition.owner, transition.id));
+            // A finished Out transition keeps the screen covered
+            if transition.direction == TransitionDirection::In {
+                self.transition = None;
+            }
+        }
     }
 
     // Rendering
+    // Render a whole frame at once, independent of the beam
     pub fn render(&mut self) {
+        self.start_frame();
+        for line in 0..self.height {
+            self.output_line(line);
+        }
+        self.finish_frame();
+    }
+
+    fn start_frame(&mut self) {
+        self.step_animations();
+        self.begin_palette_frame();
+        self.composed = false;
+        self.compose_from = 0;
+        self.status &= !STATUS_SPRITE_OVERFLOW;
+    }
+
+    // Animated tiles and palette cycles follow the frame count. A frame
+    // rendered more than once (while stopped) steps the cycles only once.
+    fn step_animations(&mut self) {
+        self.pattern_animations.update(self.frame_count);
+        self.hud.tile_animations.update(self.frame_count);
+
+        if self.cycled_frame == Some(self.frame_count) {
+            return;
+        }
+        self.cycled_frame = Some(self.frame_count);
+        for (cycle, added) in &self.palette_cycles {
+            let elapsed = self.frame_count.saturating_sub(*added);
+            if elapsed > 0 && elapsed.is_multiple_of(cycle.frames_per_step as u64) {
+                self.palette_ram.rotate(cycle.palette, cycle.start, cycle.length);
+            }
+        }
+    }
+
+    // Draw every layer into the line buffer
+    fn compose_frame(&mut self) {
+        std::mem::swap(&mut self.screen_buffer, &mut self.line_buffer);
+
         if self.color_test_mode {
             self.render_color_test();
         } else if self.intro_mode {
@@ -423,40 +933,201 @@ impl Ppu {
         } else if self.zsynth_mode {
             self.render_zsynth_screen();
         } else {
-            self.render_game();
+            self.render_game(self.layers_enabled);
+
+            // Debug: Render coordinate display
+            self.render_debug_coordinates();
         }
+
+        std::mem::swap(&mut self.screen_buffer, &mut self.line_buffer);
+        self.composed = true;
     }
 
-    fn render_game(&mut self) {
-        // Clear screen with background color
-        let bg_color = MASTER_PALETTE[0]; // Black
-        for i in (0..self.screen_buffer.len()).step_by(4) {
-            self.screen_buffer[i] = bg_color.0;     // R
-            self.screen_buffer[i + 1] = bg_color.1; // G
-            self.screen_buffer[i + 2] = bg_color.2; // B
-            self.screen_buffer[i + 3] = 255;        // A
+    // Copy a finished line to the output, applying the screen transition, then
+    // color math. In RGBA mode brightness and tint changes mid-frame take
+    // effect from the next line; indexed lines also copy their composed
+    // indices, and the palette they're shown with is built once per frame.
+    fn output_line(&mut self, line: usize) {
+        if !self.composed {
+            self.compose_frame();
         }
 
-        // Render background patterns
-        self.render_background();
+        let row = self.width * 4;
+        let range = line * row..(line + 1) * row;
+        self.screen_buffer[range.clone()].copy_from_slice(&self.line_buffer[range.clone()]);
+        if let Some(transition) = &self.transition {
+            transition.apply_line(&mut self.screen_buffer[range.clone()], line, self.width, self.height, &self.line_buffer);
+        }
 
-        // Render sprites provided by cartridge
-        let sprites = self.sprites.clone();
-        let scroll_x = self.scroll_x;
-        let scroll_y = self.scroll_y;
+        match self.output_mode {
+            OutputMode::Indexed => {
+                let indices = line * self.width..(line + 1) * self.width;
+                self.index_buffer[indices.clone()].copy_from_slice(&self.composed_indices[indices.clone()]);
+                if let Some(transition) = &self.transition {
+                    // Master color 0 is black
+                    transition.apply_index_line(&mut self.index_buffer[indices], line, self.width, self.height, &self.composed_indices, 0);
+                }
+            }
+            OutputMode::Rgba => {
+                if !self.color_math.is_identity() {
+                    for pixel in self.screen_buffer[range].chunks_exact_mut(4) {
+                        let color = self.color_math.apply((pixel[0], pixel[1], pixel[2]));
+                        pixel[0] = color.0;
+                        pixel[1] = color.1;
+                        pixel[2] = color.2;
+                    }
+                }
+            }
+        }
+    }
 
-        // Render sprites provided by cartridge
-        for sprite in &sprites {
-            if sprite.active {
-                self.render_sprite(sprite.x - scroll_x, sprite.y - scroll_y, sprite.sprite_id, sprite.flip_horizontal);
+    // End of the visible field: per-frame output work
+    fn finish_frame(&mut self) {
+        self.end_palette_frame();
+        if self.output_mode == OutputMode::Indexed {
+            self.build_active_palette();
+        }
+        self.color_math.step_fade();
+        self.step_transition();
+        self.track_dirty_regions();
+
+        if self.post_process_enabled {
+            self.post_processor.process(&self.screen_buffer, self.width, self.height);
+        }
+    }
+
+    // Post-processing works from the RGBA frame. Settings can be changed
+    // while it's off and take effect on the next processed frame.
+    pub fn set_post_process_enabled(&mut self, enabled: bool) {
+        self.post_process_enabled = enabled;
+    }
+
+    pub fn post_process_settings_mut(&mut self) -> &mut PostProcessSettings {
+        &mut self.post_processor.settings
+    }
+
+    pub fn post_processor(&self) -> Option<&PostProcessor> {
+        if self.post_process_enabled {
+            Some(&self.post_processor)
+        } else {
+            None
+        }
+    }
+
+    // Dirty tracking - compares each finished frame with the previous one
+    pub fn set_dirty_tracking(&mut self, enabled: bool) {
+        self.dirty_tracker = if enabled { Some(DirtyTracker::new()) } else { None };
+        self.tracked_palette.clear();
+    }
+
+    pub fn dirty_tracker(&self) -> Option<&DirtyTracker> {
+        self.dirty_tracker.as_ref()
+    }
+
+    fn track_dirty_regions(&mut self) {
+        let tracker = match self.dirty_tracker.as_mut() {
+            Some(tracker) => tracker,
+            None => return,
+        };
+
+        match self.output_mode {
+            OutputMode::Rgba => tracker.update(&self.screen_buffer, self.height, 4),
+            OutputMode::Indexed => {
+                // A palette change (fades, tints) recolors every pixel
+                if self.tracked_palette != self.active_palette {
+                    tracker.invalidate();
+                    self.tracked_palette.clone_from(&self.active_palette);
+                }
+                tracker.update(&self.index_buffer, self.height, 1);
+            }
+        }
+    }
+
+    pub fn set_output_mode(&mut self, mode: OutputMode) {
+        self.output_mode = mode;
+        if let Some(tracker) = self.dirty_tracker.as_mut() {
+            tracker.invalidate();
+        }
+        if mode == OutputMode::Indexed {
+            self.index_buffer.resize(self.width * self.height, 0);
+            self.active_palette.resize(MASTER_PALETTE.len() * 4, 0);
+            self.composed_indices.resize(self.width * self.height, 0);
+        } else {
+            self.index_buffer = Vec::new();
+            self.active_palette = Vec::new();
+            self.composed_indices = Vec::new();
+        }
+        self.buffer_generation = self.buffer_generation.wrapping_add(1);
+
+        // Lines not yet output are composed again, with their indices
+        self.invalidate_lines();
+    }
+
+    pub fn get_output_mode(&self) -> OutputMode {
+        self.output_mode
+    }
+
+    // Color math is RGBA-only in indexed mode. Each index is the master color
+    // drawn last on that pixel: blend modes, sprite tints and the background
+    // blend don't change it. Brightness, screen tint and transition fades are
+    // applied to the palette the frame is displayed with, as they stand at
+    // the end of the frame.
+    fn build_active_palette(&mut self) {
+        for (entry, &color) in self.active_palette.chunks_exact_mut(4).zip(MASTER_PALETTE.iter()) {
+            let color = self.color_math.apply(color);
+            entry[0] = color.0;
+            entry[1] = color.1;
+            entry[2] = color.2;
+            entry[3] = 255;
+        }
+        if let Some(transition) = &self.transition {
+            transition.apply_palette(&mut self.active_palette);
+        }
+    }
+
+    fn render_game(&mut self, layers: [bool; LAYER_COUNT]) {
+        // Clear each line with the backdrop color palette RAM had on that
+        // line, so raster writes to the backdrop only recolor lines below them.
+        // Alpha stays 0 until the background layer draws over a pixel.
+        for y in self.compose_from..self.height {
+            let backdrop = self.line_palettes.get(y).unwrap_or(&self.palette_ram).backdrop_index();
+            let bg_color = MASTER_PALETTE[backdrop as usize];
+            for pixel in self.screen_buffer[y * self.width * 4..(y + 1) * self.width * 4].chunks_exact_mut(4) {
+                pixel[0] = bg_color.0; // R
+                pixel[1] = bg_color.1; // G
+                pixel[2] = bg_color.2; // B
+                pixel[3] = 0;          // A
+            }
+            if let Some(indices) = self.composed_indices.get_mut(y * self.width..(y + 1) * self.width) {
+                indices.fill(backdrop);
             }
         }
 
-        // Render lives counter
-        self.render_lives_counter();
+        // Render background patterns
+        if layers[Layer::Background as usize] {
+            self.render_background();
+        }
+        self.blend_background_layer();
+
+        // Render the affine background layer on top of the scenery
+        if self.mode7.enabled && layers[Layer::Mode7 as usize] {
+            self.render_mode7_layer();
+        }
 
-        // Debug: Render coordinate display
-        self.render_debug_coordinates();
+        let hud = layers[Layer::Hud as usize];
+        if hud && self.hud.priority == HudPriority::BelowSprites {
+            self.render_hud_layer();
+        }
+
+        // Render sprites provided by cartridge
+        if layers[Layer::Sprites as usize] {
+            self.render_sprite_layer(self.scroll_x, self.scroll_y);
+        }
+
+        // Render the HUD on top of the world
+        if hud && self.hud.priority == HudPriority::AboveSprites {
+            self.render_hud_layer();
+        }
     }
 
     fn render_background(&mut self) {
@@ -473,32 +1144,55 @@ impl Ppu {
         self.render_ground_terrain();
     }
 
+    // The background layer's blend mode applies against the backdrop, on the
+    // pixels the background drew (alpha 255). Leaves the whole frame opaque.
+    fn blend_background_layer(&mut self) {
+        let mode = self.layer_blend[Layer::Background as usize];
+        for (y, line) in self.screen_buffer.chunks_exact_mut(self.width * 4).enumerate().skip(self.compose_from) {
+            let backdrop = self.line_palettes.get(y).unwrap_or(&self.palette_ram).backdrop();
+            for pixel in line.chunks_exact_mut(4) {
+                if pixel[3] == 255 && mode != BlendMode::Opaque {
+                    let color = mode.apply(backdrop, (pixel[0], pixel[1], pixel[2]));
+                    pixel[0] = color.0;
+                    pixel[1] = color.1;
+                    pixel[2] = color.2;
+                }
+                pixel[3] = 255;
+            }
+        }
+    }
+
+    // A scenery color's master index as palette RAM had it on a line, None
+    // if transparent
+    fn scenery_index(&self, palette: u8, index: u8, line: usize) -> Option<u8> {
+        self.palette_for_line(line).resolve_index(PaletteRam::bg_palette_slot(palette), index)
+    }
+
     fn render_sky_gradient(&mut self) {
         // Create a vertical gradient from light blue (top) to lighter blue/white (bottom)
-        for y in 0..SCREEN_HEIGHT {
+        for y in self.compose_from..self.height {
             // Calculate gradient position (0.0 at top, 1.0 at bottom)
-            let gradient_pos = y as f32 / SCREEN_HEIGHT as f32;
+            let gradient_pos = y as f32 / self.height as f32;
 
             // Sky gradient: bright blue at top, lighter towards horizon
             let palette_index = if gradient_pos < 0.3 {
-                84u8  // Bright blue
+                1u8  // Bright blue
             } else if gradient_pos < 0.6 {
-                85u8  // Slightly lighter blue
+                2u8  // Slightly lighter blue
             } else if gradient_pos < 0.8 {
-                86u8  // Even lighter blue
+                3u8  // Even lighter blue
             } else {
-                87u8  // Light blue near horizon
+                4u8  // Light blue near horizon
             };
 
-            let color = MASTER_PALETTE[palette_index as usize % MASTER_PALETTE.len()];
+            let color = match self.scenery_index(SKY_PALETTE, palette_index, y) {
+                Some(color) => color,
+                None => continue,
+            };
 
             // Fill the entire width with this color
-            for x in 0..SCREEN_WIDTH {
-                let pixel_index = (y * SCREEN_WIDTH + x) * 4;
-                self.screen_buffer[pixel_index] = color.0;
-                self.screen_buffer[pixel_index + 1] = color.1;
-                self.screen_buffer[pixel_index + 2] = color.2;
-                self.screen_buffer[pixel_index + 3] = 255;
+            for x in 0..self.width {
+                self.put_pixel(y * self.width + x, color);
             }
         }
     }
@@ -509,16 +1203,14 @@ impl Ppu {
         let mountain_offset = -self.scroll_x * mountain_parallax_factor;
 
         // Render mountain layers (back to front)
-        self.render_mountain_layer(mountain_offset * 0.5, 100, 96u8);  // Far mountains (purple)
-        self.render_mountain_layer(mountain_offset * 0.7, 120, 80u8);  // Mid mountains (darker blue)
-        self.render_mountain_layer(mountain_offset, 140, 48u8);        // Near mountains (dark green)
+        self.render_mountain_layer(mountain_offset * 0.5, 100, 1u8);  // Far mountains (purple)
+        self.render_mountain_layer(mountain_offset * 0.7, 120, 2u8);  // Mid mountains (darker blue)
+        self.render_mountain_layer(mountain_offset, 140, 3u8);        // Near mountains (dark green)
     }
 
     fn render_mountain_layer(&mut self, offset: f32, base_height: usize, color_index: u8) {
-        let color = MASTER_PALETTE[color_index as usize % MASTER_PALETTE.len()];
-
         // Create mountain silhouette using a simple sin wave pattern
-        for x in 0..SCREEN_WIDTH {
+        for x in 0..self.width {
             let world_x = x as f32 + self.scroll_x + offset;
 
             // Create mountain profile using multiple sin waves for natural look
@@ -531,19 +1223,10 @@ impl Ppu {
             let mountain_top = (base_height as f32 + mountain_height) as usize;
 
             // Fill from mountain top to bottom of screen
-            for y in mountain_top..SCREEN_HEIGHT {
-                if y < SCREEN_HEIGHT {
-                    let pixel_index = (y * SCREEN_WIDTH + x) * 4;
-                    // Blend with existing color for transparency effect
-                    let existing_r = self.screen_buffer[pixel_index];
-                    let existing_g = self.screen_buffer[pixel_index + 1];
-                    let existing_b = self.screen_buffer[pixel_index + 2];
-
-                    // Simple alpha blending (50% mountain, 50% sky)
-                    self.screen_buffer[pixel_index] = ((color.0 as u16 + existing_r as u16) / 2) as u8;
-                    self.screen_buffer[pixel_index + 1] = ((color.1 as u16 + existing_g as u16) / 2) as u8;
-                    self.screen_buffer[pixel_index + 2] = ((color.2 as u16 + existing_b as u16) / 2) as u8;
-                    self.screen_buffer[pixel_index + 3] = 255;
+            for y in mountain_top..self.height {
+                // 50% blend with the sky for a hazy, distant look
+                if let Some(color) = self.scenery_index(HILLS_PALETTE, color_index, y) {
+                    self.blend_pixel(x, y, color, BlendMode::Half);
                 }
             }
         }
@@ -553,7 +1236,7 @@ impl Ppu {
         // Render ground level terrain that scrolls with camera
         let ground_level = 200; // Base ground level in world coordinates
 
-        for x in 0..SCREEN_WIDTH {
+        for x in 0..self.width {
             let world_x = x as f32 + self.scroll_x;
 
             // Create slight terrain variation
@@ -564,25 +1247,25 @@ impl Ppu {
             let screen_ground_y = (world_ground_y as f32 - self.scroll_y) as i32;
 
             // Render ground from terrain level to bottom of screen
-            for screen_y in screen_ground_y.max(0)..SCREEN_HEIGHT as i32 {
-                if screen_y >= 0 && screen_y < SCREEN_HEIGHT as i32 {
-                    let pixel_index = (screen_y as usize * SCREEN_WIDTH + x) * 4;
+            for screen_y in screen_ground_y.max(self.compose_from as i32)..self.height as i32 {
+                if screen_y >= 0 && screen_y < self.height as i32 {
+                    let pixel_index = screen_y as usize * self.width + x;
 
                     // Ground color based on depth from surface
                     let depth = screen_y - screen_ground_y;
                     let ground_color = if depth < 5 {
-                        49u8  // Bright green grass
+                        1u8  // Bright green grass
                     } else if depth < 15 {
-                        33u8  // Brown dirt
+                        2u8  // Brown dirt
                     } else {
-                        17u8  // Dark brown rock
+                        3u8  // Dark brown rock
                     };
 
-                    let color = MASTER_PALETTE[ground_color as usize % MASTER_PALETTE.len()];
-                    self.screen_buffer[pixel_index] = color.0;
-                    self.screen_buffer[pixel_index + 1] = color.1;
-                    self.screen_buffer[pixel_index + 2] = color.2;
-                    self.screen_buffer[pixel_index + 3] = 255;
+                    let color = match self.scenery_index(GROUND_PALETTE, ground_color, screen_y as usize) {
+                        Some(color) => color,
+                        None => continue,
+                    };
+                    self.put_pixel(pixel_index, color);
                 }
             }
         }
@@ -595,7 +1278,7 @@ impl Ppu {
 
         // Place trees at regular intervals
         for tree_pos in (0..1200).step_by(80) {
-            let tree_x = (tree_pos as f32 + tree_offset) % (SCREEN_WIDTH as f32 + 100.0) - 50.0;
+            let tree_x = (tree_pos as f32 + tree_offset) % (self.width as f32 + 100.0) - 50.0;
             let ground_y = 200.0 + ((tree_x * 0.02).sin() * 5.0); // Follow ground contour
 
             self.render_single_tree(tree_x as i32, ground_y as i32);
@@ -608,41 +1291,33 @@ impl Ppu {
         let trunk_height = 25;
         let crown_radius = 15;
 
-        // Render trunk
-        let trunk_color = MASTER_PALETTE[32 % MASTER_PALETTE.len()]; // Brown
+        // Render trunk (brown)
         for y in (base_y - trunk_height)..base_y {
             for x in (base_x - trunk_width / 2)..(base_x + trunk_width / 2) {
-                if x >= 0 && x < SCREEN_WIDTH as i32 && y >= 0 && y < SCREEN_HEIGHT as i32 {
-                    let pixel_index = ((y as usize * SCREEN_WIDTH) + x as usize) * 4;
-                    if pixel_index + 3 < self.screen_buffer.len() {
-                        self.screen_buffer[pixel_index] = trunk_color.0;
-                        self.screen_buffer[pixel_index + 1] = trunk_color.1;
-                        self.screen_buffer[pixel_index + 2] = trunk_color.2;
-                        self.screen_buffer[pixel_index + 3] = 255;
-                    }
+                if x >= 0 && x < self.width as i32 && y >= self.compose_from as i32 && y < self.height as i32 {
+                    let Some(trunk_color) = self.scenery_index(HILLS_PALETTE, 4, y as usize) else {
+                        continue;
+                    };
+                    self.put_pixel((y as usize * self.width) + x as usize, trunk_color);
                 }
             }
         }
 
-        // Render crown (circular)
-        let crown_color = MASTER_PALETTE[48 % MASTER_PALETTE.len()]; // Dark green
+        // Render crown (circular, dark green)
         let crown_center_y = base_y - trunk_height - crown_radius / 2;
 
         for y in (crown_center_y - crown_radius)..(crown_center_y + crown_radius) {
             for x in (base_x - crown_radius)..(base_x + crown_radius) {
-                if x >= 0 && x < SCREEN_WIDTH as i32 && y >= 0 && y < SCREEN_HEIGHT as i32 {
+                if x >= 0 && x < self.width as i32 && y >= self.compose_from as i32 && y < self.height as i32 {
                     let dist_sq = (x - base_x) * (x - base_x) + (y - crown_center_y) * (y - crown_center_y);
                     if dist_sq <= (crown_radius * crown_radius) {
-                        let pixel_index = ((y as usize * SCREEN_WIDTH) + x as usize) * 4;
-                        if pixel_index + 3 < self.screen_buffer.len() {
-                            // Add some variation to the crown shape
-                            let variation = ((x as f32 * 0.3).sin() + (y as f32 * 0.4).cos()) * 0.3;
-                            if variation > -0.2 { // Create irregular crown edge
-                                self.screen_buffer[pixel_index] = crown_color.0;
-                                self.screen_buffer[pixel_index + 1] = crown_color.1;
-                                self.screen_buffer[pixel_index + 2] = crown_color.2;
-                                self.screen_buffer[pixel_index + 3] = 255;
-                            }
+                        // Add some variation to the crown shape
+                        let variation = ((x as f32 * 0.3).sin() + (y as f32 * 0.4).cos()) * 0.3;
+                        if variation > -0.2 { // Create irregular crown edge
+                            let Some(crown_color) = self.scenery_index(HILLS_PALETTE, 3, y as usize) else {
+                                continue;
+                            };
+                            self.put_pixel((y as usize * self.width) + x as usize, crown_color);
                         }
                     }
                 }
@@ -650,330 +1325,248 @@ impl Ppu {
         }
     }
 
-    fn render_sprite(&mut self, x: f32, y: f32, sprite_id: u32, flip_horizontal: bool) {
-        // Get sprite dimensions based on sprite type
-        let (sprite_width, sprite_height) = match sprite_id {
-            0 => (32, 28),  // Hambert (larger)
-            1 => (32, 16),  // Platform
-            2 => (24, 24),  // Enemy
-            6 => (16, 16),  // Hamberry
-            3 => (20, 28),  // Hexagnome (scaled down for performance)
-            4 => (12, 12),  // Shuriken
-            5 => (12, 12),  // Small Hambert head (for lives counter)
-            10 => (25, 80), // White piano key (unpressed)
-            11 => (25, 80), // White piano key (pressed)
-            12 => (15, 50), // Black piano key (unpressed)
-            13 => (15, 50), // Black piano key (pressed)
-            _ => (32, 32),  // Default
-        };
+    fn render_mode7_layer(&mut self) {
+        let bank = std::mem::take(&mut self.sprite_bank);
+        if let Some(pattern) = bank.get(self.pattern_animations.resolve(self.mode7.pattern_id)) {
+            let slot = self.mode7.palette.map(PaletteRam::bg_palette_slot);
+            let (width, height) = (pattern.width as i32, pattern.height as i32);
+
+            for screen_y in self.mode7.first_line..=self.mode7.last_line {
+                for screen_x in 0..self.width {
+                    let (u, v) = self.mode7.texture_coords(screen_x, screen_y);
+                    let (mut tx, mut ty) = (u.floor() as i32, v.floor() as i32);
+                    if self.mode7.wrap {
+                        tx = tx.rem_euclid(width);
+                        ty = ty.rem_euclid(height);
+                    } else if tx < 0 || ty < 0 || tx >= width || ty >= height {
+                        continue;
+                    }
+
+                    let color_index = pattern.pixel(tx as u32, ty as u32);
+                    if color_index == 0 {
+                        continue;
+                    }
+                    let color = match slot {
+                        Some(slot) => match self.palette_for_line(screen_y).resolve_index(slot, color_index) {
+                            Some(color) => color,
+                            None => continue,
+                        },
+                        None => color_index,
+                    };
+                    self.blend_pixel(screen_x, screen_y, color, self.layer_blend[Layer::Mode7 as usize]);
+                }
+            }
+        }
+        self.sprite_bank = bank;
+    }
+
+    // Metasprites are expanded into hardware sprites, then each hardware
+    // sprite is drawn on the lines where it got one of the line's sprite
+    // slots. Sprites past the per-frame limit are not drawn.
+    fn render_sprite_layer(&mut self, scroll_x: f32, scroll_y: f32) {
+        let bank = std::mem::take(&mut self.sprite_bank);
+        let hardware_sprites = expand_sprites(&self.sprites, &bank, &self.pattern_animations, scroll_x, scroll_y);
+        let mut counter = ScanlineSpriteCounter::new(self.height);
+        self.sprite_lines.resize(self.height, false);
+
+        let mut drawn = 0;
+        for (sprite, x, y) in &hardware_sprites {
+            // Sprites without a pattern in the bank are not drawn
+            let pattern = match bank.get(sprite.sprite_id) {
+                Some(pattern) => pattern,
+                None => continue,
+            };
+            if drawn == MAX_HARDWARE_SPRITES {
+                break;
+            }
+            drawn += 1;
+
+            let (top, bottom) = sprite_line_span(pattern, *y, sprite.affine);
+            counter.claim(top, bottom, &mut self.sprite_lines);
+            match sprite.affine {
+                Some(affine) => self.render_affine_pattern(pattern, sprite, *x, *y, affine),
+                None => self.render_pattern(pattern, sprite, *x, *y),
+            }
+        }
+
+        if counter.overflowed() {
+            self.status |= STATUS_SPRITE_OVERFLOW;
+        }
+        self.sprite_bank = bank;
+    }
+
+    fn render_pattern(&mut self, pattern: &SpritePattern, sprite: &SpriteData, x: f32, y: f32) {
+        let sprite_width = pattern.width;
+        let sprite_height = pattern.height;
 
         for py in 0..sprite_height {
             for px in 0..sprite_width {
                 let screen_x = x as i32 + px as i32;
                 let screen_y = y as i32 + py as i32;
 
-                if screen_x >= 0 && screen_x < SCREEN_WIDTH as i32 &&
-                   screen_y >= 0 && screen_y < SCREEN_HEIGHT as i32 {
+                if screen_x >= 0 && screen_x < self.width as i32 &&
+                   screen_y >= 0 && screen_y < self.height as i32 {
 
-                    // Apply horizontal flipping if needed
-                    let sprite_px = if flip_horizontal {
+                    // Apply flipping if needed
+                    let sprite_px = if sprite.flip_horizontal {
                         sprite_width - 1 - px
                     } else {
                         px
                     };
-                    
-                    let color_index = self.get_sprite_pixel(sprite_id, sprite_px, py);
-                    if color_index > 0 {
-                        let mut color = MASTER_PALETTE[color_index as usize % MASTER_PALETTE.len()];
-                        
-                        // Apply death flash effect for player sprite (sprite_id 0)
-                        if sprite_id == 0 && self.player_dying {
-                            if self.player_death_flash {
-                                // Flash white
-                                color = (255, 255, 255);
-                            } else {
-                                // Flash red
-                                color = (255, 100, 100);
-                            }
-                        }
-                        // Apply invulnerability flash effect for player sprite (sprite_id 0)
-                        else if sprite_id == 0 && self.player_invulnerable {
-                            if self.player_invul_flash {
-                                // Make semi-transparent (skip rendering this pixel)
-                                continue;
-                            } else {
-                                // Normal rendering
-                            }
-                        }
-                        
-                        let buffer_index = (screen_y as usize * SCREEN_WIDTH + screen_x as usize) * 4;
+                    let sprite_py = if sprite.flip_vertical {
+                        sprite_height - 1 - py
+                    } else {
+                        py
+                    };
 
-                        if buffer_index + 3 < self.screen_buffer.len() {
-                            self.screen_buffer[buffer_index] = color.0;
-                            self.screen_buffer[buffer_index + 1] = color.1;
-                            self.screen_buffer[buffer_index + 2] = color.2;
-                            self.screen_buffer[buffer_index + 3] = 255;
-                        }
-                    }
+                    let color_index = pattern.pixel(sprite_px, sprite_py);
+                    self.plot_sprite_pixel(sprite, screen_x as usize, screen_y as usize, color_index);
                 }
             }
         }
     }
 
-    fn get_sprite_pixel(&self, sprite_id: u32, x: u32, y: u32) -> u8 {
-        match sprite_id {
-            0 => self.get_new_hambert_pixel(x, y),  // Player/Hambert (new improved sprite)
-            1 => self.get_platform_pixel(x, y),     // Platform
-            2 => self.get_enemy_pixel(x, y),        // Basic enemy
-            3 => self.get_hexagnome_pixel(x, y),    // Hexagnome
-            4 => self.get_shuriken_pixel(x, y),     // Shuriken
-            5 => self.get_small_hambert_head_pixel(x, y), // Small Hambert head
-            6 => self.get_hamberry_pixel(x, y),     // Hamberry collectible
-            7 => self.get_blood_goblin_pixel(x, y), // Blood Goblin
-            10 => self.get_white_piano_key_pixel(x, y, false), // White key unpressed
-            11 => self.get_white_piano_key_pixel(x, y, true),  // White key pressed
-            12 => self.get_black_piano_key_pixel(x, y, false), // Black key unpressed
-            13 => self.get_black_piano_key_pixel(x, y, true),  // Black key pressed
-            _ => 0, // Transparent for unknown sprites
+    fn render_affine_pattern(&mut self, pattern: &SpritePattern, sprite: &SpriteData, x: f32, y: f32, affine: SpriteAffine) {
+        if affine.scale_x == 0.0 || affine.scale_y == 0.0 {
+            return;
         }
-    }
 
-    fn get_hambert_pixel(&self, x: u32, y: u32) -> u8 {
-        // Scale from 32x28 back to original 24x20 sprite data
-        if x >= 32 || y >= 28 {
-            return 0; // Transparent outside bounds
-        }
-        
-        // Scale coordinates to original sprite size
-        let orig_x = (x * 24) / 32;  // Scale from 32 to 24
-        let orig_y = (y * 20) / 28;  // Scale from 28 to 20
-
-        // Original pixel data array from hambertBoy.js (24x20, scaled to 32x28)
-        let pixel_data = [
-            [0,0,0,0,0,0,0,1,1,1,1,1,1,1,1,1,0,0,0,0,0,0,0,0],
-            [0,0,0,0,1,1,1,1,1,1,1,1,1,1,1,1,1,1,0,0,0,0,0,0],
-            [0,0,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,0,0,0],
-            [0,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,0],
-            [1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1],
-            [1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1],
-            [1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1],
-            [1,1,1,2,2,2,1,1,1,1,1,1,1,1,1,2,2,2,1,1,1,1,1,1],
-            [1,1,1,2,3,2,1,1,1,1,1,1,1,1,1,2,3,2,1,1,1,1,1,1],
-            [1,1,1,1,1,1,1,1,1,1,4,4,4,1,1,1,1,1,1,1,1,1,1,1],
-            [1,1,1,1,1,1,1,1,1,4,4,4,4,4,1,1,1,1,1,1,1,1,1,1],
-            [1,1,1,1,1,1,1,1,1,1,4,4,4,1,1,1,1,1,1,1,1,1,1,1],
-            [0,0,1,1,1,1,1,1,1,1,1,6,6,1,1,1,1,1,1,1,1,1,0,0],
-            [0,0,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,0,0],
-            [0,0,0,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,0,0,0],
-            [0,0,0,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,0,0,0],
-            [0,0,0,0,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,0,0,0,0],
-            [0,0,0,0,7,7,7,7,7,0,0,0,0,7,7,7,7,7,0,0,0,0,0,0],
-            [0,0,0,0,5,5,5,5,5,5,0,0,5,5,5,5,5,5,0,0,0,0,0,0],
-            [0,0,0,0,5,5,5,5,5,5,0,0,5,5,5,5,5,5,0,0,0,0,0,0],
-        ];
-
-        // Use scaled coordinates to access original pixel data
-        let pixel = pixel_data[orig_y as usize][orig_x as usize];
-        match pixel {
-            0 => 0,   // transparent
-            1 => 10,  // mid-light gray fur
-            2 => 0,   // black eye outline
-            3 => 15,  // white eye
-            4 => 0,   // black nose
-            5 => 16,  // red boots
-            6 => 120, // pink tongue
-            7 => 32,  // brown boot tops
-            _ => 0,   // transparent fallback
-        }
-    }
-
-    fn get_platform_pixel(&self, x: u32, y: u32) -> u8 {
-        // Detailed platform texture like original
-        if y < 2 {
-            // Top grass layer with variation
-            if (x + y) % 3 == 0 {
-                52 // Bright green grass
-            } else {
-                49 // Medium green grass
-            }
-        } else if y < 6 {
-            // Dirt layer with some texture
-            if (x + y) % 4 == 0 {
-                34 // Lighter brown dirt
-            } else {
-                33 // Medium brown dirt
-            }
-        } else if y < 10 {
-            // Deeper dirt
-            if (x + y) % 5 == 0 {
-                32 // Dark brown
-            } else {
-                17 // Very dark brown
-            }
-        } else {
-            // Rock layer at bottom
-            if (x + y) % 6 == 0 {
-                8 // Dark gray rock
-            } else {
-                1 // Very dark gray/black rock
+        let sprite_width = pattern.width as f32;
+        let sprite_height = pattern.height as f32;
+        let center_x = x + sprite_width / 2.0;
+        let center_y = y + sprite_height / 2.0;
+
+        // Screen-space bounding box of the rotated, scaled pattern
+        let half_width = sprite_width * affine.scale_x.abs() / 2.0;
+        let half_height = sprite_height * affine.scale_y.abs() / 2.0;
+        let radius = (half_width * half_width + half_height * half_height).sqrt();
+        let min_x = ((center_x - radius).floor() as i32).max(0);
+        let max_x = ((center_x + radius).ceil() as i32).min(self.width as i32);
+        let min_y = ((center_y - radius).floor() as i32).max(0);
+        let max_y = ((center_y + radius).ceil() as i32).min(self.height as i32);
+
+        // Map each covered screen pixel back into the pattern (inverse transform)
+        let (sin, cos) = affine.rotation.sin_cos();
+        for screen_y in min_y..max_y {
+            for screen_x in min_x..max_x {
+                let dx = screen_x as f32 + 0.5 - center_x;
+                let dy = screen_y as f32 + 0.5 - center_y;
+                let u = (dx * cos + dy * sin) / affine.scale_x + sprite_width / 2.0;
+                let v = (dy * cos - dx * sin) / affine.scale_y + sprite_height / 2.0;
+                if u < 0.0 || v < 0.0 || u >= sprite_width || v >= sprite_height {
+                    continue;
+                }
+
+                let mut sprite_px = u as u32;
+                let mut sprite_py = v as u32;
+                if sprite.flip_horizontal {
+                    sprite_px = pattern.width - 1 - sprite_px;
+                }
+                if sprite.flip_vertical {
+                    sprite_py = pattern.height - 1 - sprite_py;
+                }
+
+                let color_index = pattern.pixel(sprite_px, sprite_py);
+                self.plot_sprite_pixel(sprite, screen_x as usize, screen_y as usize, color_index);
             }
         }
     }
 
-    fn get_enemy_pixel(&self, x: u32, y: u32) -> u8 {
-        // Simple 24x24 enemy sprite (red)
-        if x < 2 || x >= 22 || y < 2 || y >= 22 {
-            0 // Black border
-        } else {
-            16 // Red from palette
+    fn plot_sprite_pixel(&mut self, sprite: &SpriteData, screen_x: usize, screen_y: usize, color_index: u8) {
+        if color_index == 0 || !self.sprite_lines.get(screen_y).copied().unwrap_or(false) {
+            return;
         }
-    }
 
-    fn get_hexagnome_pixel(&self, x: u32, y: u32) -> u8 {
-        // 20x28 hexagnome sprite - full bitmap with scaling
-        if x >= 20 || y >= 28 {
-            return 0; // Transparent outside bounds
-        }
-        
-        // Scale coordinates to original sprite size for lookup
-        let orig_x = (x * 26) / 20;  // Scale from 20 to 26
-        let orig_y = (y * 32) / 28;  // Scale from 28 to 32
-        
-        if orig_x >= 26 || orig_y >= 32 {
-            return 0;
-        }
-        
-        // Original hexagnome pixel data - kept as static for performance
-        static HEXAGNOME_PIXEL_DATA: [[u8; 26]; 32] = [
-            [0,0,0,0,0,0,0,0,1,3,3,4,4,3,3,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,3,0,3,3,4,4,3,3,2,1,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,1,6,5,2,3,3,3,2,2,2,4,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,8,13,7,2,2,2,2,2,4,4,4,3,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,13,11,11,4,2,2,3,4,5,4,4,3,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,11,10,10,9,3,0,2,3,2,2,2,4,5,4,0,0,0,0,0,0],
-            [0,0,0,0,0,9,12,9,3,1,1,1,1,2,1,2,5,4,5,5,3,0,0,0,0,0],
-            [0,0,0,0,0,0,4,1,1,1,1,1,2,2,3,5,6,5,4,5,4,4,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,0,1,1,2,3,5,5,6,5,4,5,4,3,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,0,0,1,2,4,5,5,6,4,4,6,5,2,2,0,0,0],
-            [0,0,0,0,0,0,0,0,0,3,1,1,3,4,5,6,6,4,4,5,6,2,2,0,0,0],
-            [0,0,0,0,0,0,0,0,3,1,1,1,3,5,5,6,5,3,4,5,5,3,3,0,0,0],
-            [0,0,0,0,0,0,3,3,3,1,0,1,3,5,5,6,5,3,4,4,5,3,3,0,0,0],
-            [0,3,3,3,3,3,3,3,3,1,2,3,3,4,5,6,3,3,4,4,6,3,2,0,0,0],
-            [0,3,13,13,12,3,3,3,2,3,3,3,4,4,4,4,3,3,4,5,4,1,0,1,0,0],
-            [0,0,3,13,12,3,3,3,3,3,3,3,4,3,3,4,4,4,4,5,5,3,2,1,0,0],
-            [0,0,3,3,3,0,0,3,3,3,3,4,3,2,3,5,4,4,4,5,4,3,5,3,0,0],
-            [0,0,0,0,0,0,3,3,3,3,2,2,1,1,4,5,4,4,4,4,4,4,2,3,3,0],
-            [0,0,0,0,0,2,3,3,2,2,1,1,1,1,2,4,4,4,4,4,4,4,2,2,3,0],
-            [0,0,0,0,0,5,2,2,1,0,1,2,3,3,3,3,4,4,4,4,4,3,2,2,3,0],
-            [0,0,0,0,3,12,12,8,1,2,2,3,4,4,3,3,4,4,4,4,4,3,2,2,3,0],
-            [0,0,0,0,3,13,13,7,3,3,3,4,4,4,3,3,3,4,4,4,3,3,2,3,2,0],
-            [0,0,0,0,3,3,3,3,3,3,3,3,4,5,4,4,4,4,4,3,3,2,2,3,3,0],
-            [0,0,0,0,0,0,0,0,3,3,3,2,3,4,5,5,4,4,3,3,3,2,1,3,0,0],
-            [0,0,0,0,0,0,0,0,3,3,4,3,2,2,3,4,4,3,3,3,3,2,1,3,0,0],
-            [0,0,0,0,0,0,0,4,3,3,4,5,4,3,3,3,4,3,3,3,2,2,3,3,0,0],
-            [0,0,0,0,0,0,0,3,3,3,4,4,4,4,4,3,3,3,3,3,2,2,3,3,0,0],
-            [0,0,0,0,0,0,0,3,3,2,3,4,4,4,4,3,3,3,3,3,2,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,0,3,3,3,3,3,3,3,3,3,3,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,3,3,3,3,3,2,3,3,3,3,3,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,3,3,3,3,3,2,3,3,3,3,3,3,3,0,0,0,0,0],
-            [0,0,0,0,0,0,3,3,3,3,3,3,2,3,3,3,3,3,3,3,3,0,0,0,0,0],
-        ];
-
-        HEXAGNOME_PIXEL_DATA[orig_y as usize][orig_x as usize]
-    }
-
-    fn get_shuriken_pixel(&self, x: u32, y: u32) -> u8 {
-        // 12x12 spinning shuriken - simplified for now
-        let center_x = 6.0;
-        let center_y = 6.0;
-        let dx = x as f32 - center_x;
-        let dy = y as f32 - center_y;
-        let distance = (dx * dx + dy * dy).sqrt();
-
-        if distance <= 4.0 && distance >= 1.0 {
-            if distance <= 2.8 {
-                7  // Light gray center
-            } else {
-                0  // Black edges
+        // Sprites with a sub-palette index through palette RAM. Direct-color
+        // sprites (no sub-palette) index the master palette and aren't counted
+        // against the on-screen color limit.
+        let index = match sprite.palette {
+            Some(palette) => {
+                let slot = PaletteRam::sprite_palette_slot(palette);
+                match self.palette_for_line(screen_y).resolve_index(slot, color_index) {
+                    Some(index) => index,
+                    None => return,
+                }
             }
+            None => color_index,
+        };
+
+        let color = match sprite.tint {
+            Some(tint) => tint.apply(MASTER_PALETTE[index as usize]),
+            None => MASTER_PALETTE[index as usize],
+        };
+
+        // A sprite's own blend mode overrides the sprite layer's
+        let blend = if sprite.blend != BlendMode::Opaque {
+            sprite.blend
         } else {
-            0 // Transparent
+            self.layer_blend[Layer::Sprites as usize]
+        };
+        self.blend_color(screen_x, screen_y, color, index, blend);
+    }
+
+    // Opaque write of a master color to a composed pixel
+    fn put_pixel(&mut self, pixel: usize, index: u8) {
+        let color = MASTER_PALETTE[index as usize];
+        self.screen_buffer[pixel * 4..pixel * 4 + 4].copy_from_slice(&[color.0, color.1, color.2, 255]);
+        if let Some(composed) = self.composed_indices.get_mut(pixel) {
+            *composed = index;
         }
     }
 
-    fn get_blood_goblin_pixel(&self, x: u32, y: u32) -> u8 {
-        // 20x38 blood goblin sprite using actual converted data
-        if x >= 20 || y >= 38 {
-            return 0; // Transparent outside bounds
+    fn blend_pixel(&mut self, x: usize, y: usize, index: u8, mode: BlendMode) {
+        self.blend_color(x, y, MASTER_PALETTE[index as usize], index, mode);
+    }
+
+    // Blends (and tints) are RGBA-only: the index plane takes the drawn
+    // pixel's master index as is
+    fn blend_color(&mut self, x: usize, y: usize, color: (u8, u8, u8), index: u8, mode: BlendMode) {
+        if x >= self.width || y >= self.height || y < self.compose_from {
+            return;
+        }
+
+        let buffer_index = (y * self.width + x) * 4;
+        let existing = (
+            self.screen_buffer[buffer_index],
+            self.screen_buffer[buffer_index + 1],
+            self.screen_buffer[buffer_index + 2],
+        );
+        let color = mode.apply(existing, color);
+        self.screen_buffer[buffer_index] = color.0;
+        self.screen_buffer[buffer_index + 1] = color.1;
+        self.screen_buffer[buffer_index + 2] = color.2;
+        self.screen_buffer[buffer_index + 3] = 255;
+        if let Some(composed) = self.composed_indices.get_mut(y * self.width + x) {
+            *composed = index;
         }
-        
-        // Blood goblin pixel data (20x38) - using the actual converted sprite data
-        static BLOOD_GOBLIN_PIXEL_DATA: [[u8; 20]; 38] = [
-            [0,0,0,0,0,0,122,122,0,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,45,5,0,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,5,30,18,17,17,6,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,8,42,45,19,18,18,45,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,7,45,9,8,45,18,45,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,4,41,41,45,45,30,6,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,4,28,28,4,5,8,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,44,44,28,4,4,44,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,28,45,17,17,18,44,4,17,45,8,0,0,0,0,0,0],
-            [0,0,47,9,44,30,7,18,18,45,45,45,8,8,0,0,0,0,0,0],
-            [0,47,42,3,45,46,45,18,45,45,45,44,5,42,8,0,0,0,0,0],
-            [47,41,4,0,8,45,45,45,45,29,18,110,6,45,41,0,0,0,0,0],
-            [47,6,0,0,110,30,45,45,18,18,17,0,0,0,41,9,0,0,0,0],
-            [9,6,0,0,110,45,45,45,45,18,45,0,0,0,41,9,0,0,0,0],
-            [0,41,6,0,8,45,45,18,18,45,29,0,0,3,8,0,0,0,0,0],
-            [0,7,8,0,110,18,18,29,18,29,23,0,4,41,0,0,0,0,0,0],
-            [0,0,8,7,45,18,29,18,18,18,23,8,3,0,0,0,0,0,0,0],
-            [0,0,10,7,6,28,18,18,18,18,44,4,8,0,0,0,0,0,0,0],
-            [0,0,0,11,28,17,18,18,18,44,4,8,0,0,0,0,0,0,0,0],
-            [0,0,0,0,18,18,18,18,18,16,8,0,0,0,0,0,0,0,0,0],
-            [0,0,0,111,18,18,18,18,18,18,25,0,0,0,0,0,0,0,0,0],
-            [0,0,0,45,18,18,18,18,18,18,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,17,18,18,18,18,17,45,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,45,17,18,18,18,17,25,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,18,17,18,17,18,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,17,17,17,17,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,110,17,45,17,17,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,45,17,111,25,17,45,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,17,17,0,0,0,45,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,17,45,0,0,0,10,110,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,17,9,0,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,45,17,0,0,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,16,46,0,0,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,16,0,0,0,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,45,16,0,0,0,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,16,17,0,0,0,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],
-        ];
-        
-        BLOOD_GOBLIN_PIXEL_DATA[y as usize][x as usize]
     }
 
     fn render_debug_coordinates(&mut self) {
         // Show world coordinates at each corner
-        let text_color = MASTER_PALETTE[15]; // White
+        let text_color = 15; // White
+        let width = self.width as f32;
+        let height = self.height as f32;
+        let right_x = self.width - 70;
+        let bottom_y = self.height - 10;
 
         // Top-left: (scroll_x, scroll_y)
         let tl_text = format!("({:.0},{:.0})", self.scroll_x, self.scroll_y);
         self.render_text(&tl_text, 2, 2, text_color);
 
-        // Top-right: (scroll_x + 320, scroll_y)
-        let tr_text = format!("({:.0},{:.0})", self.scroll_x + 320.0, self.scroll_y);
-        self.render_text(&tr_text, 250, 2, text_color);
+        // Top-right: (scroll_x + width, scroll_y)
+        let tr_text = format!("({:.0},{:.0})", self.scroll_x + width, self.scroll_y);
+        self.render_text(&tr_text, right_x, 2, text_color);
 
-        // Bottom-left: (scroll_x, scroll_y + 240)
-        let bl_text = format!("({:.0},{:.0})", self.scroll_x, self.scroll_y + 240.0);
-        self.render_text(&bl_text, 2, 230, text_color);
+        // Bottom-left: (scroll_x, scroll_y + height)
+        let bl_text = format!("({:.0},{:.0})", self.scroll_x, self.scroll_y + height);
+        self.render_text(&bl_text, 2, bottom_y, text_color);
 
-        // Bottom-right: (scroll_x + 320, scroll_y + 240)
-        let br_text = format!("({:.0},{:.0})", self.scroll_x + 320.0, self.scroll_y + 240.0);
-        self.render_text(&br_text, 250, 230, text_color);
+        // Bottom-right: (scroll_x + width, scroll_y + height)
+        let br_text = format!("({:.0},{:.0})", self.scroll_x + width, self.scroll_y + height);
+        self.render_text(&br_text, right_x, bottom_y, text_color);
     }
 
-    fn render_text(&mut self, text: &str, x: usize, y: usize, color: (u8, u8, u8)) {
+    fn render_text(&mut self, text: &str, x: usize, y: usize, color: u8) {
         // Multi-language text rendering using the font system
         let characters = self.font_system.encode_text(text);
         
@@ -989,7 +1582,7 @@ impl Ppu {
         self.font_system.set_language(language);
     }
 
-    fn render_char_data(&mut self, font_data: &[u8; 8], x: usize, y: usize, color: (u8, u8, u8)) {
+    fn render_char_data(&mut self, font_data: &[u8; 8], x: usize, y: usize, color: u8) {
 
         for row in 0..8 {
             let byte = font_data[row];
@@ -998,21 +1591,15 @@ impl Ppu {
                     let pixel_x = x + col;
                     let pixel_y = y + row;
 
-                    if pixel_x < SCREEN_WIDTH && pixel_y < SCREEN_HEIGHT {
-                        let pixel_index = (pixel_y * SCREEN_WIDTH + pixel_x) * 4;
-                        if pixel_index + 3 < self.screen_buffer.len() {
-                            self.screen_buffer[pixel_index] = color.0;
-                            self.screen_buffer[pixel_index + 1] = color.1;
-                            self.screen_buffer[pixel_index + 2] = color.2;
-                            self.screen_buffer[pixel_index + 3] = 255;
-                        }
+                    if pixel_x < self.width && pixel_y < self.height {
+                        self.blend_pixel(pixel_x, pixel_y, color, self.layer_blend[Layer::Hud as usize]);
                     }
                 }
             }
         }
     }
 
-    fn render_small_text(&mut self, text: &str, x: usize, y: usize, color: (u8, u8, u8)) {
+    fn render_small_text(&mut self, text: &str, x: usize, y: usize, color: u8) {
         // Small text rendering at 6 pixels tall (6x5 pixels per character)
         for (i, ch) in text.chars().enumerate() {
             if ch.is_ascii() {
@@ -1024,7 +1611,7 @@ impl Ppu {
         }
     }
 
-    fn render_small_char(&mut self, char_index: usize, x: usize, y: usize, color: (u8, u8, u8)) {
+    fn render_small_char(&mut self, char_index: usize, x: usize, y: usize, color: u8) {
         let font_data = FONT_8X8[char_index];
 
         // Render at 6 pixels tall by scaling 8x8 to 6x5
@@ -1040,14 +1627,8 @@ impl Ppu {
                     let pixel_x = x + col;
                     let pixel_y = y + row;
                     
-                    if pixel_x < SCREEN_WIDTH && pixel_y < SCREEN_HEIGHT {
-                        let pixel_index = (pixel_y * SCREEN_WIDTH + pixel_x) * 4;
-                        if pixel_index + 3 < self.screen_buffer.len() {
-                            self.screen_buffer[pixel_index] = color.0;
-                            self.screen_buffer[pixel_index + 1] = color.1;
-                            self.screen_buffer[pixel_index + 2] = color.2;
-                            self.screen_buffer[pixel_index + 3] = 255;
-                        }
+                    if pixel_x < self.width && pixel_y < self.height {
+                        self.blend_pixel(pixel_x, pixel_y, color, self.layer_blend[Layer::Hud as usize]);
                     }
                 }
             }
@@ -1059,13 +1640,7 @@ impl Ppu {
         const SQUARE_SIZE: usize = 20; // Bigger squares to fill screen better
         
         // Clear screen with dark background
-        let bg_color = MASTER_PALETTE[0]; // Black
-        for i in (0..self.screen_buffer.len()).step_by(4) {
-            self.screen_buffer[i] = bg_color.0;
-            self.screen_buffer[i + 1] = bg_color.1;
-            self.screen_buffer[i + 2] = bg_color.2;
-            self.screen_buffer[i + 3] = 255;
-        }
+        self.clear_screen(0); // Black
 
         // Render organized color families without labels
         let mut current_y = 5;
@@ -1111,20 +1686,13 @@ impl Ppu {
                 let square_y = y;
 
                 // Render color square
-                let color = MASTER_PALETTE[color_index];
                 for py in 0..square_size {
                     for px in 0..square_size {
                         let screen_x = square_x + px;
                         let screen_y = square_y + py;
                         
-                        if screen_x < SCREEN_WIDTH && screen_y < SCREEN_HEIGHT {
-                            let buffer_index = (screen_y * SCREEN_WIDTH + screen_x) * 4;
-                            if buffer_index + 3 < self.screen_buffer.len() {
-                                self.screen_buffer[buffer_index] = color.0;
-                                self.screen_buffer[buffer_index + 1] = color.1;
-                                self.screen_buffer[buffer_index + 2] = color.2;
-                                self.screen_buffer[buffer_index + 3] = 255;
-                            }
+                        if screen_x < self.width && screen_y < self.height {
+                            self.put_pixel(screen_y * self.width + screen_x, color_index as u8);
                         }
                     }
                 }
@@ -1133,9 +1701,9 @@ impl Ppu {
                 let bg_color = MASTER_PALETTE[color_index];
                 let brightness = (bg_color.0 as u32 + bg_color.1 as u32 + bg_color.2 as u32) / 3;
                 let text_color = if brightness > 128 { 
-                    MASTER_PALETTE[0] // Black for bright backgrounds
+                    0 // Black for bright backgrounds
                 } else { 
-                    MASTER_PALETTE[15] // White for dark backgrounds
+                    15 // White for dark backgrounds
                 };
                 
                 self.render_small_text(&color_index.to_string(), square_x + 2, square_y + 2, text_color);
@@ -1143,216 +1711,99 @@ impl Ppu {
         }
     }
 
-    fn render_intro_screen(&mut self) {
-        // Clear screen with dark blue background
-        let bg_color = MASTER_PALETTE[82]; // Dark blue from palette
-        for i in (0..self.screen_buffer.len()).step_by(4) {
-            self.screen_buffer[i] = bg_color.0;     // R
-            self.screen_buffer[i + 1] = bg_color.1; // G
-            self.screen_buffer[i + 2] = bg_color.2; // B
-            self.screen_buffer[i + 3] = 255;        // A
+    // Fill the whole screen with one master color
+    fn clear_screen(&mut self, index: u8) {
+        for pixel in 0..self.width * self.height {
+            self.put_pixel(pixel, index);
         }
+    }
 
-        // Render large Hambert sprite in center of screen
-        let sprite_scale = 3; // Make it 3x larger (96x84 pixels)
-        let sprite_x = (SCREEN_WIDTH as i32 - 32 * sprite_scale) / 2;
+    fn render_intro_screen(&mut self) {
+        // Clear screen with dark blue background
+        self.clear_screen(82); // Dark blue from palette
+
+        // Render the cartridge's title sprite large in the center of the
+        // screen - an ordinary hardware sprite scaled up by the affine unit
+        let (sprite_width, sprite_height) = self.intro_sprite
+            .and_then(|sprite_id| self.sprite_bank.get(sprite_id))
+            .map(|pattern| (pattern.width as i32, pattern.height as i32))
+            .unwrap_or((0, 0));
+        let sprite_scale = 3; // Make it 3x larger
+        let sprite_x = (self.width as i32 - sprite_width * sprite_scale) / 2;
         let sprite_y = 50; // Position it in upper portion of screen
 
-        self.render_large_hambert_sprite(sprite_x, sprite_y, sprite_scale);
+        if let Some(sprite_id) = self.intro_sprite {
+            // Scaling is about the pattern's center, so the unscaled sprite
+            // sits one sprite size in from the scaled top-left
+            let title_sprite = SpriteData {
+                x: (sprite_x + sprite_width) as f32,
+                y: (sprite_y + sprite_height) as f32,
+                sprite_id,
+                active: true,
+                flip_horizontal: false,
+                flip_vertical: false,
+                palette: self.intro_palette,
+                affine: Some(SpriteAffine { rotation: 0.0, scale_x: sprite_scale as f32, scale_y: sprite_scale as f32 }),
+                blend: BlendMode::Opaque,
+                tint: None,
+                clip: None,
+            };
+            let sprites = std::mem::replace(&mut self.sprites, vec![title_sprite]);
+            self.render_sprite_layer(0.0, 0.0);
+            self.sprites = sprites;
+        }
 
         // Render intro text below the sprite
-        let text_y = sprite_y + 32 * sprite_scale + 20; // Below the large sprite (32 is new height)
-        let text_color = MASTER_PALETTE[15]; // White
+        let text_y = sprite_y + sprite_height * sprite_scale + 20; // Below the large sprite
+        let text_color = 15; // White
         self.render_intro_text(text_y, text_color);
     }
 
-    fn get_small_hambert_head_pixel(&self, x: u32, y: u32) -> u8 {
-        // Small 12x12 version of Hambert's head for lives counter
-        if x >= 12 || y >= 12 {
-            return 0; // Transparent outside bounds
+    fn render_hud_layer(&mut self) {
+        if !self.hud.enabled {
+            return;
         }
 
-        // Extract just the head portion (rows 1-12) from the full sprite and scale down
-        // This is a simplified version of Hambert's head
-        let head_data = [
-            [0,0,0,1,1,1,1,1,1,0,0,0],  // Top of head outline
-            [0,0,1,9,8,8,1,1,9,1,0,0],  // Head with some facial features
-            [0,1,9,9,9,9,9,10,9,9,1,0], // More head detail
-            [0,1,9,8,8,9,10,8,8,9,1,0], // Eyes area
-            [1,9,9,10,9,7,9,10,9,9,8,1], // More facial features
-            [9,10,10,10,9,9,9,9,11,11,9,0], // Face shading
-            [9,9,10,9,9,7,8,7,7,9,8,0], // Nose/mouth area
-            [8,9,7,7,7,7,7,7,7,7,7,8], // Lower face
-            [7,10,11,10,10,10,10,10,10,8,8,0], // Chin area
-            [10,9,7,10,10,10,10,10,10,10,9,8], // Lower head
-            [10,9,7,10,8,8,8,10,10,8,9,0], // Jaw line
-            [11,7,7,10,8,10,8,8,10,7,9,11], // Bottom of head
-        ];
-
-        head_data[y as usize][x as usize]
-    }
-
-    fn render_lives_counter(&mut self) {
-        // Get lives count from the HUD register (set by cartridge)
-        let lives = self.hud_lives as u32;
-        let start_x = 10; // 10 pixels from left edge
-        let start_y = 10; // 10 pixels from top edge
-        let spacing = 20; // 20 pixels between each heart (bigger hearts need more space)
+        let slot = self.hud.palette.map(PaletteRam::bg_palette_slot);
+        let blend = self.layer_blend[Layer::Hud as usize];
+        let last_row = self.hud.window_bottom.min(self.hud.rows() - 1);
 
-        for i in 0..lives {
-            let x = start_x + (i * spacing);
-            self.render_pink_heart_text(x as f32, start_y as f32);
-        }
-    }
-
-    fn render_pink_heart_text(&mut self, x: f32, y: f32) {
-        // Render a big heart pattern in light red
-        let heart_color = MASTER_PALETTE[104]; // Color index 104 as requested
-        
-        // 16x16 heart pattern (double size)
-        let heart_pattern = [
-            0b0000000000000000, // ................
-            0b0000000000000000, // ................
-            0b0011110001111000, // ..####...####...
-            0b0111111011111100, // .######.######..
-            0b1111111111111110, // ##############..
-            0b1111111111111110, // ##############..
-            0b1111111111111110, // ##############..
-            0b1111111111111110, // ##############..
-            0b0111111111111100, // .############...
-            0b0011111111111000, // ..##########....
-            0b0001111111110000, // ...########.....
-            0b0000111111100000, // ....######......
-            0b0000011111000000, // .....####.......
-            0b0000001110000000, // ......##........
-            0b0000000100000000, // .......#........
-            0b0000000000000000, // ................
-        ];
-
-        for (row, &pattern) in heart_pattern.iter().enumerate() {
-            for col in 0..16 {
-                if (pattern & (1 << (15 - col))) != 0 {
-                    let screen_x = (x as usize + col).min(SCREEN_WIDTH - 1);
-                    let screen_y = (y as usize + row).min(SCREEN_HEIGHT - 1);
-                    let pixel_index = ((screen_y * SCREEN_WIDTH) + screen_x) * 4;
-                    
-                    if pixel_index + 3 < self.screen_buffer.len() {
-                        self.screen_buffer[pixel_index] = heart_color.0;
-                        self.screen_buffer[pixel_index + 1] = heart_color.1;
-                        self.screen_buffer[pixel_index + 2] = heart_color.2;
-                        self.screen_buffer[pixel_index + 3] = 255; // Alpha
-                    }
+        for row in self.hud.window_top..=last_row {
+            for column in 0..self.hud.columns() {
+                let cell = self.hud.cell(column, row);
+                let tile = self.hud.tile_data(cell.tile).copied();
+                if tile.is_none() && self.hud.fill.is_none() {
+                    continue;
                 }
-            }
-        }
-    }
 
-    fn get_new_hambert_pixel(&self, x: u32, y: u32) -> u8 {
-        // New improved Hambert idle sprite data (30x32) - version 2 with better outlines
-        if x >= 30 || y >= 32 {
-            return 0; // Transparent outside bounds
-        }
-
-        let pixel_data = [
-            [0,0,0,0,0,0,0,0,0,0,0,0,0,1,1,1,1,1,0,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,0,0,1,1,1,9,8,8,1,1,1,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,0,0,0,9,9,9,9,9,10,9,0,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,0,0,9,9,9,8,8,9,10,8,8,0,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,0,10,9,9,10,9,7,9,10,9,9,8,0,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,9,10,10,10,10,9,9,9,9,11,11,9,9,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,9,9,10,9,9,9,7,8,7,7,9,8,9,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,8,9,7,7,7,7,7,7,7,7,7,7,7,8,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,9,7,10,11,10,10,10,10,10,10,10,10,10,10,8,8,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,10,9,7,10,10,10,10,10,10,10,10,10,10,10,10,9,8,0,0,0,0,0,0],
-            [0,0,0,0,0,0,10,10,9,7,10,10,8,8,8,10,10,10,8,8,8,10,10,8,9,0,0,0,0,0],
-            [0,0,0,0,0,11,10,11,7,7,10,8,8,10,8,8,10,10,8,10,8,8,10,7,9,11,0,0,0,0],
-            [0,0,0,0,9,10,10,10,7,10,10,0,0,0,0,8,9,8,0,0,0,0,10,7,7,11,11,0,0,0],
-            [0,0,0,0,10,10,11,7,7,10,10,9,14,0,15,11,8,12,15,0,15,8,10,9,7,10,11,0,0,0],
-            [0,0,0,10,10,11,10,7,10,10,10,10,15,15,15,11,10,10,15,15,15,10,10,7,7,9,11,0,0,0],
-            [0,0,0,10,10,10,11,7,10,10,10,10,10,9,10,10,12,10,9,9,9,10,10,7,7,10,11,0,0,0],
-            [0,0,0,0,11,9,8,7,10,10,10,10,10,10,9,8,0,9,9,9,10,10,11,7,6,9,0,0,0,0],
-            [0,0,0,0,0,0,0,5,10,10,10,10,10,9,9,0,0,0,10,9,10,10,10,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,7,10,9,9,10,10,10,12,0,0,0,11,10,11,10,10,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,7,10,10,10,10,10,10,0,7,5,10,0,10,10,9,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,7,10,10,10,10,10,0,10,12,12,9,10,0,10,9,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,7,7,10,11,10,10,10,7,12,12,9,10,10,10,10,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,7,10,10,10,10,11,8,27,12,9,10,10,10,10,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,7,7,7,7,10,10,10,10,10,10,10,10,9,8,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,18,18,18,18,10,10,10,10,10,10,8,7,18,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,18,18,18,18,6,5,4,6,7,6,7,7,18,0,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,18,18,18,18,10,8,7,6,6,6,18,18,18,18,0,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,0,18,18,18,18,18,18,18,18,0,18,18,18,18,18,18,0,0,0,0,0,0],
-            [0,0,0,0,0,0,0,0,18,18,18,18,18,18,18,18,18,18,0,18,18,18,18,18,18,18,0,0,0,0],
-            [0,0,0,0,0,0,0,18,18,18,18,18,18,18,18,18,18,18,18,0,18,18,18,18,18,18,18,0,0,0],
-            [0,0,0,0,0,0,0,18,18,18,18,18,18,18,18,18,18,18,18,18,0,18,18,18,18,18,18,0,0,0],
-            [0,0,0,0,0,0,0,18,18,18,18,18,18,18,18,18,18,18,18,18,0,18,18,18,18,18,18,0,0,0],
-        ];
-
-        // Return the pixel value directly (already mapped to correct palette indices)
-        pixel_data[y as usize][x as usize]
-    }
-
-    fn get_hamberry_pixel(&self, x: u32, y: u32) -> u8 {
-        // 16x16 hamberry sprite data
-        if x >= 16 || y >= 16 {
-            return 0; // Transparent outside bounds
-        }
-
-        let pixel_data = [
-            [0,0,0,0,1,1,0,0,0,0,1,1,0,0,0,0],
-            [0,0,1,1,1,61,1,1,0,1,61,61,1,0,0,0],
-            [0,0,1,62,63,4,62,4,60,62,4,63,1,1,0,0],
-            [0,0,0,3,63,63,60,0,0,60,63,63,1,0,0,0],
-            [0,0,0,0,2,61,3,1,1,3,60,1,1,0,0,0],
-            [0,0,0,0,16,16,1,21,21,1,16,16,0,0,0,0],
-            [0,0,0,16,16,1,1,17,17,1,2,16,0,0,0,0],
-            [0,0,0,16,0,28,17,0,0,18,28,0,16,16,0,0],
-            [0,0,16,16,16,21,22,2,16,22,21,16,28,16,0,0],
-            [0,0,16,17,2,21,21,0,0,21,21,1,17,16,0,0],
-            [0,0,16,2,1,1,1,28,28,1,1,1,16,16,0,0],
-            [0,0,16,16,18,1,28,22,22,16,1,18,16,0,0,0],
-            [0,0,0,16,16,18,1,17,17,1,18,16,16,0,0,0],
-            [0,0,0,0,16,16,0,2,2,0,16,16,0,0,0,0],
-            [0,0,0,0,0,16,16,16,21,16,16,0,0,0,0,0],
-            [0,0,0,0,0,0,0,16,16,16,0,0,0,0,0,0],
-        ];
-
-        // Return the pixel value directly (already mapped to correct palette indices)
-        pixel_data[y as usize][x as usize]
-    }
-
-    fn render_large_hambert_sprite(&mut self, base_x: i32, base_y: i32, scale: i32) {
-        // Render scaled up Hambert sprite using the new improved sprite data
-        for py in 0..32 { // New sprite height (30x32)
-            for px in 0..30 { // New sprite width
-                let color_index = self.get_new_hambert_pixel(px, py);
-                if color_index > 0 { // Only render non-transparent pixels
-                    let color = MASTER_PALETTE[color_index as usize % MASTER_PALETTE.len()];
-
-                    // Scale up the pixel by drawing a scale x scale block
-                    for sy in 0..scale {
-                        for sx in 0..scale {
-                            let screen_x = base_x + (px as i32 * scale) + sx;
-                            let screen_y = base_y + (py as i32 * scale) + sy;
-
-                            if screen_x >= 0 && screen_x < SCREEN_WIDTH as i32 &&
-                               screen_y >= 0 && screen_y < SCREEN_HEIGHT as i32 {
-                                let buffer_index = (screen_y as usize * SCREEN_WIDTH + screen_x as usize) * 4;
-                                if buffer_index + 3 < self.screen_buffer.len() {
-                                    self.screen_buffer[buffer_index] = color.0;
-                                    self.screen_buffer[buffer_index + 1] = color.1;
-                                    self.screen_buffer[buffer_index + 2] = color.2;
-                                    self.screen_buffer[buffer_index + 3] = 255;
-                                }
+                for tile_y in 0..HUD_TILE_SIZE {
+                    let pixel_y = row * HUD_TILE_SIZE + tile_y;
+                    let bits = tile.map_or(0, |tile| tile[tile_y]);
+                    for tile_x in 0..HUD_TILE_SIZE {
+                        let color_index = if (bits >> tile_x) & 1 != 0 {
+                            cell.color
+                        } else {
+                            match self.hud.fill {
+                                Some(fill) => fill,
+                                None => continue,
                             }
-                        }
+                        };
+
+                        let color = match slot {
+                            Some(slot) => match self.palette_for_line(pixel_y).resolve_index(slot, color_index) {
+                                Some(color) => color,
+                                None => continue,
+                            },
+                            None => color_index,
+                        };
+                        self.blend_pixel(column * HUD_TILE_SIZE + tile_x, pixel_y, color, blend);
                     }
                 }
             }
         }
     }
 
-    fn render_intro_text(&mut self, y: i32, color: (u8, u8, u8)) {
+    fn render_intro_text(&mut self, y: i32, color: u8) {
         // Clone the text to avoid borrowing issues
         let text = self.intro_text.clone();
 
@@ -1363,7 +1814,7 @@ impl Ppu {
 
         // Center the text horizontally
         let text_width = text.len() * 8; // 8 pixels per character
-        let text_x = (SCREEN_WIDTH as i32 - text_width as i32) / 2;
+        let text_x = (self.width as i32 - text_width as i32) / 2;
 
         // Make sure the position is valid
         let safe_x = text_x.max(0) as usize;
@@ -1373,110 +1824,475 @@ impl Ppu {
         self.render_text(&text, 20, 200, color);
 
         // Also render at the calculated center position
-        if safe_y < SCREEN_HEIGHT && safe_x < SCREEN_WIDTH {
+        if safe_y < self.height && safe_x < self.width {
             self.render_text(&text, safe_x, safe_y, color);
         }
     }
 
     fn render_zsynth_screen(&mut self) {
         // Clear screen with dark purple background for Z-Synth
-        let bg_color = MASTER_PALETTE[95]; // Dark purple from palette
-        for i in (0..self.screen_buffer.len()).step_by(4) {
-            self.screen_buffer[i] = bg_color.0;     // R
-            self.screen_buffer[i + 1] = bg_color.1; // G
-            self.screen_buffer[i + 2] = bg_color.2; // B
-            self.screen_buffer[i + 3] = 255;        // A
-        }
+        self.clear_screen(95); // Dark purple from palette
 
         // Render test text to verify rendering pipeline
-        let title_color = MASTER_PALETTE[15]; // White
+        let title_color = 15; // White
         self.render_text("Z-SYNTH PIANO", 110, 20, title_color);
         
-        let info_color = MASTER_PALETTE[31]; // Light blue
+        let info_color = 31; // Light blue
         self.render_text("TEST RENDERING MODE", 90, 40, info_color);
         self.render_text("KEYS: Z S X D C V G B H N J M", 50, 60, info_color);
         self.render_text("NOTES: C2 through B2", 80, 80, info_color);
 
         // Render sprites provided by cartridge (piano keys)
-        let sprites = self.sprites.clone();
-        for sprite in &sprites {
-            if sprite.active {
-                self.render_sprite(sprite.x, sprite.y, sprite.sprite_id, sprite.flip_horizontal);
-            }
-        }
+        self.render_sprite_layer(0.0, 0.0);
 
         // Debug info
-        let debug_color = MASTER_PALETTE[47]; // Yellow
+        let debug_color = 47; // Yellow
         self.render_text(&format!("Sprites: {}", self.sprites.len()), 10, 200, debug_color);
         self.render_text(&format!("Frame: {}", self.frame_count), 10, 220, debug_color);
     }
 
-    pub fn get_screen_buffer(&self) -> Vec<u8> {
-        self.screen_buffer.clone()
+    // Borrowed view of the RGBA frame. Its address is stable from frame to
+    // frame, but set_video_mode reallocates it (and the indexed buffers), as
+    // does set_output_mode for the indexed buffers. Both bump the buffer
+    // generation, so zero-copy views must be rebuilt when it changes.
+    pub fn screen_buffer(&self) -> &[u8] {
+        &self.screen_buffer
+    }
+
+    pub fn buffer_generation(&self) -> u32 {
+        self.buffer_generation
+    }
+
+    // Indexed output - empty unless the output mode is Indexed
+    pub fn index_buffer(&self) -> &[u8] {
+        &self.index_buffer
+    }
+
+    pub fn active_palette(&self) -> &[u8] {
+        &self.active_palette
     }
 
     pub fn get_frame_count(&self) -> u64 {
         self.frame_count
     }
 
-    // Piano key rendering methods
-    fn get_white_piano_key_pixel(&self, x: u32, y: u32, is_pressed: bool) -> u8 {
-        if x >= 25 || y >= 80 {
-            return 0; // Transparent outside bounds
-        }
+    // Debug views - render one into the debug image. Leaves the main output,
+    // the status register and the layer toggles untouched.
+    pub fn render_debug_view(&mut self, view: DebugView) {
+        self.debug_image = match view {
+            DebugView::PatternSheet(palette) => debug_view::render_pattern_sheet(&self.sprite_bank, &self.palette_ram, palette),
+            DebugView::Palettes => debug_view::render_palettes(&self.palette_ram),
+            DebugView::Oam => debug_view::render_oam(&self.oam_entries(), MAX_HARDWARE_SPRITES),
+            DebugView::Layer(layer) => self.render_layer_image(layer),
+        };
+    }
 
-        // White piano key design with borders and shading
-        let border_thickness = 1;
-        let shadow_width = 2;
-        
-        // Define key regions
-        let is_border = x < border_thickness || x >= 25 - border_thickness || 
-                       y < border_thickness || y >= 80 - border_thickness;
-        let is_right_shadow = x >= 25 - shadow_width;
-        let is_bottom_shadow = y >= 80 - shadow_width;
-        
-        if is_pressed {
-            // Pressed key - use red to make it obvious
-            if is_border {
-                16 // Red
-            } else {
-                20 // Bright red
+    pub fn debug_image(&self) -> &DebugImage {
+        &self.debug_image
+    }
+
+    // Hardware sprites this frame after metasprite expansion, in priority order
+    fn oam_entries(&self) -> Vec<OamEntry> {
+        expand_sprites(&self.sprites, &self.sprite_bank, &self.pattern_animations, self.scroll_x, self.scroll_y)
+            .into_iter()
+            .map(|(sprite, x, y)| {
+                let (width, height) = self.sprite_bank.get(sprite.sprite_id).map_or((0, 0), |pattern| (pattern.width, pattern.height));
+                OamEntry {
+                    x: x.floor() as i32,
+                    y: y.floor() as i32,
+                    pattern_id: sprite.sprite_id,
+                    width,
+                    height,
+                    flip_horizontal: sprite.flip_horizontal,
+                    flip_vertical: sprite.flip_vertical,
+                    palette: sprite.palette,
+                    affine: sprite.affine.is_some(),
+                    blended: sprite.blend != BlendMode::Opaque || sprite.tint.is_some(),
+                }
+            })
+            .collect()
+    }
+
+    // One layer of the game screen drawn alone over the backdrop
+    fn render_layer_image(&mut self, layer: Layer) -> DebugImage {
+        let mut layers = [false; LAYER_COUNT];
+        layers[layer as usize] = true;
+
+        let (status, compose_from) = (self.status, self.compose_from);
+        let mut image = DebugImage::new(self.width, self.height);
+        std::mem::swap(&mut self.screen_buffer, &mut image.pixels);
+        let composed_indices = std::mem::take(&mut self.composed_indices); // Not drawn
+        self.compose_from = 0;
+        self.render_game(layers);
+        std::mem::swap(&mut self.screen_buffer, &mut image.pixels);
+        self.composed_indices = composed_indices;
+        self.status = status;
+        self.compose_from = compose_from;
+        image
+    }
+}
+
+// Hardware sprites for a frame, in priority order, with screen positions.
+// Metasprite pieces take the group's palette, blend, tint and affine; with
+// rotation or scaling each piece's center moves around the group's center.
+// A sprite on an animation clip draws the clip frame's pattern; animated
+// patterns are then resolved to this frame's pattern, for the group and for
+// each piece.
+fn expand_sprites(sprites: &[SpriteData], bank: &SpriteBank, animations: &TileAnimator, scroll_x: f32, scroll_y: f32) -> Vec<(SpriteData, f32, f32)> {
+    let mut hardware_sprites = Vec::new();
+    for sprite in sprites.iter().filter(|sprite| sprite.active) {
+        let x = sprite.x - scroll_x;
+        let y = sprite.y - scroll_y;
+        let clip_pattern = sprite.clip.as_ref().and_then(|position| bank.clip_pattern(position));
+        let sprite_id = animations.resolve(clip_pattern.unwrap_or(sprite.sprite_id));
+        let metasprite = match bank.metasprite(sprite_id) {
+            Some(metasprite) => metasprite,
+            None => {
+                let mut hardware_sprite = sprite.clone();
+                hardware_sprite.sprite_id = sprite_id;
+                hardware_sprites.push((hardware_sprite, x, y));
+                continue;
             }
-        } else {
-            // Unpressed key - use white/gray
-            if is_border {
-                1 // Black border
-            } else {
-                15 // White main area
+        };
+
+        let piece_size = |pattern_id| bank.get(pattern_id).map(|pattern| (pattern.width, pattern.height));
+        let group_center_x = x + metasprite.width as f32 / 2.0;
+        let group_center_y = y + metasprite.height as f32 / 2.0;
+        for piece in metasprite.placed_pieces(sprite.flip_horizontal, sprite.flip_vertical, piece_size) {
+            let (width, height) = piece_size(piece.pattern_id).unwrap_or((0, 0));
+            let mut piece_x = x + piece.offset_x as f32;
+            let mut piece_y = y + piece.offset_y as f32;
+            if let Some(affine) = sprite.affine {
+                let dx = (piece_x + width as f32 / 2.0 - group_center_x) * affine.scale_x;
+                let dy = (piece_y + height as f32 / 2.0 - group_center_y) * affine.scale_y;
+                let (sin, cos) = affine.rotation.sin_cos();
+                piece_x = group_center_x + dx * cos - dy * sin - width as f32 / 2.0;
+                piece_y = group_center_y + dx * sin + dy * cos - height as f32 / 2.0;
             }
+
+            let mut hardware_sprite = sprite.clone();
+            hardware_sprite.sprite_id = animations.resolve(piece.pattern_id);
+            hardware_sprite.flip_horizontal = piece.flip_horizontal;
+            hardware_sprite.flip_vertical = piece.flip_vertical;
+            hardware_sprites.push((hardware_sprite, piece_x, piece_y));
         }
     }
+    hardware_sprites
+}
 
-    fn get_black_piano_key_pixel(&self, x: u32, y: u32, is_pressed: bool) -> u8 {
-        if x >= 15 || y >= 50 {
-            return 0; // Transparent outside bounds
+// Screen lines a sprite covers (top inclusive, bottom exclusive)
+fn sprite_line_span(pattern: &SpritePattern, y: f32, affine: Option<SpriteAffine>) -> (i32, i32) {
+    match affine {
+        Some(affine) => {
+            // Same bounding circle the affine renderer scans
+            let half_width = pattern.width as f32 * affine.scale_x.abs() / 2.0;
+            let half_height = pattern.height as f32 * affine.scale_y.abs() / 2.0;
+            let radius = (half_width * half_width + half_height * half_height).sqrt();
+            let center_y = y + pattern.height as f32 / 2.0;
+            ((center_y - radius).floor() as i32, (center_y + radius).ceil() as i32)
         }
+        None => (y as i32, y as i32 + pattern.height as i32),
+    }
+}
 
-        // Black piano key design
-        let border_thickness = 1;
-        let highlight_width = 1;
-        
-        let is_border = x < border_thickness || x >= 15 - border_thickness || 
-                       y < border_thickness || y >= 50 - border_thickness;
-        let is_left_highlight = x < highlight_width;
-        let is_top_highlight = y < highlight_width;
-        
-        if is_pressed {
-            // Pressed black key - use bright color to make it obvious  
-            24 // Bright yellow when pressed
-        } else {
-            // Unpressed black key - black
-            if is_border {
-                3 // Dark gray border
-            } else {
-                1 // Black main area
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transition::TransitionKind;
+
+    // Run the beam to the start of the next VBlank
+    fn run_frame(ppu: &mut Ppu) {
+        let memory = Memory::new();
+        while !ppu.step(&memory) {}
+    }
+
+    fn pixel(ppu: &Ppu, x: usize, y: usize) -> (u8, u8, u8) {
+        let index = (y * ppu.get_screen_width() + x) * 4;
+        let buffer = ppu.screen_buffer();
+        (buffer[index], buffer[index + 1], buffer[index + 2])
+    }
+
+    #[test]
+    fn test_scenery_and_backdrop_use_palette_ram() {
+        let mut ppu = Ppu::new();
+        run_frame(&mut ppu);
+        let x = ppu.get_screen_width() / 2;
+        assert_eq!(pixel(&ppu, x, 0), MASTER_PALETTE[84]); // Top of the sky
+
+        // Recoloring the sky palette recolors the scenery
+        assert!(ppu.write_palette(SKY_PALETTE as usize, 1, 15));
+        ppu.set_layer_enabled(Layer::Background, false);
+        ppu.schedule_palette_write(100, 0, 0, 15);
+        run_frame(&mut ppu);
+        // A raster write to the backdrop only affects the lines below it
+        assert_eq!(pixel(&ppu, x, 99), MASTER_PALETTE[0]);
+        assert_eq!(pixel(&ppu, x, 100), MASTER_PALETTE[15]);
+
+        ppu.set_layer_enabled(Layer::Background, true);
+        run_frame(&mut ppu);
+        assert_eq!(pixel(&ppu, x, 0), MASTER_PALETTE[15]);
+    }
+
+    #[test]
+    fn test_background_blends_onto_backdrop() {
+        let mut ppu = Ppu::new();
+        assert!(ppu.write_palette(0, 0, 16));
+        ppu.set_layer_blend(Layer::Background, BlendMode::Add);
+        run_frame(&mut ppu);
+        let x = ppu.get_screen_width() / 2;
+        assert_eq!(pixel(&ppu, x, 0), BlendMode::Add.apply(MASTER_PALETTE[16], MASTER_PALETTE[84]));
+    }
+
+    #[test]
+    fn test_indexed_output() {
+        let mut ppu = ppu_with_sprite();
+        ppu.set_output_mode(OutputMode::Indexed);
+        ppu.set_layer_blend(Layer::Background, BlendMode::Half);
+        ppu.set_layer_blend(Layer::Sprites, BlendMode::Add);
+        ppu.set_master_brightness(8);
+        run_frame(&mut ppu);
+
+        let width = ppu.get_screen_width();
+        assert_eq!(ppu.index_buffer().len(), width * ppu.get_screen_height());
+        // Blended pixels keep the index they were drawn with: the sky's from
+        // palette RAM, the direct-color sprite's own
+        assert_eq!(ppu.index_buffer()[width / 2], 84);
+        assert_eq!(ppu.index_buffer()[108 * width + 108], 20);
+        assert_ne!(pixel(&ppu, 108, 108), MASTER_PALETTE[20]);
+
+        // Brightness is applied to the displayed palette, not the indices
+        let entry = &ppu.active_palette()[84 * 4..84 * 4 + 4];
+        let mut color_math = ColorMath::new();
+        color_math.set_brightness(8);
+        let expected = color_math.apply(MASTER_PALETTE[84]);
+        assert_eq!(entry, [expected.0, expected.1, expected.2, 255]);
+    }
+
+    #[test]
+    fn test_indexed_transitions() {
+        let mut ppu = Ppu::new();
+        ppu.set_output_mode(OutputMode::Indexed);
+        let width = ppu.get_screen_width();
+
+        // Wiped pixels are covered with black
+        ppu.start_transition(Transition::new(0, TransitionKind::WipeHorizontal, TransitionDirection::Out, 2));
+        run_frame(&mut ppu);
+        run_frame(&mut ppu);
+        assert_eq!(ppu.index_buffer()[0], 0);
+        assert_eq!(ppu.index_buffer()[width - 1], 84);
+        assert_eq!(ppu.active_palette()[84 * 4], MASTER_PALETTE[84].0);
+
+        // A fade is applied to the palette
+        ppu.start_transition(Transition::new(1, TransitionKind::Fade, TransitionDirection::Out, 2));
+        run_frame(&mut ppu);
+        run_frame(&mut ppu);
+        let (r, g, b) = MASTER_PALETTE[84];
+        assert_eq!(ppu.index_buffer()[0], 84);
+        assert_eq!(&ppu.active_palette()[84 * 4..84 * 4 + 4], [r / 2, g / 2, b / 2, 255]);
+    }
+
+    #[test]
+    fn test_video_mode_switch_reallocates_buffers() {
+        let mut ppu = Ppu::new();
+        ppu.set_output_mode(OutputMode::Indexed);
+        run_frame(&mut ppu);
+        let generation = ppu.buffer_generation();
+        let pointer = ppu.screen_buffer().as_ptr();
+
+        // Frames don't move the buffers
+        run_frame(&mut ppu);
+        assert_eq!(ppu.screen_buffer().as_ptr(), pointer);
+        assert_eq!(ppu.buffer_generation(), generation);
+
+        ppu.set_video_mode(VideoMode::HiResInterlaced);
+        assert_ne!(ppu.buffer_generation(), generation);
+        assert_eq!(ppu.screen_buffer().len(), 320 * 480 * 4);
+        assert_eq!(ppu.index_buffer().len(), 320 * 480);
+        assert_eq!(ppu.hud().rows(), 60);
+        run_frame(&mut ppu);
+
+        // Setting the current mode again keeps the buffers
+        let (generation, pointer) = (ppu.buffer_generation(), ppu.screen_buffer().as_ptr());
+        ppu.set_video_mode(VideoMode::HiResInterlaced);
+        assert_eq!((ppu.buffer_generation(), ppu.screen_buffer().as_ptr()), (generation, pointer));
+
+        ppu.set_video_mode(VideoMode::Low);
+        assert_eq!(ppu.screen_buffer().len(), 256 * 224 * 4);
+        assert_eq!(ppu.index_buffer().len(), 256 * 224);
+        run_frame(&mut ppu);
+    }
+
+    #[test]
+    fn test_raster_writes_land_on_the_first_frame_of_a_mode() {
+        // Scheduled before the very first frame
+        let mut ppu = Ppu::new();
+        ppu.set_layer_enabled(Layer::Background, false);
+        ppu.schedule_palette_write(100, 0, 0, 15);
+        let x = ppu.get_screen_width() / 2;
+        run_frame(&mut ppu);
+        assert_eq!(pixel(&ppu, x, 99), MASTER_PALETTE[0]);
+        assert_eq!(pixel(&ppu, x, 100), MASTER_PALETTE[15]);
+
+        // And across a mode switch
+        ppu.schedule_palette_write(50, 0, 0, 16);
+        ppu.set_video_mode(VideoMode::Low);
+        run_frame(&mut ppu);
+        assert_eq!(pixel(&ppu, x, 49), MASTER_PALETTE[15]);
+        assert_eq!(pixel(&ppu, x, 50), MASTER_PALETTE[16]);
+    }
+
+    #[test]
+    fn test_mid_frame_write_only_recomposes_lines_below_the_beam() {
+        let mut ppu = Ppu::new();
+        let memory = Memory::new();
+        run_frame(&mut ppu);
+        while ppu.get_scanline() != 0 {
+            ppu.step(&memory);
+        }
+        while ppu.get_scanline() < 100 {
+            ppu.step(&memory);
+        }
+
+        // Lines 0-99 are out; a write now shows from line 100 on
+        ppu.set_layer_enabled(Layer::Background, false);
+        ppu.invalidate_lines();
+        run_frame(&mut ppu);
+        let x = ppu.get_screen_width() / 2;
+        assert_eq!(pixel(&ppu, x, 0), MASTER_PALETTE[84]);
+        assert_eq!(pixel(&ppu, x, 99), MASTER_PALETTE[85]);
+        assert_eq!(pixel(&ppu, x, 100), MASTER_PALETTE[0]);
+
+        // Written during VBlank, the whole next frame changes
+        ppu.set_layer_enabled(Layer::Background, true);
+        ppu.invalidate_lines();
+        run_frame(&mut ppu);
+        assert_ne!(pixel(&ppu, x, 100), MASTER_PALETTE[0]);
+    }
+
+    fn ppu_with_sprite() -> Ppu {
+        let mut ppu = Ppu::new();
+        let mut bank = SpriteBank::new();
+        bank.insert(0, SpritePattern::from_fn(16, 16, |_, _| 20));
+        ppu.load_sprite_bank(bank);
+        ppu.add_sprite_with_palette(100.0, 100.0, 0, true, false, None);
+        ppu
+    }
+
+    #[test]
+    fn test_layer_debug_views_isolate_one_layer() {
+        let mut ppu = ppu_with_sprite();
+        run_frame(&mut ppu);
+        let status = ppu.read_status();
+        let image_pixel = |ppu: &Ppu, x: usize, y: usize| {
+            let image = ppu.debug_image();
+            let index = (y * image.width + x) * 4;
+            (image.pixels[index], image.pixels[index + 1], image.pixels[index + 2])
+        };
+
+        ppu.render_debug_view(DebugView::Layer(Layer::Sprites));
+        assert_eq!(image_pixel(&ppu, 104, 104), MASTER_PALETTE[20]);
+        assert_eq!(image_pixel(&ppu, 160, 0), MASTER_PALETTE[0]); // Backdrop only
+        assert_eq!(image_pixel(&ppu, 2, 2), MASTER_PALETTE[0]);   // No debug overlay
+
+        ppu.render_debug_view(DebugView::Layer(Layer::Background));
+        assert_eq!(image_pixel(&ppu, 160, 0), MASTER_PALETTE[84]);
+        assert_ne!(image_pixel(&ppu, 104, 104), MASTER_PALETTE[20]);
+
+        // The main output and status register are untouched
+        assert_eq!(ppu.read_status(), status);
+        assert_eq!(pixel(&ppu, 104, 104), MASTER_PALETTE[20]);
+    }
+
+    #[test]
+    fn test_layer_toggles() {
+        let mut ppu = ppu_with_sprite();
+        ppu.set_layer_enabled(Layer::Sprites, false);
+        run_frame(&mut ppu);
+        assert_ne!(pixel(&ppu, 104, 104), MASTER_PALETTE[20]);
+
+        // The coordinate overlay stays when the HUD is off
+        ppu.set_layer_enabled(Layer::Sprites, true);
+        ppu.set_layer_enabled(Layer::Hud, false);
+        ppu.invalidate_lines();
+        run_frame(&mut ppu);
+        assert_eq!(pixel(&ppu, 104, 104), MASTER_PALETTE[20]);
+        let overlay = (0..10).flat_map(|y| (0..60).map(move |x| (x, y)));
+        assert!(overlay.into_iter().any(|(x, y)| pixel(&ppu, x, y) == MASTER_PALETTE[15]));
+    }
+
+    #[test]
+    fn test_sprites_draw_their_clip_frame() {
+        let mut ppu = ppu_with_sprite();
+        ppu.sprite_bank.insert(1, SpritePattern::from_fn(16, 16, |_, _| 30));
+        let mut data = b"ZSA\x01\x01\x04walk\x00\x02".to_vec();
+        data.extend_from_slice(&[0, 0, 8, 0xFF, 1, 0, 8, 0xFF]);
+        assert!(ppu.load_animations(&data));
+        assert!(!ppu.load_animations(&data[..8]));
+
+        ppu.sprites[0].clip = Some(ClipFrame { clip: String::from("walk"), frame: 1 });
+        run_frame(&mut ppu);
+        assert_eq!(pixel(&ppu, 104, 104), MASTER_PALETTE[30]);
+
+        // An unknown clip falls back to the sprite's own pattern
+        ppu.sprites[0].clip = Some(ClipFrame { clip: String::from("run"), frame: 0 });
+        ppu.invalidate_lines();
+        run_frame(&mut ppu);
+        assert_eq!(pixel(&ppu, 104, 104), MASTER_PALETTE[20]);
+    }
+
+    #[test]
+    fn test_intro_sprite_is_a_scaled_hardware_sprite() {
+        let mut ppu = ppu_with_sprite();
+        ppu.set_intro_sprite(Some(0), None);
+        ppu.set_intro_mode(true);
+        run_frame(&mut ppu);
+
+        // 16x16 at 3x, centered horizontally from line 50
+        let left = (ppu.get_screen_width() - 48) / 2;
+        assert_eq!(pixel(&ppu, left, 50), MASTER_PALETTE[20]);
+        assert_eq!(pixel(&ppu, left + 47, 97), MASTER_PALETTE[20]);
+        assert_ne!(pixel(&ppu, left + 48, 97), MASTER_PALETTE[20]);
+        assert_eq!(ppu.sprites.len(), 1); // The game's sprites are left alone
+    }
+
+    #[test]
+    fn test_palette_cycle_waits_a_full_period() {
+        let mut ppu = Ppu::new();
+        ppu.load_palette(3, &[0, 10, 11, 12]);
+        let cycle = PaletteCycle { palette: 3, start: 1, length: 3, frames_per_step: 2 };
+        assert!(!ppu.add_palette_cycle(PaletteCycle { start: 15, ..cycle }));
+        assert!(!ppu.add_palette_cycle(PaletteCycle { start: 0, ..cycle }));
+        ppu.set_palette_size(3, 4);
+        assert!(!ppu.add_palette_cycle(PaletteCycle { start: 2, ..cycle }));
+        assert!(ppu.add_palette_cycle(cycle));
+
+        // Frame 0 and 1 show the palette as loaded, frame 2 the first step
+        let mut firsts = Vec::new();
+        for _ in 0..5 {
+            run_frame(&mut ppu);
+            firsts.push(ppu.read_palette(3, 1));
         }
+        assert_eq!(firsts, [10, 10, 12, 12, 11]);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_replaced_transition_is_reported_completed() {
+        let mut ppu = Ppu::new();
+        let out = Transition::new(0, TransitionKind::Fade, TransitionDirection::Out, 30).owned_by(TransitionOwner::Cartridge);
+        ppu.start_transition(out);
+        run_frame(&mut ppu);
+        assert!(ppu.take_completed_transitions().is_empty());
+
+        // The host's transition takes over; the cartridge's still finishes
+        ppu.start_transition(Transition::new(0, TransitionKind::Iris, TransitionDirection::Out, 2));
+        assert_eq!(ppu.take_completed_transitions(), [(TransitionOwner::Cartridge, 0)]);
+        run_frame(&mut ppu);
+        run_frame(&mut ppu);
+        assert_eq!(ppu.take_completed_transitions(), [(TransitionOwner::Host, 0)]);
+
+        // A finished Out transition holds the screen without being reported again
+        assert!(!ppu.is_transition_running());
+        ppu.start_transition(Transition::new(1, TransitionKind::Iris, TransitionDirection::In, 2));
+        assert!(ppu.take_completed_transitions().is_empty());
+    }
+}
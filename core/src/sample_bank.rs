@@ -0,0 +1,205 @@
+// PCM sample playback - a bank of 8-bit unsigned samples loaded at runtime
+// (usually from cartridge data) and a few sample channels that play them at
+// once. Each channel has its own playback rate, volume and pan, and plays a
+// sample either once or looping between the sample's loop points, so drum
+// kits, voice clips and sustained instrument samples can overlap.
+
+use crate::mixer;
+
+pub const SAMPLE_CHANNELS: usize = 4;
+pub const MAX_SAMPLES: usize = 64;
+
+pub struct Sample {
+    data: Vec<u8>,     // 8-bit unsigned, 128 = silence
+    sample_rate: u32,  // Rate the data was recorded at
+    loop_start: usize, // Looping channels repeat data[loop_start..loop_end]
+    loop_end: usize,
+}
+
+#[derive(Clone, Copy, Default)]
+struct SampleChannel {
+    active: bool,
+    sample: usize,
+    position: f32, // Fractional index into the data
+    rate: f32,     // Playback rate, 1.0 = original pitch
+    volume: f32,
+    pan: f32, // -1.0 (left) to 1.0 (right)
+    looping: bool,
+    started: u64, // Start order, so the oldest channel is reused first
+}
+
+#[derive(Default)]
+pub struct SamplePlayer {
+    samples: Vec<Sample>,
+    channels: [SampleChannel; SAMPLE_CHANNELS],
+    starts: u64,
+}
+
+impl SamplePlayer {
+    pub fn new() -> SamplePlayer {
+        SamplePlayer::default()
+    }
+
+    // Returns the new sample's ID, or None for empty data or a full bank.
+    // Loop points start out covering the whole sample.
+    pub fn load(&mut self, data: &[u8], sample_rate: u32) -> Option<u32> {
+        if data.is_empty() || sample_rate == 0 || self.samples.len() >= MAX_SAMPLES {
+            return None;
+        }
+        self.samples.push(Sample {
+            data: data.to_vec(),
+            sample_rate,
+            loop_start: 0,
+            loop_end: data.len(),
+        });
+        Some((self.samples.len() - 1) as u32)
+    }
+
+    pub fn set_loop(&mut self, sample: u32, start: u32, end: u32) -> bool {
+        match self.samples.get_mut(sample as usize) {
+            Some(sample) if start < end && end as usize <= sample.data.len() => {
+                sample.loop_start = start as usize;
+                sample.loop_end = end as usize;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Forget every sample (and stop the channels playing them)
+    pub fn clear(&mut self) {
+        self.stop_all();
+        self.samples.clear();
+    }
+
+    // Start a sample on a free channel, taking over the oldest one if they
+    // are all busy. Returns the channel.
+    pub fn play(&mut self, sample: u32, rate: f32, volume: f32, pan: f32, looping: bool) -> Option<u32> {
+        if sample as usize >= self.samples.len() {
+            return None;
+        }
+        let channel = match self.channels.iter().position(|channel| !channel.active) {
+            Some(free) => free,
+            None => (0..SAMPLE_CHANNELS).min_by_key(|&index| self.channels[index].started)?,
+        };
+
+        self.starts += 1;
+        self.channels[channel] = SampleChannel {
+            active: true,
+            sample: sample as usize,
+            position: 0.0,
+            rate: rate.max(0.0),
+            volume: volume.clamp(0.0, 1.0),
+            pan: pan.clamp(-1.0, 1.0),
+            looping,
+            started: self.starts,
+        };
+        Some(channel as u32)
+    }
+
+    pub fn stop(&mut self, channel: u32) {
+        if let Some(channel) = self.channels.get_mut(channel as usize) {
+            channel.active = false;
+        }
+    }
+
+    pub fn stop_all(&mut self) {
+        self.channels.iter_mut().for_each(|channel| channel.active = false);
+    }
+
+    pub fn set_rate(&mut self, channel: u32, rate: f32) {
+        if let Some(channel) = self.channels.get_mut(channel as usize) {
+            channel.rate = rate.max(0.0);
+        }
+    }
+
+    pub fn set_volume(&mut self, channel: u32, volume: f32) {
+        if let Some(channel) = self.channels.get_mut(channel as usize) {
+            channel.volume = volume.clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn set_pan(&mut self, channel: u32, pan: f32) {
+        if let Some(channel) = self.channels.get_mut(channel as usize) {
+            channel.pan = pan.clamp(-1.0, 1.0);
+        }
+    }
+
+    // Next (left, right) sample of all channels
+    pub fn sample(&mut self, output_rate: f32) -> (f32, f32) {
+        let (mut left, mut right) = (0.0, 0.0);
+        for channel in self.channels.iter_mut().filter(|channel| channel.active) {
+            let sample = &self.samples[channel.sample];
+            let mut index = channel.position as usize;
+            if channel.looping && index >= sample.loop_end {
+                // Fast playback of a short loop can overshoot it several times
+                let (loop_start, loop_length) = (sample.loop_start as f32, (sample.loop_end - sample.loop_start) as f32);
+                channel.position = loop_start + (channel.position - loop_start) % loop_length;
+                index = channel.position as usize;
+            }
+            if index >= sample.data.len() {
+                channel.active = false; // One-shot finished
+                continue;
+            }
+
+            let level = (sample.data[index] as f32 - 128.0) / 128.0 * channel.volume;
+            channel.position += sample.sample_rate as f32 * channel.rate / output_rate;
+
+            let (level_left, level_right) = mixer::pan(level, channel.pan);
+            left += level_left;
+            right += level_right;
+        }
+        (left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_shot_and_loop_channels() {
+        let mut player = SamplePlayer::new();
+        let ramp = player.load(&[128, 192, 255, 64], 1000).unwrap();
+        assert!(player.set_loop(ramp, 1, 3));
+        assert!(!player.set_loop(ramp, 3, 9));
+
+        // Centered, so both sides match; output rate equals the sample rate
+        assert_eq!(player.play(ramp, 1.0, 1.0, 0.0, false), Some(0));
+        assert_eq!(player.play(ramp, 1.0, 1.0, 0.0, true), Some(1));
+        let mut levels = Vec::new();
+        for _ in 0..6 {
+            let (left, right) = player.sample(1000.0);
+            assert_eq!(left, right);
+            levels.push(left);
+        }
+        // The looping channel wraps back to index 1 after index 2
+        assert_eq!(levels, [0.0, 1.0, 2.0 * 127.0 / 128.0, 0.0, 127.0 / 128.0, 0.5]);
+        assert!(!player.channels[0].active);
+
+        // All channels busy: the oldest is reused
+        for _ in 0..3 {
+            player.play(ramp, 1.0, 1.0, 0.0, false);
+        }
+        assert_eq!(player.play(ramp, 1.0, 1.0, 0.0, false), Some(1));
+    }
+
+    #[test]
+    fn test_fast_playback_of_short_loops() {
+        let mut player = SamplePlayer::new();
+        let ramp = player.load(&[128, 192, 255, 64], 1000).unwrap();
+
+        // One-sample loop at triple speed stays on it
+        player.set_loop(ramp, 2, 3);
+        player.play(ramp, 3.0, 1.0, 0.0, true);
+        let levels: Vec<f32> = (0..4).map(|_| player.sample(1000.0).0).collect();
+        assert_eq!(levels, [0.0, 127.0 / 128.0, 127.0 / 128.0, 127.0 / 128.0]);
+        player.stop_all();
+
+        // Two-sample loop at 2.5x: positions 0, 2.5, 5 -> 1, 7.5 -> 1.5, 10 -> 2
+        player.set_loop(ramp, 1, 3);
+        player.play(ramp, 2.5, 1.0, 0.0, true);
+        let levels: Vec<f32> = (0..5).map(|_| player.sample(1000.0).0).collect();
+        assert_eq!(levels, [0.0, 127.0 / 128.0, 0.5, 0.5, 127.0 / 128.0]);
+    }
+}
@@ -0,0 +1,290 @@
+// Sound effects - the .zsx effect table format, an sfxr-style synthesizer
+// and the manager that plays effects on a small pool of voices. Effects are
+// data, so a cartridge ships a table of them and sound designers can tune one
+// by editing bytes.
+//
+// Each effect is a pure function of its parameters and the frame number: the
+// manager runs once per video frame, like the music driver, and the APU
+// applies each voice's frequency, pulse width and volume. When every voice is
+// busy a new effect takes over the lowest-priority one (the oldest if several
+// tie), but never one of higher priority than itself - then the new effect is
+// dropped. Effects can ask for the music to be ducked while they play.
+//
+// .zsx layout (all values are bytes):
+//
+//   Header, 5 bytes
+//     0-3   "ZSX" and format version 1
+//     4     effect count
+//   Effects, 16 bytes each
+//     0     waveform (0-4 as for the SID voices - 4 is noise - or SID
+//           waveform bits $10-$F0)
+//     1     start note (MIDI)
+//     2     slide, signed, 1/16 semitone per frame
+//     3     delta slide, signed, added to the slide each frame in 1/64
+//           semitone steps
+//     4, 5  vibrato depth (1/16 semitones) and speed (1/64 cycle per frame)
+//     6, 7  arpeggio: signed semitone jump, and the frame it happens on
+//           (0 = no jump)
+//     8, 9  pulse width (0-255 of the period) and signed duty sweep per frame
+//     10-12 volume envelope: attack, sustain and decay in frames. The effect
+//           lasts as long as the three together.
+//     13    punch - extra volume (0-255 = up to double) at the start of the
+//           sustain, fading over it
+//     14    volume, 0-255
+//     15    priority in bits 0-6; bit 7 ducks the music while it plays
+
+pub const SFX_VOICES: usize = 4;
+
+const HEADER_SIZE: usize = 5;
+const EFFECT_SIZE: usize = 16;
+const DUCK_MUSIC: u8 = 0x80;
+
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct SoundEffect {
+    pub waveform: u8,
+    pub start_note: u8,
+    pub slide: i8,
+    pub delta_slide: i8,
+    pub vibrato_depth: u8,
+    pub vibrato_speed: u8,
+    pub arpeggio_note: i8,
+    pub arpeggio_frame: u8,
+    pub duty: u8,
+    pub duty_sweep: i8,
+    pub attack: u8,
+    pub sustain: u8,
+    pub decay: u8,
+    pub punch: u8,
+    pub volume: u8,
+    pub priority: u8, // Higher wins voice stealing
+    pub duck_music: bool,
+}
+
+// A voice's settings for one frame of an effect
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SfxFrame {
+    pub frequency: f32,
+    pub pulse_width: f32,
+    pub volume: f32,
+}
+
+impl SoundEffect {
+    pub fn from_bytes(bytes: &[u8]) -> Result<SoundEffect, String> {
+        if bytes.len() != EFFECT_SIZE {
+            return Err(format!("Sound effects are {} bytes, got {}", EFFECT_SIZE, bytes.len()));
+        }
+        Ok(SoundEffect {
+            waveform: bytes[0],
+            start_note: bytes[1].min(127),
+            slide: bytes[2] as i8,
+            delta_slide: bytes[3] as i8,
+            vibrato_depth: bytes[4],
+            vibrato_speed: bytes[5],
+            arpeggio_note: bytes[6] as i8,
+            arpeggio_frame: bytes[7],
+            duty: bytes[8],
+            duty_sweep: bytes[9] as i8,
+            attack: bytes[10],
+            sustain: bytes[11],
+            decay: bytes[12],
+            punch: bytes[13],
+            volume: bytes[14],
+            priority: bytes[15] & !DUCK_MUSIC,
+            duck_music: bytes[15] & DUCK_MUSIC != 0,
+        })
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.attack as u32 + self.sustain as u32 + self.decay as u32
+    }
+
+    // None once the effect has finished
+    pub fn frame(&self, frame: u32) -> Option<SfxFrame> {
+        if frame >= self.frames() {
+            return None;
+        }
+        let t = frame as f32;
+
+        let mut note = self.start_note as f32
+            + self.slide as f32 * t / 16.0
+            + self.delta_slide as f32 * t * t / 128.0;
+        if self.arpeggio_frame > 0 && frame >= self.arpeggio_frame as u32 {
+            note += self.arpeggio_note as f32;
+        }
+        if self.vibrato_depth > 0 {
+            let phase = t * self.vibrato_speed as f32 / 64.0;
+            note += self.vibrato_depth as f32 / 16.0 * (phase * std::f32::consts::TAU).sin();
+        }
+        let note = note.clamp(0.0, 127.0);
+
+        let duty = self.duty as f32 + self.duty_sweep as f32 * t;
+        let (attack, sustain) = (self.attack as u32, self.sustain as u32);
+        let envelope = if frame < attack {
+            t / attack as f32
+        } else if frame < attack + sustain {
+            let punch = self.punch as f32 / 255.0;
+            1.0 + punch * (1.0 - (frame - attack) as f32 / sustain as f32)
+        } else {
+            1.0 - (frame - attack - sustain) as f32 / self.decay as f32
+        };
+
+        Some(SfxFrame {
+            frequency: 440.0 * 2.0_f32.powf((note - 69.0) / 12.0),
+            pulse_width: (duty / 256.0).clamp(0.05, 0.95),
+            volume: envelope * self.volume as f32 / 255.0,
+        })
+    }
+}
+
+// An effect table: the sound IDs a cartridge plays by are indices into it
+pub fn table_from_bytes(data: &[u8]) -> Result<Vec<SoundEffect>, String> {
+    if data.len() < HEADER_SIZE || &data[0..3] != b"ZSX" {
+        return Err(String::from("Not a .zsx file"));
+    }
+    if data[3] != 1 {
+        return Err(format!("Unsupported .zsx version {}", data[3]));
+    }
+    let expected = HEADER_SIZE + data[4] as usize * EFFECT_SIZE;
+    if data.len() < expected {
+        return Err(format!(".zsx data is {} bytes, expected {}", data.len(), expected));
+    }
+    data[HEADER_SIZE..expected].chunks_exact(EFFECT_SIZE).map(SoundEffect::from_bytes).collect()
+}
+
+#[derive(Clone, Copy, Default)]
+struct SfxSlot {
+    active: bool,
+    effect: SoundEffect,
+    frame: u32,
+    started: u64, // Start order, so the oldest voice is stolen first
+}
+
+#[derive(Default)]
+pub struct SfxManager {
+    effects: Vec<SoundEffect>,
+    slots: [SfxSlot; SFX_VOICES],
+    starts: u64,
+}
+
+impl SfxManager {
+    pub fn new() -> SfxManager {
+        SfxManager::default()
+    }
+
+    pub fn load(&mut self, effects: Vec<SoundEffect>) {
+        self.stop_all();
+        self.effects = effects;
+    }
+
+    pub fn effect(&self, sound_id: u32) -> Option<SoundEffect> {
+        self.effects.get(sound_id as usize).copied()
+    }
+
+    // Returns the voice the effect starts on, or None if it is empty or
+    // every voice is playing something more important
+    pub fn play(&mut self, effect: SoundEffect) -> Option<usize> {
+        if effect.frames() == 0 {
+            return None;
+        }
+        let voice = match self.slots.iter().position(|slot| !slot.active) {
+            Some(free) => free,
+            None => {
+                let (voice, slot) = self.slots.iter().enumerate()
+                    .min_by_key(|(_, slot)| (slot.effect.priority, slot.started))?;
+                if slot.effect.priority > effect.priority {
+                    return None;
+                }
+                voice
+            }
+        };
+
+        self.starts += 1;
+        self.slots[voice] = SfxSlot {
+            active: true,
+            effect,
+            frame: 0,
+            started: self.starts,
+        };
+        Some(voice)
+    }
+
+    pub fn stop_all(&mut self) {
+        self.slots.iter_mut().for_each(|slot| slot.active = false);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.slots.iter().any(|slot| slot.active)
+    }
+
+    pub fn is_ducking(&self) -> bool {
+        self.slots.iter().any(|slot| slot.active && slot.effect.duck_music)
+    }
+
+    // The current frame of a voice's effect, None if it is silent
+    pub fn output(&self, voice: usize) -> Option<SfxFrame> {
+        let slot = &self.slots[voice];
+        if slot.active { slot.effect.frame(slot.frame) } else { None }
+    }
+
+    // Advance every effect by one video frame
+    pub fn step_frame(&mut self) {
+        for slot in self.slots.iter_mut().filter(|slot| slot.active) {
+            slot.frame += 1;
+            slot.active = slot.frame < slot.effect.frames();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(priority: u8) -> SoundEffect {
+        // Rises an octave over 16 frames at full volume
+        SoundEffect::from_bytes(&[0, 60, 12, 0, 0, 0, 0, 0, 128, 0, 0, 16, 0, 0, 255, priority]).unwrap()
+    }
+
+    #[test]
+    fn test_effect_frames() {
+        let effect = effect(0);
+        let start = effect.frame(0).unwrap();
+        assert!((start.frequency - 261.63).abs() < 0.01);
+        assert_eq!((start.pulse_width, start.volume), (0.5, 1.0));
+        // Half an octave up halfway through
+        assert!((effect.frame(8).unwrap().frequency - 261.63 * 2.0_f32.sqrt()).abs() < 0.02);
+        assert_eq!(effect.frame(16), None);
+
+        // Attack ramps up, punch peaks at the start of the sustain, decay
+        // fades out
+        let shaped = SoundEffect { attack: 2, sustain: 2, decay: 2, punch: 255, ..effect };
+        let volumes: Vec<f32> = (0..6).map(|frame| shaped.frame(frame).unwrap().volume).collect();
+        assert_eq!(volumes, [0.0, 0.5, 2.0, 1.5, 1.0, 0.5]);
+
+        let mut table = b"ZSX\x01\x01".to_vec();
+        table.extend_from_slice(&[0, 60, 12, 0, 0, 0, 0, 0, 128, 0, 0, 16, 0, 0, 255, 0x81]);
+        let loaded = table_from_bytes(&table).unwrap();
+        assert_eq!((loaded[0].priority, loaded[0].duck_music), (1, true));
+        assert!(table_from_bytes(&table[..10]).is_err());
+    }
+
+    #[test]
+    fn test_voice_allocation_and_stealing() {
+        let mut sfx = SfxManager::new();
+        assert_eq!(sfx.play(effect(2 | DUCK_MUSIC)), Some(0));
+        assert!(sfx.is_ducking());
+        for voice in 1..SFX_VOICES {
+            assert_eq!(sfx.play(effect(1)), Some(voice));
+        }
+
+        // Full: a low-priority effect is dropped, an equal one steals the
+        // oldest of the lowest priority
+        assert_eq!(sfx.play(effect(0)), None);
+        assert_eq!(sfx.play(effect(1)), Some(1));
+
+        for _ in 0..16 {
+            sfx.step_frame();
+        }
+        assert!(!sfx.is_playing());
+        assert_eq!(sfx.output(0), None);
+    }
+}
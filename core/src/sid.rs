@@ -0,0 +1,106 @@
+// SID voice features - the parts of the MOS 6581/8580 that make the SID voices
+// more than three independent oscillators. Each voice has a control register
+// like the real chip's:
+//
+//   bit 0     gate (rising edge starts the envelope, falling edge releases)
+//   bit 1     hard sync to the previous voice
+//   bit 2     ring modulation by the previous voice (triangle only)
+//   bit 3     test (holds the oscillator and noise generator at zero)
+//   bits 4-7  waveforms: $10 triangle, $20 sawtooth, $40 pulse, $80 noise
+//
+// "Previous" wraps around: voice 1 is modulated by voice 3. Selecting several
+// waveforms ANDs their 12-bit outputs together, giving the chip's combined
+// waveforms (e.g. $30 triangle+saw).
+
+pub const SID_GATE: u8 = 0x01;
+pub const SID_SYNC: u8 = 0x02;
+pub const SID_RING_MOD: u8 = 0x04;
+pub const SID_TEST: u8 = 0x08;
+pub const SID_TRIANGLE: u8 = 0x10;
+pub const SID_SAWTOOTH: u8 = 0x20;
+pub const SID_PULSE: u8 = 0x40;
+pub const SID_NOISE: u8 = 0x80;
+pub const SID_WAVEFORMS: u8 = 0xF0;
+
+// Filter type bit selecting the 6581's cutoff curve (otherwise 8580)
+pub const SID_FILTER_6581: u8 = 0x04;
+
+#[derive(Clone, Copy, Default)]
+pub struct SidControl {
+    pub gate: bool,
+    pub sync: bool,
+    pub ring_mod: bool,
+    pub test: bool,
+    pub waveform_mask: u8, // SID waveform bits; 0 = use the oscillator's waveform index
+    pub wrapped: bool,     // Oscillator wrapped on its last sample
+
+    // Modulating voice's state from the previous sample
+    pub modulator_phase: f32,
+    pub modulator_wrapped: bool,
+}
+
+impl SidControl {
+    pub fn ring_inverts(&self) -> bool {
+        self.ring_mod && self.modulator_phase >= 0.5
+    }
+}
+
+// One sample of a combined SID waveform, -1.0 to 1.0. `noise` is the noise
+// generator's current register, already clocked by the caller.
+pub fn combined_waveform(mask: u8, phase: f32, pulse_width: f32, ring_inverts: bool, noise: u16) -> f32 {
+    let accumulator = (phase * 4096.0) as u32 & 0xFFF;
+    let mut output = 0xFFF;
+
+    if mask & SID_TRIANGLE != 0 {
+        // The top accumulator bit (XORed with the modulator's for ring
+        // modulation) folds the ramp back down
+        let folded = if (accumulator & 0x800 != 0) ^ ring_inverts { !accumulator } else { accumulator };
+        output &= (folded & 0x7FF) << 1;
+    }
+    if mask & SID_SAWTOOTH != 0 {
+        output &= accumulator;
+    }
+    if mask & SID_PULSE != 0 && phase >= pulse_width {
+        output = 0;
+    }
+    if mask & SID_NOISE != 0 {
+        output &= noise as u32 & 0xFFF;
+    }
+
+    output as f32 / 2047.5 - 1.0
+}
+
+// The 6581's cutoff control is strongly non-linear: the bottom of the range
+// barely moves from ~200 Hz, then the cutoff climbs steeply through the middle
+// and flattens out near the top. `cutoff` is what the same setting gives on
+// the 8580, which is close to linear over 0-12 kHz.
+pub fn cutoff_6581(cutoff: f32) -> f32 {
+    let position = (cutoff / 12_000.0).clamp(0.0, 1.0);
+    let curve = ((position - 0.55) * 8.0).tanh() * 0.5 + 0.5;
+    200.0 + curve * 17_800.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_combined_waveforms_and_ring() {
+        // Triangle is symmetric about its peak, ring modulation flips it
+        let triangle = combined_waveform(SID_TRIANGLE, 0.45, 0.5, false, 0);
+        assert_eq!(triangle, combined_waveform(SID_TRIANGLE, 0.55, 0.5, false, 0));
+        assert!(triangle > 0.7);
+        assert!(combined_waveform(SID_TRIANGLE, 0.45, 0.5, true, 0) < -0.7);
+
+        // Triangle+saw is never louder than either on its own
+        for step in 0..64 {
+            let phase = step as f32 / 64.0;
+            let combined = combined_waveform(SID_TRIANGLE | SID_SAWTOOTH, phase, 0.5, false, 0);
+            assert!(combined <= combined_waveform(SID_SAWTOOTH, phase, 0.5, false, 0));
+            assert!(combined <= combined_waveform(SID_TRIANGLE, phase, 0.5, false, 0));
+        }
+        assert_eq!(combined_waveform(SID_SAWTOOTH | SID_PULSE, 0.75, 0.5, false, 0), -1.0);
+
+        assert!(cutoff_6581(0.0) < 250.0 && cutoff_6581(12_000.0) > 17_000.0);
+    }
+}
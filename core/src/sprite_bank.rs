@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use crate::metasprite::{MetaSprite, MAX_SPRITE_SIZE};
+use crate::animation::{AnimationLibrary, ClipFrame};
+
+// Sprite/pattern bank - indexed pixel data uploaded by the cartridge at load
+// time. The PPU renders sprites purely from this bank, so adding a character
+// is an asset change rather than a PPU change. Characters larger than one
+// hardware sprite are registered as metasprites built from bank patterns, and
+// animation clips (.zsa data) live here too so the renderer can resolve a
+// sprite's clip frame to its pattern.
+
+#[derive(Clone)]
+pub struct SpritePattern {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>, // Row-major palette indices, 0 = transparent
+}
+
+impl SpritePattern {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Option<SpritePattern> {
+        if width == 0 || height == 0 || pixels.len() != (width * height) as usize {
+            return None;
+        }
+        Some(SpritePattern { width, height, pixels })
+    }
+
+    // Build a pattern from a pixel function (procedural art, resampled art)
+    pub fn from_fn<F: Fn(u32, u32) -> u8>(width: u32, height: u32, pixel: F) -> SpritePattern {
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(pixel(x, y));
+            }
+        }
+        SpritePattern { width, height, pixels }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> u8 {
+        if x >= self.width || y >= self.height {
+            return 0; // Transparent outside bounds
+        }
+        self.pixels[(y * self.width + x) as usize]
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct SpriteBank {
+    patterns: HashMap<u32, SpritePattern>,
+    metasprites: HashMap<u32, MetaSprite>,
+    animations: AnimationLibrary,
+}
+
+impl SpriteBank {
+    pub fn new() -> SpriteBank {
+        SpriteBank {
+            patterns: HashMap::new(),
+            metasprites: HashMap::new(),
+            animations: AnimationLibrary::new(),
+        }
+    }
+
+    // Patterns larger than a hardware sprite (32x32) are refused - bigger
+    // characters are metasprites
+    pub fn insert(&mut self, sprite_id: u32, pattern: SpritePattern) -> bool {
+        if pattern.width > MAX_SPRITE_SIZE || pattern.height > MAX_SPRITE_SIZE {
+            return false;
+        }
+        self.patterns.insert(sprite_id, pattern);
+        true
+    }
+
+    // Upload raw pixel data - returns false if the data doesn't match the size
+    // or the pattern is larger than a hardware sprite
+    pub fn upload(&mut self, sprite_id: u32, width: u32, height: u32, pixels: &[u8]) -> bool {
+        match SpritePattern::new(width, height, pixels.to_vec()) {
+            Some(pattern) => self.insert(sprite_id, pattern),
+            None => false,
+        }
+    }
+
+    pub fn get(&self, sprite_id: u32) -> Option<&SpritePattern> {
+        self.patterns.get(&sprite_id)
+    }
+
+    // Pattern IDs in ascending order
+    pub fn pattern_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.patterns.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    // Metasprite IDs share the sprite ID space and take precedence over patterns
+    pub fn insert_metasprite(&mut self, sprite_id: u32, metasprite: MetaSprite) {
+        self.metasprites.insert(sprite_id, metasprite);
+    }
+
+    pub fn metasprite(&self, sprite_id: u32) -> Option<&MetaSprite> {
+        self.metasprites.get(&sprite_id)
+    }
+
+    // Upload .zsa animation clips, replacing any loaded before - returns false
+    // if the data is invalid
+    pub fn load_animations(&mut self, data: &[u8]) -> bool {
+        match AnimationLibrary::from_bytes(data) {
+            Ok(animations) => {
+                self.animations = animations;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    // Pattern shown on a clip's frame, None for an unknown clip or frame
+    pub fn clip_pattern(&self, position: &ClipFrame) -> Option<u32> {
+        self.animations.pattern(position)
+    }
+
+    pub fn clear(&mut self) {
+        self.patterns.clear();
+        self.metasprites.clear();
+        self.animations = AnimationLibrary::new();
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upload_refuses_patterns_larger_than_a_hardware_sprite() {
+        let mut bank = SpriteBank::new();
+        assert!(bank.upload(0, 32, 32, &[1; 32 * 32]));
+        assert!(!bank.upload(1, 33, 8, &[1; 33 * 8]));
+        assert!(!bank.upload(2, 8, 48, &[1; 8 * 48]));
+        assert!(!bank.upload(3, 8, 8, &[1; 10]));
+        assert_eq!(bank.pattern_ids(), [0]);
+    }
+}
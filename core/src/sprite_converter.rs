@@ -1,7 +1,10 @@
 use image::{RgbaImage, Rgba};
 use std::collections::HashMap;
 
-// Master palette from PPU - keeping in sync
+// The converter quantizes against its own 128-color table, which predates
+// the PPU's master palette and differs from it in the cyans (64-79), blues
+// 92-95, purples 108-111 and 112-127. Existing art was converted with this
+// table, so it's kept to give the same indices when art is re-converted.
 const MASTER_PALETTE: [(u8, u8, u8); 128] = [
     // Grayscale ramp (0-15)
     (0, 0, 0), (17, 17, 17), (34, 34, 34), (51, 51, 51),
@@ -100,6 +100,15 @@ impl ZebratronSystem {
         self.ppu.get_screen_buffer()
     }
 
+    // Zero-copy access - a stable pointer into wasm memory for a Uint8Array view
+    pub fn get_screen_buffer_ptr(&self) -> *const u8 {
+        self.ppu.get_screen_buffer_ptr()
+    }
+
+    pub fn get_screen_buffer_len(&self) -> usize {
+        self.ppu.get_screen_buffer_len()
+    }
+
     pub fn get_screen_width(&self) -> u32 {
         self.ppu.get_screen_width()
     }
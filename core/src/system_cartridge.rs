@@ -1,12 +1,26 @@
 use wasm_bindgen::prelude::*;
 use crate::cpu::Cpu;
-use crate::ppu_clean::Ppu;
+use crate::ppu_clean::{Layer, OutputMode, Ppu, SpriteAffine, SpriteData, STATUS_HBLANK};
+use crate::debug_view::DebugView;
+use crate::color_math::{BlendMode, Tint};
+use crate::palette::{PaletteCycle, MASTER_PALETTE};
+use crate::hud::{HudCell, HudPriority, HudTile};
+use crate::post_process::{UpscaleFilter, MAX_BLOOM_RADIUS, MAX_SCALE};
+use crate::video_mode::VideoMode;
+use crate::mode7::Mode7Matrix;
+use crate::tile_animation::TileAnimation;
+use crate::animation::ClipFrame;
+use crate::transition::{Transition, TransitionDirection, TransitionKind, TransitionOwner};
 use crate::apu::Apu;
 use crate::memory::Memory;
-use crate::cartridge::{HambertCartridge, ZSynthCartridge};
+use crate::cartridge::{AudioCommands, HambertCartridge, MusicCommand, SoundSource, ZSynthCartridge};
 use crate::font_system::{FontSystem, Language};
 use crate::utils;
 
+// APU steps per frame - audio runs at a fixed rate whatever the video
+// mode's line timing
+const APU_STEPS_PER_FRAME: u32 = 89_342;
+
 #[wasm_bindgen]
 pub struct ZebratronCartridgeSystem {
     cpu: Cpu,
@@ -20,6 +34,7 @@ pub struct ZebratronCartridgeSystem {
     frame_ready: bool,
     last_game_state: u32, // Track game state changes for audio management
     font_system: FontSystem, // Internationalization support
+    completed_transition: Option<u32>, // Last finished screen transition, for the host
 }
 
 #[wasm_bindgen]
@@ -40,6 +55,7 @@ impl ZebratronCartridgeSystem {
             frame_ready: false,
             last_game_state: 0, // Start with intro state
             font_system: FontSystem::new(), // Initialize font system with English
+            completed_transition: None,
         };
         
         // Set to Japanese language for hiragana text
@@ -49,7 +65,31 @@ impl ZebratronCartridgeSystem {
 
     // Load the Hambert cartridge
     pub fn load_hambert_cartridge(&mut self) -> bool {
-        let hambert = HambertCartridge::new();
+        let mut hambert = HambertCartridge::new();
+        hambert.set_screen_size(self.ppu.get_screen_width() as u32, self.ppu.get_screen_height() as u32);
+        self.ppu_mut().load_sprite_bank(hambert.sprite_bank());
+        self.ppu.load_sprite_palettes(hambert.sprite_palettes());
+        self.ppu.load_animations(&hambert.animation_data());
+        self.ppu.clear_pattern_animations();
+        self.ppu.clear_palette_cycles();
+        let (intro_sprite, intro_palette) = hambert.intro_sprite();
+        self.ppu.set_intro_sprite(Some(intro_sprite), Some(intro_palette));
+        self.ppu.reset_hud();
+        for (index, tile) in hambert.hud_tiles().iter().enumerate() {
+            self.ppu.hud_mut().upload_tile(index, *tile);
+        }
+        for animation in hambert.hud_tile_animations() {
+            self.ppu.hud_mut().tile_animations.add(animation);
+        }
+        self.apu.clear_music();
+        for track in hambert.music_tracks() {
+            self.apu.load_music(&track);
+        }
+        self.apu.load_sfx_table(&hambert.sound_effects());
+        self.apu.clear_samples();
+        for (data, sample_rate) in hambert.samples() {
+            self.apu.load_sample(data, sample_rate);
+        }
         self.hambert_cartridge = Some(hambert);
         self.zsynth_cartridge = None;
         self.current_cartridge_type = 1;
@@ -60,6 +100,15 @@ impl ZebratronCartridgeSystem {
     // Load the Z-Synth cartridge
     pub fn load_zsynth_cartridge(&mut self) -> bool {
         let zsynth = ZSynthCartridge::new();
+        self.ppu_mut().load_sprite_bank(zsynth.sprite_bank());
+        self.ppu.load_sprite_palettes(zsynth.sprite_palettes());
+        self.ppu.clear_pattern_animations();
+        self.ppu.clear_palette_cycles();
+        self.ppu.set_intro_sprite(None, None);
+        self.ppu.reset_hud();
+        self.apu.clear_music();
+        self.apu.clear_sfx_table();
+        self.apu.clear_samples();
         self.zsynth_cartridge = Some(zsynth);
         self.hambert_cartridge = None;
         self.current_cartridge_type = 2;
@@ -85,46 +134,84 @@ impl ZebratronCartridgeSystem {
         self.running
     }
 
-    // Step one frame - handles cartridge update and PPU rendering
+    // Step one frame - runs game logic during VBlank, then the beam draws the
+    // frame line by line until the next VBlank starts
     pub fn step_frame(&mut self) -> bool {
         if !self.running {
             return false;
         }
 
-        // Step PPU until a frame is complete (authentic timing)
-        loop {
-            let frame_complete = self.ppu.step(&self.memory);
+        // Update cartridge game logic every frame
+        match self.current_cartridge_type {
+            1 => {
+                if let Some(ref mut cartridge) = self.hambert_cartridge {
+                    cartridge.update_game(false, false, false, false); // No input here, input handled separately
+                }
+            }
+            2 => {
+                if let Some(ref mut cartridge) = self.zsynth_cartridge {
+                    cartridge.update_synth(); // Z-Synth doesn't use regular input here
+                }
+            }
+            _ => {}
+        }
 
-            // Step APU for sound effect processing
-            self.apu.step();
+        // Report finished transitions and start requested ones
+        self.process_cartridge_transitions();
 
-            if frame_complete {
-                self.frame_ready = true;
+        // Sync cartridge data with PPU
+        self.sync_cartridge_to_ppu();
 
-                // Update cartridge game logic every frame
-                match self.current_cartridge_type {
-                    1 => {
-                        if let Some(ref mut cartridge) = self.hambert_cartridge {
-                            cartridge.update_game(false, false, false, false); // No input here, input handled separately
-                        }
-                    }
-                    2 => {
-                        if let Some(ref mut cartridge) = self.zsynth_cartridge {
-                            cartridge.update_synth(); // Z-Synth doesn't use regular input here
-                        }
-                    }
-                    _ => {}
-                }
+        // Step PPU until the frame is complete (authentic timing)
+        while !self.ppu.step(&self.memory) {}
 
-                // Sync cartridge data with PPU
-                self.sync_cartridge_to_ppu();
+        // Step APU for sound effect processing
+        for _ in 0..APU_STEPS_PER_FRAME {
+            self.apu.step();
+        }
 
-                // Process cartridge audio commands
-                self.process_cartridge_audio();
+        // Music driver and sound effects run once per frame, like a driver
+        // called from VBlank
+        self.apu.step_music_frame();
+        self.apu.step_sfx_frame();
 
-                return true;
+        // Process cartridge audio commands
+        self.process_cartridge_audio();
+
+        self.frame_ready = true;
+        true
+    }
+
+    // Run the beam to the next HBlank (or the start of VBlank) so the host
+    // can change the PPU between lines. Game logic and audio still advance
+    // in step_frame. Returns the status register.
+    pub fn step_scanline(&mut self) -> u8 {
+        if self.running {
+            loop {
+                let was_hblank = self.ppu.read_status() & STATUS_HBLANK != 0;
+                let frame_complete = self.ppu.step(&self.memory);
+                let hblank = self.ppu.read_status() & STATUS_HBLANK != 0;
+                if frame_complete || (hblank && !was_hblank) {
+                    break;
+                }
             }
         }
+        self.ppu.read_status()
+    }
+
+    // Status register: bit 7 = VBlank, bit 6 = HBlank, bit 5 = odd field,
+    // bit 4 = sprite overflow (a line had more than 16 sprites this frame)
+    pub fn get_ppu_status(&self) -> u8 {
+        self.ppu.read_status()
+    }
+
+    // Beam position: scanline within the field and dot within the line
+    pub fn get_scanline(&self) -> u32 {
+        self.ppu.get_scanline() as u32
+    }
+
+    pub fn get_beam_x(&self) -> u32 {
+        self.ppu.get_cycle() as u32
     }
 
     // Update cartridge game logic and sync with PPU
@@ -150,7 +237,18 @@ impl ZebratronCartridgeSystem {
         self.process_cartridge_audio();
     }
 
+    // PPU access for host writes that change what's drawn. Writes can land
+    // mid-frame (see step_scanline), so lines the beam hasn't reached yet
+    // are recomposed with the new state; lines already output are left as
+    // they were.
+    fn ppu_mut(&mut self) -> &mut Ppu {
+        self.ppu.invalidate_lines();
+        &mut self.ppu
+    }
+
     fn sync_cartridge_to_ppu(&mut self) {
+        // Input can arrive mid-frame, so this is a host write like any other
+        self.ppu.invalidate_lines();
         match self.current_cartridge_type {
             1 => {
                 // Hambert cartridge
@@ -200,15 +298,57 @@ impl ZebratronCartridgeSystem {
                                     .unwrap()
                                     .as_bool()
                                     .unwrap_or(false);
-
-                                self.ppu.add_sprite(x, y, sprite_id, active, facing_left);
+                                let palette = js_sys::Reflect::get(&entity_data, &"palette".into())
+                                    .unwrap()
+                                    .as_f64()
+                                    .map(|p| p as u8);
+                                let rotation = js_sys::Reflect::get(&entity_data, &"rotation".into())
+                                    .unwrap()
+                                    .as_f64()
+                                    .map(|r| r as f32);
+                                let blend = js_sys::Reflect::get(&entity_data, &"blend".into())
+                                    .unwrap()
+                                    .as_f64()
+                                    .map_or(BlendMode::Opaque, |b| BlendMode::from_u32(b as u32));
+                                let tint = js_sys::Reflect::get(&entity_data, &"tint".into())
+                                    .unwrap()
+                                    .as_f64()
+                                    .map(|color| Tint {
+                                        color: MASTER_PALETTE[color as usize % MASTER_PALETTE.len()],
+                                        strength: js_sys::Reflect::get(&entity_data, &"tint_strength".into())
+                                            .unwrap()
+                                            .as_f64()
+                                            .unwrap_or(255.0) as u8,
+                                    });
+                                let clip = js_sys::Reflect::get(&entity_data, &"clip".into())
+                                    .unwrap()
+                                    .as_string()
+                                    .map(|clip| ClipFrame {
+                                        clip,
+                                        frame: js_sys::Reflect::get(&entity_data, &"clip_frame".into())
+                                            .unwrap()
+                                            .as_f64()
+                                            .unwrap_or(0.0) as usize,
+                                    });
+
+                                self.ppu.add_sprite_data(SpriteData {
+                                    x,
+                                    y,
+                                    sprite_id,
+                                    active,
+                                    flip_horizontal: facing_left,
+                                    flip_vertical: false,
+                                    palette,
+                                    affine: rotation.map(|rotation| SpriteAffine { rotation, scale_x: 1.0, scale_y: 1.0 }),
+                                    blend,
+                                    tint,
+                                    clip,
+                                });
                             }
                         }
 
-                        // Sync player states for visual effects
-                        self.ppu.set_lives(cartridge.get_lives());
-                        self.ppu.set_player_death_state(cartridge.is_player_dying(), cartridge.get_player_death_flash());
-                        self.ppu.set_player_invulnerability_state(cartridge.is_player_invulnerable(), cartridge.get_player_invul_flash());
+                        // Redraw the HUD layer
+                        cartridge.draw_hud(self.ppu.hud_mut());
                     }
                 }
             }
@@ -241,16 +381,9 @@ impl ZebratronCartridgeSystem {
                                 .as_bool()
                                 .unwrap_or(false);
                             
-                            // Use different sprite IDs for different key states
-                            // 10 = white key unpressed, 11 = white key pressed
-                            // 12 = black key unpressed, 13 = black key pressed
-                            let sprite_id = if is_black {
-                                if is_pressed { 13 } else { 12 }
-                            } else {
-                                if is_pressed { 11 } else { 10 }
-                            };
-                            
-                            self.ppu.add_sprite(x, y, sprite_id, true, false); // Piano keys don't flip
+                            // Pressed keys swap to their pressed palette
+                            let (sprite_id, palette) = ZSynthCartridge::key_sprite(is_black, is_pressed);
+                            self.ppu.add_sprite_with_palette(x, y, sprite_id, true, false, Some(palette)); // Piano keys don't flip
                         }
                     }
                 }
@@ -261,6 +394,28 @@ impl ZebratronCartridgeSystem {
         }
     }
 
+    // Transitions finish at VBlank; the cartridge hears about it before its
+    // next frame is drawn, so a scene switched on completion is never shown
+    // uncovered. Each side only hears about its own transitions' IDs.
+    fn process_cartridge_transitions(&mut self) {
+        for (owner, id) in self.ppu.take_completed_transitions() {
+            match owner {
+                TransitionOwner::Host => self.completed_transition = Some(id),
+                TransitionOwner::Cartridge => {
+                    if let Some(ref mut cartridge) = self.hambert_cartridge {
+                        cartridge.on_transition_complete(id);
+                    }
+                }
+            }
+        }
+        if let Some(ref mut cartridge) = self.hambert_cartridge {
+            if let Some(transition) = cartridge.take_transition() {
+                self.ppu.invalidate_lines();
+                self.ppu.start_transition(transition.owned_by(TransitionOwner::Cartridge));
+            }
+        }
+    }
+
     fn process_cartridge_audio(&mut self) {
         // Check for game state changes and stop audio if transitioning to intro
         if self.current_cartridge_type == 1 {
@@ -294,6 +449,28 @@ impl ZebratronCartridgeSystem {
                 if let Some(ref mut cartridge) = self.hambert_cartridge {
                     cartridge.clear_pending_sounds();
                 }
+
+                // Music requests
+                let music_commands = match self.hambert_cartridge {
+                    Some(ref mut cartridge) => cartridge.take_music_commands(),
+                    None => Vec::new(),
+                };
+                for command in music_commands {
+                    match command {
+                        MusicCommand::Play(music_id) => AudioCommands::play_music(self, music_id),
+                        MusicCommand::Stop => AudioCommands::stop_music(self),
+                        MusicCommand::SetVolume(volume) => AudioCommands::set_music_volume(self, volume),
+                    }
+                }
+
+                // Register writes - addresses outside the APU page are ignored
+                let apu_writes = match self.hambert_cartridge {
+                    Some(ref mut cartridge) => cartridge.take_apu_writes(),
+                    None => Vec::new(),
+                };
+                for (address, value) in apu_writes {
+                    self.apu.write_register(address, value);
+                }
             }
             2 => {
                 // Z-Synth cartridge - process note on/off events
@@ -319,85 +496,480 @@ impl ZebratronCartridgeSystem {
         }
     }
 
-    fn play_sound_effect(&mut self, sound_id: u32) {
-        // Map sound IDs to APU actions
-        match sound_id {
-            0 => self.play_jump_sound(),      // Jump
-            1 => self.play_land_sound(),      // Land
-            2 => self.play_collect_sound(),   // Collect
-            3 => self.play_enemy_hit_sound(), // Enemy hit
-            4 => self.play_shuriken_sound(),  // Shuriken throw
-            5 => self.play_death_sound(),     // Death
-            6 => self.apu.play_laugh_sample(), // Laughter
-            7 => self.apu.play_voice_effect(1), // Gasp
-            8 => self.apu.play_voice_effect(2), // Grunt
-            _ => {}, // Unknown sound
+    // Frames are drawn line by line while step_frame runs the beam. When
+    // the system is stopped, draw the current state in one go.
+    pub fn render(&mut self) {
+        if self.running {
+            return;
+        }
+
+        // Update PPU with current game state
+        if let Some(cartridge) = &self.hambert_cartridge {
+            cartridge.draw_hud(self.ppu.hud_mut());
         }
+        
+        self.ppu.render();
+    }
+
+    pub fn stop_all_audio(&mut self) {
+        // Stop all audio when transitioning between game states
+        self.apu.exit_sound_test_mode();
+        self.apu.stop_sound_effects();
+        self.apu.stop_music();
     }
 
-    fn play_jump_sound(&mut self) {
-        // Longer, smoother rising pitch sweep from C4 to G5 over 0.6 seconds
-        self.apu.play_sound_effect(60, 79, 1, 0.6); // C4 to G5, sawtooth, 600ms
+    pub fn get_screen_buffer(&self) -> js_sys::Uint8Array {
+        js_sys::Uint8Array::from(self.ppu.screen_buffer())
     }
 
-    fn play_land_sound(&mut self) {
-        // Short downward thud for landing
-        self.apu.play_sound_effect(55, 40, 0, 0.15); // G3 to E2, pulse wave, 150ms
+    // Zero-copy access to the RGBA frame: create a Uint8Array view over the
+    // wasm memory buffer once and re-read it each frame. Recreate the view if
+    // the memory grows (its buffer is detached), and re-fetch the pointer and
+    // length when get_buffer_generation changes - video mode and output mode
+    // changes reallocate the frame buffers.
+    pub fn get_screen_buffer_ptr(&self) -> *const u8 {
+        self.ppu.screen_buffer().as_ptr()
     }
 
-    fn play_collect_sound(&mut self) {
-        // Pleasant pickup sound - use timed sound effect
-        self.apu.play_sound_effect(72, 84, 3, 0.2); // C5 to C6, sine wave, 200ms
+    pub fn get_screen_buffer_len(&self) -> usize {
+        self.ppu.screen_buffer().len()
     }
 
-    fn play_enemy_hit_sound(&mut self) {
-        // Sharp hit sound - brief noise burst
-        self.apu.play_sound_effect(60, 48, 4, 0.1); // C4 to C3, noise, 100ms
+    pub fn get_buffer_generation(&self) -> u32 {
+        self.ppu.buffer_generation()
     }
 
-    fn play_shuriken_sound(&mut self) {
-        // Whoosh sound for projectile - brief triangle wave
-        self.apu.play_sound_effect(55, 48, 2, 0.15); // G3 to C3, triangle, 150ms
+    // CRT post-processing into a separate (scaled) output buffer. Strengths
+    // are 0-255; scale is 1-4x, filter 0 = nearest, 1 = sharp bilinear; the
+    // bloom radius is 1-16 output pixels.
+    pub fn set_post_process_enabled(&mut self, enabled: bool) {
+        self.ppu.set_post_process_enabled(enabled);
     }
 
-    fn play_death_sound(&mut self) {
-        // Dramatic descending death sound - classic "bonk" effect
-        // Start high and sweep down over 1 second for dramatic effect
-        self.apu.play_sound_effect(84, 36, 1, 1.0); // C6 down to C2, sawtooth, 1 second
+    pub fn set_post_scale(&mut self, scale: f32, filter: u32) {
+        let settings = self.ppu.post_process_settings_mut();
+        settings.scale = scale.clamp(1.0, MAX_SCALE);
+        settings.filter = if filter == 1 { UpscaleFilter::SharpBilinear } else { UpscaleFilter::Nearest };
     }
 
-    pub fn render(&mut self) {
-        // Update PPU with current game state
-        if let Some(cartridge) = &self.hambert_cartridge {
-            self.ppu.set_lives(cartridge.get_lives());
-            self.ppu.set_player_death_state(
-                cartridge.is_player_dying(),
-                cartridge.get_player_death_flash()
-            );
+    pub fn set_post_scanlines(&mut self, strength: u8) {
+        self.ppu.post_process_settings_mut().scanlines = strength;
+    }
+
+    pub fn set_post_aperture_grille(&mut self, strength: u8) {
+        self.ppu.post_process_settings_mut().aperture_grille = strength;
+    }
+
+    pub fn set_post_bloom(&mut self, strength: u8, threshold: u8, radius: u32) {
+        let settings = self.ppu.post_process_settings_mut();
+        settings.bloom = strength;
+        settings.bloom_threshold = threshold;
+        settings.bloom_radius = (radius as usize).clamp(1, MAX_BLOOM_RADIUS);
+    }
+
+    pub fn set_post_ntsc_bleed(&mut self, strength: u8) {
+        self.ppu.post_process_settings_mut().ntsc_bleed = strength;
+    }
+
+    // Zero-copy view of the post-processed frame (null/0 while disabled)
+    pub fn get_post_buffer_ptr(&self) -> *const u8 {
+        self.ppu.post_processor().map_or(std::ptr::null(), |post| post.output().as_ptr())
+    }
+
+    pub fn get_post_buffer_len(&self) -> usize {
+        self.ppu.post_processor().map_or(0, |post| post.output().len())
+    }
+
+    pub fn get_post_buffer_width(&self) -> u32 {
+        self.ppu.post_processor().map_or(0, |post| post.output_width() as u32)
+    }
+
+    pub fn get_post_buffer_height(&self) -> u32 {
+        self.ppu.post_processor().map_or(0, |post| post.output_height() as u32)
+    }
+
+    // PPU debug views, rendered into a separate RGBA image on request:
+    // 0 = pattern sheet (drawn with palette RAM sub-palette `palette` - 0-7
+    // background, 8-15 sprite - or the master palette), 1 = palette RAM, 2 = OAM list, 3-6 = background, mode-7,
+    // sprite or HUD layer alone. Returns false for an unknown view.
+    pub fn render_debug_view(&mut self, view: u32, palette: Option<u8>) -> bool {
+        match DebugView::from_u32(view, palette) {
+            Some(view) => {
+                self.ppu.render_debug_view(view);
+                true
+            }
+            None => false,
         }
-        
-        self.ppu.render();
     }
 
-    pub fn stop_all_audio(&mut self) {
-        // Stop all audio when transitioning between game states
-        self.apu.exit_sound_test_mode();
+    pub fn get_debug_image_ptr(&self) -> *const u8 {
+        self.ppu.debug_image().pixels.as_ptr()
     }
 
-    pub fn get_screen_buffer(&self) -> js_sys::Uint8Array {
-        let buffer = self.ppu.get_screen_buffer();
-        js_sys::Uint8Array::from(&buffer[..])
+    pub fn get_debug_image_len(&self) -> usize {
+        self.ppu.debug_image().pixels.len()
+    }
+
+    pub fn get_debug_image_width(&self) -> u32 {
+        self.ppu.debug_image().width as u32
+    }
+
+    pub fn get_debug_image_height(&self) -> u32 {
+        self.ppu.debug_image().height as u32
+    }
+
+    // Show or hide a layer (0=background, 1=mode7, 2=sprites, 3=HUD) in the
+    // main output
+    pub fn set_layer_enabled(&mut self, layer: u32, enabled: bool) {
+        if let Some(layer) = Layer::from_u32(layer) {
+            self.ppu_mut().set_layer_enabled(layer, enabled);
+        }
+    }
+
+    // Dirty tracking - off by default since it keeps a copy of the last frame
+    pub fn set_dirty_tracking(&mut self, enabled: bool) {
+        self.ppu.set_dirty_tracking(enabled);
+    }
+
+    // One bit per scanline (bit line % 8 of byte line / 8) set when the line
+    // changed since the previous frame
+    pub fn get_changed_lines(&self) -> Vec<u8> {
+        self.ppu.dirty_tracker().map_or(Vec::new(), |tracker| tracker.changed_lines().to_vec())
+    }
+
+    // [x, y, width, height] of everything that changed, empty if nothing did
+    pub fn get_dirty_rect(&self) -> Vec<u32> {
+        match self.ppu.dirty_tracker().and_then(|tracker| tracker.rect()) {
+            Some((x, y, width, height)) => vec![x as u32, y as u32, width as u32, height as u32],
+            None => Vec::new(),
+        }
+    }
+
+    // Video mode: 0 = 256x224, 1 = 320x240 (default), 2 = 320x480 interlaced.
+    // Returns false for an unknown mode. Switching to another mode
+    // reallocates the frame buffers and bumps get_buffer_generation.
+    pub fn set_video_mode(&mut self, mode: u32) -> bool {
+        match VideoMode::from_u32(mode) {
+            Some(mode) => {
+                self.ppu_mut().set_video_mode(mode);
+                if let Some(ref mut cartridge) = self.hambert_cartridge {
+                    cartridge.set_screen_size(mode.width() as u32, mode.height() as u32);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get_video_mode(&self) -> u32 {
+        self.ppu.get_video_mode().to_u32()
+    }
+
+    pub fn get_screen_width(&self) -> u32 {
+        self.ppu.get_screen_width() as u32
+    }
+
+    pub fn get_screen_height(&self) -> u32 {
+        self.ppu.get_screen_height() as u32
+    }
+
+    // Field parity in interlaced modes (false = even field)
+    pub fn is_odd_field(&self) -> bool {
+        self.ppu.is_odd_field()
+    }
+
+    // Output mode: 0 = RGBA, 1 = indexed (palette indices + active palette)
+    pub fn set_output_mode(&mut self, mode: u32) {
+        let mode = if mode == 1 { OutputMode::Indexed } else { OutputMode::Rgba };
+        self.ppu_mut().set_output_mode(mode);
+    }
+
+    pub fn get_output_mode(&self) -> u32 {
+        match self.ppu.get_output_mode() {
+            OutputMode::Rgba => 0,
+            OutputMode::Indexed => 1,
+        }
+    }
+
+    // Zero-copy access to the indexed frame: wrap these in a Uint8Array over
+    // the wasm memory buffer. Like the RGBA frame, re-fetch them when
+    // get_buffer_generation changes.
+    pub fn get_index_buffer_ptr(&self) -> *const u8 {
+        self.ppu.index_buffer().as_ptr()
+    }
+
+    pub fn get_index_buffer_len(&self) -> usize {
+        self.ppu.index_buffer().len()
+    }
+
+    // 256 RGBA entries with master brightness, tint and transition fades applied
+    pub fn get_active_palette_ptr(&self) -> *const u8 {
+        self.ppu.active_palette().as_ptr()
+    }
+
+    pub fn get_active_palette_len(&self) -> usize {
+        self.ppu.active_palette().len()
+    }
+
+    pub fn get_index_buffer(&self) -> js_sys::Uint8Array {
+        js_sys::Uint8Array::from(self.ppu.index_buffer())
+    }
+
+    pub fn get_active_palette(&self) -> js_sys::Uint8Array {
+        js_sys::Uint8Array::from(self.ppu.active_palette())
     }
 
     // PPU control methods
     pub fn toggle_color_test(&mut self) {
-        self.ppu.toggle_color_test();
+        self.ppu_mut().toggle_color_test();
     }
 
     pub fn get_color_test_mode(&self) -> bool {
         self.ppu.get_color_test_mode()
     }
 
+    // HUD layer - a grid of 8x8 cells covering the screen that ignores
+    // scroll (40x30 in the standard mode, resized with the video mode).
+    // Colors index the HUD palette, or the master palette when none is set.
+    // The cartridge only redraws its own cells, so host text stays put.
+    pub fn hud_clear(&mut self) {
+        self.ppu_mut().hud_mut().clear();
+    }
+
+    pub fn get_hud_columns(&self) -> u32 {
+        self.ppu.hud().columns() as u32
+    }
+
+    pub fn get_hud_rows(&self) -> u32 {
+        self.ppu.hud().rows() as u32
+    }
+
+    pub fn hud_write_text(&mut self, column: u32, row: u32, text: &str, color: u8) -> u32 {
+        self.ppu.invalidate_lines();
+        self.ppu.hud_mut().write_text(&self.font_system, column as usize, row as usize, text, color) as u32
+    }
+
+    pub fn hud_set_tile(&mut self, column: u32, row: u32, tile: u32, color: u8) {
+        let cell = HudCell { tile: HudTile::Custom(tile as usize), color };
+        self.ppu_mut().hud_mut().set_cell(column as usize, row as usize, cell);
+    }
+
+    pub fn hud_clear_cell(&mut self, column: u32, row: u32) {
+        self.ppu_mut().hud_mut().set_cell(column as usize, row as usize, HudCell::EMPTY);
+    }
+
+    // 8 bytes, one per row, bit 0 = leftmost pixel
+    pub fn upload_hud_tile(&mut self, index: u32, data: &[u8]) -> bool {
+        match <[u8; 8]>::try_from(data) {
+            Ok(tile) => self.ppu_mut().hud_mut().upload_tile(index as usize, tile),
+            Err(_) => false,
+        }
+    }
+
+    pub fn set_hud_enabled(&mut self, enabled: bool) {
+        self.ppu_mut().hud_mut().enabled = enabled;
+    }
+
+    pub fn set_hud_palette(&mut self, palette: Option<u8>) {
+        self.ppu_mut().hud_mut().palette = palette;
+    }
+
+    pub fn set_hud_priority(&mut self, above_sprites: bool) {
+        self.ppu_mut().hud_mut().priority = if above_sprites {
+            HudPriority::AboveSprites
+        } else {
+            HudPriority::BelowSprites
+        };
+    }
+
+    // Rows covered by the HUD window, with an optional fill color behind empty cells
+    pub fn set_hud_window(&mut self, top_row: u32, bottom_row: u32, fill: Option<u8>) {
+        let hud = self.ppu_mut().hud_mut();
+        hud.window_top = top_row as usize;
+        hud.window_bottom = bottom_row as usize;
+        hud.fill = fill;
+    }
+
+    // Color math - layer: 0=background, 1=mode7, 2=sprites, 3=HUD;
+    // mode: 0=opaque, 1=50% blend, 2=additive, 3=subtractive
+    pub fn set_layer_blend(&mut self, layer: u32, mode: u32) {
+        if let Some(layer) = Layer::from_u32(layer) {
+            self.ppu_mut().set_layer_blend(layer, BlendMode::from_u32(mode));
+        }
+    }
+
+    // Master brightness 0 (black) to 15 (full)
+    pub fn set_master_brightness(&mut self, brightness: u8) {
+        self.ppu_mut().set_master_brightness(brightness);
+    }
+
+    pub fn get_master_brightness(&self) -> u8 {
+        self.ppu.get_master_brightness()
+    }
+
+    pub fn fade_brightness(&mut self, target: u8, frames: u32) {
+        self.ppu_mut().fade_brightness(target, frames);
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.ppu.is_fading()
+    }
+
+    // Tint the whole screen toward a master palette color (strength 0 = off)
+    pub fn set_screen_tint(&mut self, color: u8, strength: u8) {
+        let tint = if strength > 0 {
+            Some(Tint { color: MASTER_PALETTE[color as usize], strength })
+        } else {
+            None
+        };
+        self.ppu_mut().set_screen_tint(tint);
+    }
+
+    // Screen transitions - kind: 0=fade, 1=mosaic, 2=horizontal wipe,
+    // 3=vertical wipe, 4=iris around (center_x, center_y); direction:
+    // 0=out (cover the screen), 1=in (reveal it). The ID is reported back
+    // by take_completed_transition when it finishes, or when another
+    // transition (the host's or the cartridge's) replaces it. Cartridge
+    // transitions are never reported here. Returns false for an unknown kind.
+    pub fn start_transition(&mut self, id: u32, kind: u32, direction: u32, frames: u32, center_x: f32, center_y: f32) -> bool {
+        let kind = match TransitionKind::from_u32(kind) {
+            Some(kind) => kind,
+            None => return false,
+        };
+        let direction = if direction == 1 { TransitionDirection::In } else { TransitionDirection::Out };
+        self.ppu_mut().start_transition(Transition::new(id, kind, direction, frames).with_center(center_x, center_y));
+        true
+    }
+
+    pub fn is_transition_running(&self) -> bool {
+        self.ppu.is_transition_running()
+    }
+
+    pub fn take_completed_transition(&mut self) -> Option<u32> {
+        self.completed_transition.take()
+    }
+
+    // Mode-7 layer - an affine background drawn from a sprite bank pattern.
+    // The matrix maps screen space to texture space around the origin.
+    pub fn set_mode7_enabled(&mut self, enabled: bool) {
+        self.ppu_mut().set_mode7_enabled(enabled);
+    }
+
+    pub fn set_mode7_pattern(&mut self, pattern_id: u32, palette: Option<u8>, wrap: bool) {
+        self.ppu_mut().set_mode7_pattern(pattern_id, palette, wrap);
+    }
+
+    pub fn set_mode7_matrix(&mut self, a: f32, b: f32, c: f32, d: f32) {
+        self.ppu_mut().set_mode7_matrix(Mode7Matrix { a, b, c, d });
+    }
+
+    pub fn set_mode7_rotation(&mut self, angle: f32, scale: f32) {
+        self.ppu_mut().set_mode7_matrix(Mode7Matrix::rotate_scale(angle, scale, scale));
+    }
+
+    pub fn set_mode7_origin(&mut self, x: f32, y: f32) {
+        self.ppu_mut().set_mode7_origin(x, y);
+    }
+
+    pub fn set_mode7_scroll(&mut self, x: f32, y: f32) {
+        self.ppu_mut().set_mode7_scroll(x, y);
+    }
+
+    pub fn set_mode7_lines(&mut self, first_line: u32, last_line: u32) {
+        self.ppu_mut().set_mode7_lines(first_line as usize, last_line as usize);
+    }
+
+    // Per-scanline matrix (pseudo-3D floors); stays set until cleared
+    pub fn set_mode7_line_matrix(&mut self, line: u32, a: f32, b: f32, c: f32, d: f32) {
+        self.ppu_mut().set_mode7_line_matrix(line as usize, Mode7Matrix { a, b, c, d });
+    }
+
+    pub fn clear_mode7_line_matrices(&mut self) {
+        self.ppu_mut().clear_mode7_line_matrices();
+    }
+
+    // Sprite bank access - replace or add a pattern (one palette index per pixel)
+    pub fn upload_sprite(&mut self, sprite_id: u32, width: u32, height: u32, pixels: &[u8]) -> bool {
+        self.ppu_mut().upload_sprite(sprite_id, width, height, pixels)
+    }
+
+    // Animation clips (.zsa data) for sprites to be drawn from - returns false
+    // if the data is invalid
+    pub fn upload_animations(&mut self, data: &[u8]) -> bool {
+        self.ppu_mut().load_animations(data)
+    }
+
+    // Palette RAM - palettes 0-7 are background, 8-15 are sprite palettes.
+    // Writes return false when they would exceed 64 on-screen colors.
+    pub fn write_palette(&mut self, palette: u32, index: u32, color: u8) -> bool {
+        self.ppu_mut().write_palette(palette as usize, index as usize, color)
+    }
+
+    pub fn read_palette(&self, palette: u32, index: u32) -> u8 {
+        self.ppu.read_palette(palette as usize, index as usize)
+    }
+
+    pub fn load_palette(&mut self, palette: u32, colors: &[u8]) -> bool {
+        self.ppu_mut().load_palette(palette as usize, colors)
+    }
+
+    pub fn copy_palette(&mut self, source: u32, destination: u32) -> bool {
+        self.ppu_mut().copy_palette(source as usize, destination as usize)
+    }
+
+    pub fn set_palette_size(&mut self, palette: u32, size: u32) -> bool {
+        self.ppu_mut().set_palette_size(palette as usize, size as usize)
+    }
+
+    // Rotates the visible entries (1 up to the palette's size) of the range;
+    // returns false if fewer than two of them are in it
+    pub fn rotate_palette(&mut self, palette: u32, start: u32, length: u32) -> bool {
+        self.ppu_mut().rotate_palette(palette as usize, start as usize, length as usize)
+    }
+
+    // Palette cycling - every `frames_per_step` frames the PPU rotates entries
+    // start..start+length of a palette by one, starting a full period after
+    // it's added. Up to 8 ranges; returns false when full, for a zero rate or
+    // for a range that isn't at least two of the palette's visible entries
+    // (1 up to its size).
+    pub fn add_palette_cycle(&mut self, palette: u32, start: u32, length: u32, frames_per_step: u32) -> bool {
+        let cycle = PaletteCycle { palette: palette as usize, start: start as usize, length: length as usize, frames_per_step };
+        self.ppu_mut().add_palette_cycle(cycle)
+    }
+
+    pub fn clear_palette_cycles(&mut self) {
+        self.ppu_mut().clear_palette_cycles();
+    }
+
+    // Animated tiles - anything drawn with `base_id` shows `frames` in turn,
+    // each for `frame_duration` frames. Pattern animations cover sprites and
+    // the mode-7 texture; HUD animations cover custom HUD tiles. Up to 64
+    // each; returns false when full or without frames.
+    pub fn add_pattern_animation(&mut self, base_id: u32, frames: &[u32], frame_duration: u32) -> bool {
+        let animation = TileAnimation { base_id, frames: frames.to_vec(), frame_duration };
+        self.ppu_mut().add_pattern_animation(animation)
+    }
+
+    pub fn add_hud_tile_animation(&mut self, base_id: u32, frames: &[u32], frame_duration: u32) -> bool {
+        let animation = TileAnimation { base_id, frames: frames.to_vec(), frame_duration };
+        self.ppu_mut().hud_mut().tile_animations.add(animation)
+    }
+
+    pub fn clear_tile_animations(&mut self) {
+        self.ppu_mut().clear_pattern_animations();
+        self.ppu_mut().hud_mut().tile_animations.clear();
+    }
+
+    pub fn schedule_palette_write(&mut self, scanline: u32, palette: u32, index: u32, color: u8) {
+        // Lines past the u16 range are still past the visible area (VBlank)
+        let scanline = scanline.min(u16::MAX as u32) as u16;
+        self.ppu_mut().schedule_palette_write(scanline, palette as usize, index as usize, color);
+    }
+
+    pub fn get_palette_color_count(&self) -> u32 {
+        self.ppu.get_palette_color_count() as u32
+    }
+
     // APU methods (simplified for cartridge system)
     pub fn initialize_audio(&mut self) {
         // Simplified - no-op for cartridge system
@@ -438,7 +1010,7 @@ impl ZebratronCartridgeSystem {
             _ => Language::English, // Default to English
         };
         self.font_system.set_language(lang);
-        self.ppu.set_language(lang);
+        self.ppu_mut().set_language(lang);
     }
 
     pub fn get_language(&self) -> u32 {
@@ -464,6 +1036,21 @@ impl ZebratronCartridgeSystem {
         self.apu.get_current_note() as u32
     }
 
+    // APU registers $4000-$4017: pulse 1/2, triangle and noise channels with
+    // envelopes, sweeps and length counters clocked by the frame sequencer.
+    // Returns false for an address outside the APU page. This is the host's
+    // way in; cartridges queue writes with HambertCartridge::write_apu_register,
+    // applied after their update each frame.
+    pub fn write_apu_register(&mut self, address: u16, value: u8) -> bool {
+        self.apu.write_register(address, value)
+    }
+
+    // $4015 read - bit per channel (pulse 1, pulse 2, triangle, noise) whose
+    // length counter is still running
+    pub fn read_apu_status(&self) -> u8 {
+        self.apu.read_status_register()
+    }
+
     pub fn generate_debug_samples(&mut self, count: usize) -> Vec<f32> {
         // Simplified debug sample generation
         let mut samples = Vec::new();
@@ -477,6 +1064,17 @@ impl ZebratronCartridgeSystem {
         self.apu.generate_sample()
     }
 
+    // count stereo frames as interleaved left/right samples
+    pub fn generate_audio_frames(&mut self, count: usize) -> Vec<f32> {
+        self.apu.generate_stereo_samples(count)
+    }
+
+    // Stereo placement of the register channels (0-3: pulse 1, pulse 2,
+    // triangle, noise)
+    pub fn set_channel_pan(&mut self, channel: u8, pan: f32) {
+        self.apu.set_channel_pan(channel, pan);
+    }
+
     // Get intro text for display (for Japanese hiragana text)
     pub fn get_intro_text(&self) -> String {
         if let Some(ref cartridge) = self.hambert_cartridge {
@@ -520,6 +1118,10 @@ impl ZebratronCartridgeSystem {
         self.apu.set_delay_mix(mix);
     }
 
+    pub fn set_delay_width(&mut self, width: f32) {
+        self.apu.set_delay_width(width);
+    }
+
     // Melody controls
     pub fn set_melody_enabled(&mut self, enabled: bool) {
         self.apu.set_melody_enabled(enabled);
@@ -658,6 +1260,22 @@ impl ZebratronCartridgeSystem {
         self.apu.sid_set_filter_type(filter_type);
     }
 
+    pub fn sid_set_voice_control(&mut self, voice: u8, control: u8) {
+        self.apu.sid_set_voice_control(voice, control);
+    }
+
+    pub fn sid_set_voice_envelope(&mut self, voice: u8, attack: f32, decay: f32, sustain: f32, release: f32) {
+        self.apu.sid_set_voice_envelope(voice, attack, decay, sustain, release);
+    }
+
+    pub fn sid_set_voice_lfo(&mut self, voice: u8, lfo: u8, target: u8, waveform: u8, rate: f32, depth: f32) -> bool {
+        self.apu.sid_set_voice_lfo(voice, lfo, target, waveform, rate, depth)
+    }
+
+    pub fn sid_set_voice_pan(&mut self, voice: u8, pan: f32) {
+        self.apu.sid_set_voice_pan(voice, pan);
+    }
+
     // Polyphonic layer API delegation to APU
     pub fn poly_play_chord(&mut self, notes: &[u8]) {
         self.apu.poly_play_chord(notes.to_vec());
@@ -674,4 +1292,314 @@ impl ZebratronCartridgeSystem {
     pub fn poly_stop_all(&mut self) {
         self.apu.poly_stop_all();
     }
-}
\ No newline at end of file
+
+    pub fn set_synth_envelope(&mut self, attack: f32, decay: f32, sustain: f32, release: f32) {
+        self.apu.set_synth_envelope(attack, decay, sustain, release);
+    }
+
+    pub fn set_synth_lfo(&mut self, lfo: u8, target: u8, waveform: u8, rate: f32, depth: f32) -> bool {
+        self.apu.set_synth_lfo(lfo, target, waveform, rate, depth)
+    }
+
+    pub fn set_synth_pan(&mut self, pan: f32) {
+        self.apu.set_synth_pan(pan);
+    }
+
+    // Wavetable voice API delegation to APU
+    pub fn write_wavetable(&mut self, table: u8, samples: &[u8]) -> bool {
+        self.apu.write_wavetable(table, samples)
+    }
+
+    pub fn wavetable_voice_play(&mut self, voice: u8, table: u8, frequency: f32, volume: f32, pan: f32) {
+        self.apu.wavetable_voice_play(voice, table, frequency, volume, pan);
+    }
+
+    pub fn wavetable_voice_play_note(&mut self, voice: u8, table: u8, note: u8, volume: f32, pan: f32) {
+        self.apu.wavetable_voice_play_note(voice, table, note, volume, pan);
+    }
+
+    pub fn wavetable_voice_set_frequency(&mut self, voice: u8, frequency: f32) {
+        self.apu.wavetable_voice_set_frequency(voice, frequency);
+    }
+
+    pub fn wavetable_voice_set_volume(&mut self, voice: u8, volume: f32) {
+        self.apu.wavetable_voice_set_volume(voice, volume);
+    }
+
+    pub fn wavetable_voice_set_pan(&mut self, voice: u8, pan: f32) {
+        self.apu.wavetable_voice_set_pan(voice, pan);
+    }
+
+    pub fn wavetable_voice_stop(&mut self, voice: u8) {
+        self.apu.wavetable_voice_stop(voice);
+    }
+
+    pub fn wavetable_stop_all(&mut self) {
+        self.apu.wavetable_stop_all();
+    }
+
+    // Tracker music (.zsm) API delegation to APU
+    pub fn load_music(&mut self, data: &[u8]) -> Option<u32> {
+        self.apu.load_music(data)
+    }
+
+    pub fn play_music(&mut self, music_id: u32) -> bool {
+        self.apu.play_music(music_id)
+    }
+
+    pub fn stop_music(&mut self) {
+        self.apu.stop_music();
+    }
+
+    pub fn fade_out_music(&mut self, frames: u32) {
+        self.apu.fade_out_music(frames);
+    }
+
+    pub fn set_music_tempo(&mut self, tempo: u8) {
+        self.apu.set_music_tempo(tempo);
+    }
+
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.apu.set_music_volume(volume);
+    }
+
+    pub fn is_music_playing(&self) -> bool {
+        self.apu.is_music_playing()
+    }
+
+    // Sound effect (.zsx) API delegation to APU
+    pub fn load_sfx_table(&mut self, data: &[u8]) -> bool {
+        self.apu.load_sfx_table(data)
+    }
+
+    pub fn play_sfx(&mut self, sound_id: u32) -> bool {
+        self.apu.play_sfx(sound_id)
+    }
+
+    pub fn play_sfx_panned(&mut self, sound_id: u32, pan: f32) -> bool {
+        self.apu.play_sfx_panned(sound_id, pan)
+    }
+
+    pub fn play_sfx_definition(&mut self, definition: &[u8]) -> bool {
+        self.apu.play_sfx_definition(definition)
+    }
+
+    // Music level while a ducking sound effect plays
+    pub fn set_sfx_duck_volume(&mut self, volume: f32) {
+        self.apu.set_sfx_duck_volume(volume);
+    }
+
+    // PCM sample bank API delegation to APU
+    pub fn load_sample(&mut self, data: &[u8], sample_rate: u32) -> Option<u32> {
+        self.apu.load_sample(data, sample_rate)
+    }
+
+    pub fn set_sample_loop(&mut self, sample_id: u32, start: u32, end: u32) -> bool {
+        self.apu.set_sample_loop(sample_id, start, end)
+    }
+
+    pub fn clear_samples(&mut self) {
+        self.apu.clear_samples();
+    }
+
+    pub fn play_sample(&mut self, sample_id: u32, rate: f32, volume: f32, pan: f32, looping: bool) -> Option<u32> {
+        self.apu.play_sample(sample_id, rate, volume, pan, looping)
+    }
+
+    pub fn stop_sample_channel(&mut self, channel: u32) {
+        self.apu.stop_sample_channel(channel);
+    }
+
+    pub fn set_sample_channel_rate(&mut self, channel: u32, rate: f32) {
+        self.apu.set_sample_channel_rate(channel, rate);
+    }
+
+    pub fn set_sample_channel_volume(&mut self, channel: u32, volume: f32) {
+        self.apu.set_sample_channel_volume(channel, volume);
+    }
+
+    pub fn set_sample_channel_pan(&mut self, channel: u32, pan: f32) {
+        self.apu.set_sample_channel_pan(channel, pan);
+    }
+}
+
+impl AudioCommands for ZebratronCartridgeSystem {
+    fn play_sound_effect(&mut self, sound_id: u32) {
+        // The loaded cartridge decides what each of its sound IDs plays
+        let (source, pan) = match self.hambert_cartridge {
+            Some(ref cartridge) => (cartridge.sound_source(sound_id), cartridge.sound_pan()),
+            None => (SoundSource::Table(sound_id), 0.0),
+        };
+        match source {
+            SoundSource::Table(sound_id) => {
+                self.apu.play_sfx_panned(sound_id, pan);
+            }
+            SoundSource::Sample { sample_id, rate } => {
+                self.apu.play_sample(sample_id, rate, 1.0, pan, false);
+            }
+            SoundSource::VoiceEffect(voice_type) => self.apu.play_voice_effect(voice_type),
+        }
+    }
+
+    fn play_music(&mut self, music_id: u32) {
+        self.apu.play_music(music_id);
+    }
+
+    fn stop_music(&mut self) {
+        self.apu.stop_music();
+    }
+
+    fn set_music_volume(&mut self, volume: f32) {
+        self.apu.set_music_volume(volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::PaletteRam;
+    use crate::ppu_clean::STATUS_VBLANK;
+
+    #[test]
+    fn test_step_scanline_and_status_register() {
+        let mut system = ZebratronCartridgeSystem::new();
+        assert_eq!(system.step_scanline(), 0); // Stopped - nothing runs
+        system.start();
+
+        // Each call stops at the start of a line's HBlank
+        for line in 0..240 {
+            let status = system.step_scanline();
+            assert_eq!(status & (STATUS_HBLANK | STATUS_VBLANK), STATUS_HBLANK);
+            assert_eq!((system.get_scanline(), system.get_beam_x()), (line, 320));
+        }
+
+        // Then the frame completes as VBlank starts
+        let status = system.step_scanline();
+        assert_eq!(status & (STATUS_HBLANK | STATUS_VBLANK), STATUS_VBLANK);
+        assert_eq!(system.get_scanline(), 240);
+        assert_eq!(system.get_ppu_status(), status);
+
+        // VBlank lines still have HBlank; VBlank clears when the next frame starts
+        assert_eq!(system.step_scanline() & STATUS_HBLANK, STATUS_HBLANK);
+        while system.get_scanline() != 0 {
+            system.step_scanline();
+        }
+        assert_eq!(system.get_ppu_status() & STATUS_VBLANK, 0);
+    }
+
+    #[test]
+    fn test_mid_frame_input_redraws_lines_below_the_beam() {
+        let mut system = ZebratronCartridgeSystem::new();
+        assert!(system.load_hambert_cartridge());
+        system.start();
+        let next_frame = |system: &mut ZebratronCartridgeSystem| while system.step_scanline() & STATUS_VBLANK == 0 {};
+        let lower_lines = |system: &ZebratronCartridgeSystem| {
+            let row = system.get_screen_width() as usize * 4;
+            system.ppu.screen_buffer()[100 * row..].to_vec()
+        };
+        next_frame(&mut system);
+        let before = lower_lines(&system);
+
+        // Typing out more of the intro text while line 99 is drawn shows it
+        // on the rest of this frame
+        while system.get_scanline() != 99 {
+            system.step_scanline();
+        }
+        for _ in 0..30 {
+            system.handle_input(false, false, false, false);
+        }
+        next_frame(&mut system);
+        let during = lower_lines(&system);
+        next_frame(&mut system);
+        assert_ne!(during, before);
+        assert_eq!(during, lower_lines(&system));
+    }
+
+    #[test]
+    fn test_transition_ids_are_namespaced() {
+        let mut system = ZebratronCartridgeSystem::new();
+        let memory = Memory::new();
+        let run_frame = |system: &mut ZebratronCartridgeSystem| {
+            while !system.ppu.step(&memory) {}
+            system.process_cartridge_transitions();
+        };
+
+        // A cartridge transition finishing isn't reported to the host
+        system.ppu.start_transition(
+            Transition::new(0, TransitionKind::Fade, TransitionDirection::Out, 1).owned_by(TransitionOwner::Cartridge),
+        );
+        run_frame(&mut system);
+        assert_eq!(system.take_completed_transition(), None);
+
+        assert!(system.start_transition(0, 0, 1, 1, 0.0, 0.0));
+        run_frame(&mut system);
+        assert_eq!(system.take_completed_transition(), Some(0));
+
+        // Zero frames is an immediate cut, still reported
+        assert!(system.start_transition(1, 0, 1, 0, 0.0, 0.0));
+        run_frame(&mut system);
+        assert_eq!(system.take_completed_transition(), Some(1));
+        assert!(!system.is_transition_running());
+    }
+
+    #[test]
+    fn test_cartridge_apu_register_writes() {
+        let mut system = ZebratronCartridgeSystem::new();
+        assert!(system.load_hambert_cartridge());
+        let cartridge = system.hambert_cartridge.as_mut().unwrap();
+        cartridge.write_apu_register(0x4015, 0x02); // Enable pulse 2
+        cartridge.write_apu_register(0x4006, 0xFD);
+        cartridge.write_apu_register(0x4007, 0x08); // Load its length counter
+        cartridge.write_apu_register(0x4020, 0xFF); // Outside the page - ignored
+        assert_eq!(system.read_apu_status(), 0);
+
+        system.process_cartridge_audio();
+        assert_eq!(system.read_apu_status(), 0x02);
+        assert!(system.hambert_cartridge.as_mut().unwrap().take_apu_writes().is_empty());
+    }
+
+    #[test]
+    fn test_sound_ids_are_mapped_by_the_cartridge() {
+        let mut system = ZebratronCartridgeSystem::new();
+        assert!(system.load_hambert_cartridge());
+
+        // Hambert's laugh is a sample, placed in the stereo field like its
+        // table effects - the player starts left of center
+        assert!(system.hambert_cartridge.as_ref().unwrap().sound_pan() < 0.0);
+        AudioCommands::play_sound_effect(&mut system, 6);
+        let output = system.apu.generate_stereo_samples(4096);
+        let imbalance: f32 = output.chunks_exact(2).map(|pair| (pair[0] - pair[1]).abs()).sum();
+        assert!(imbalance > 1.0);
+
+        AudioCommands::play_sound_effect(&mut system, 7); // Hambert's gasp
+        assert!(!system.apu.is_sound_effect_playing());
+        AudioCommands::play_sound_effect(&mut system, 2);
+        assert!(system.apu.is_sound_effect_playing());
+
+        // Another cartridge's table entries 6-8 play as effects
+        assert!(system.load_zsynth_cartridge());
+        let mut table = vec![b'Z', b'S', b'X', 1, 9];
+        table.extend(std::iter::repeat_n([0, 60, 0, 0, 0, 0, 0, 0, 128, 0, 0, 4, 4, 0, 200, 0], 9).flatten());
+        assert!(system.load_sfx_table(&table));
+        AudioCommands::play_sound_effect(&mut system, 7);
+        assert!(system.apu.is_sound_effect_playing());
+    }
+
+    #[test]
+    fn test_cartridge_sprite_palettes_fit_the_color_limit() {
+        let mut system = ZebratronCartridgeSystem::new();
+        assert!(system.load_hambert_cartridge());
+        let palettes = system.hambert_cartridge.as_ref().unwrap().sprite_palettes();
+        for &(palette, colors) in palettes {
+            let slot = PaletteRam::sprite_palette_slot(palette);
+            let loaded: Vec<u8> = (0..colors.len()).map(|index| system.ppu.read_palette(slot, index)).collect();
+            assert_eq!(loaded, colors);
+        }
+        assert!(system.ppu.load_sprite_palettes(palettes));
+
+        // Switching cartridges replaces every sprite palette
+        assert!(system.load_zsynth_cartridge());
+        assert_eq!(system.ppu.read_palette(PaletteRam::sprite_palette_slot(7), 1), 0);
+        assert_eq!(system.ppu.read_palette(PaletteRam::sprite_palette_slot(0), 2), 15);
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+// Animated tiles - a remap table from tile IDs to the tile shown this frame.
+// Each animation steps its base tile through a list of frames on a fixed
+// schedule of the PPU frame count, so water, lava, torches and conveyor belts
+// animate without the game rewriting tilemaps or sprites. Anything drawn with
+// the base ID shows the current frame instead.
+
+pub const MAX_TILE_ANIMATIONS: usize = 64;
+
+#[derive(Clone)]
+pub struct TileAnimation {
+    pub base_id: u32,         // ID placed in the tilemap / sprite list
+    pub frames: Vec<u32>,     // IDs shown in turn
+    pub frame_duration: u32,  // Frames each one is shown for, at least 1
+}
+
+#[derive(Clone, Default)]
+pub struct TileAnimator {
+    animations: Vec<TileAnimation>,
+    remap: HashMap<u32, u32>,
+}
+
+impl TileAnimator {
+    pub fn new() -> TileAnimator {
+        TileAnimator::default()
+    }
+
+    // Adding an animation for a base ID that already has one replaces it.
+    // Returns false for an animation without frames or when the table is full.
+    pub fn add(&mut self, animation: TileAnimation) -> bool {
+        if animation.frames.is_empty() {
+            return false;
+        }
+        match self.animations.iter().position(|existing| existing.base_id == animation.base_id) {
+            Some(index) => self.animations[index] = animation,
+            None if self.animations.len() < MAX_TILE_ANIMATIONS => self.animations.push(animation),
+            None => return false,
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.animations.clear();
+        self.remap.clear();
+    }
+
+    // Rebuild the remap table for a frame. Depends only on the frame count,
+    // so every animation with the same schedule stays in step.
+    pub fn update(&mut self, frame_count: u64) {
+        self.remap.clear();
+        for animation in &self.animations {
+            let step = frame_count / animation.frame_duration.max(1) as u64;
+            let frame = animation.frames[(step % animation.frames.len() as u64) as usize];
+            self.remap.insert(animation.base_id, frame);
+        }
+    }
+
+    // ID to draw for a tile this frame
+    pub fn resolve(&self, id: u32) -> u32 {
+        self.remap.get(&id).copied().unwrap_or(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remap_follows_frame_count() {
+        let mut animator = TileAnimator::new();
+        assert!(animator.add(TileAnimation { base_id: 5, frames: vec![5, 20, 21], frame_duration: 4 }));
+        assert!(!animator.add(TileAnimation { base_id: 6, frames: Vec::new(), frame_duration: 4 }));
+
+        let shown: Vec<u32> = [0, 3, 4, 8, 12].iter().map(|&frame| {
+            animator.update(frame);
+            animator.resolve(5)
+        }).collect();
+        assert_eq!(shown, [5, 5, 20, 21, 5]);
+        assert_eq!(animator.resolve(6), 6);
+    }
+}
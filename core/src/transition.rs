@@ -0,0 +1,292 @@
+// Screen transitions - full-screen effects the PPU applies to each output line
+// on top of the composed frame. A transition runs for a number of frames and
+// either covers the screen (Out) or reveals it (In); a scene change is an Out
+// transition, the switch while the screen is covered, then an In transition.
+// A finished Out transition holds the screen covered until the next one.
+
+pub const MAX_MOSAIC_SIZE: usize = 16;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TransitionKind {
+    Fade,           // To and from black
+    Mosaic,         // Pixel blocks growing to 16x16, then fading out
+    WipeHorizontal, // Black edge sweeping left to right
+    WipeVertical,   // Black edge sweeping top to bottom
+    Iris,           // Circle closing on (or opening from) a point
+}
+
+impl TransitionKind {
+    pub fn from_u32(kind: u32) -> Option<TransitionKind> {
+        match kind {
+            0 => Some(TransitionKind::Fade),
+            1 => Some(TransitionKind::Mosaic),
+            2 => Some(TransitionKind::WipeHorizontal),
+            3 => Some(TransitionKind::WipeVertical),
+            4 => Some(TransitionKind::Iris),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TransitionDirection {
+    Out, // Covers the screen
+    In,  // Reveals the screen
+}
+
+// Who started a transition. The host and the cartridge choose IDs
+// independently, so a completion is only reported to the side that started it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TransitionOwner {
+    Host,
+    Cartridge,
+}
+
+#[derive(Clone, Copy)]
+pub struct Transition {
+    pub id: u32, // Chosen by whoever starts it, reported back on completion
+    pub owner: TransitionOwner,
+    pub kind: TransitionKind,
+    pub direction: TransitionDirection,
+    pub center_x: f32, // Iris center in framebuffer pixels
+    pub center_y: f32,
+    duration: u32,
+    elapsed: u32,
+}
+
+impl Transition {
+    pub fn new(id: u32, kind: TransitionKind, direction: TransitionDirection, duration: u32) -> Transition {
+        Transition {
+            id,
+            owner: TransitionOwner::Host,
+            kind,
+            direction,
+            center_x: 0.0,
+            center_y: 0.0,
+            duration,
+            elapsed: 0,
+        }
+    }
+
+    pub fn owned_by(mut self, owner: TransitionOwner) -> Transition {
+        self.owner = owner;
+        self
+    }
+
+    pub fn with_center(mut self, x: f32, y: f32) -> Transition {
+        self.center_x = x;
+        self.center_y = y;
+        self
+    }
+
+    // Advance one frame. Returns true on the frame the transition finishes;
+    // a zero-frame transition finishes on its first step.
+    pub fn step(&mut self) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+        self.elapsed += 1;
+        self.elapsed >= self.duration
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration.max(1)
+    }
+
+    // How much of the screen is covered, 0.0 (none) to 1.0 (all)
+    pub fn coverage(&self) -> f32 {
+        let progress = if self.duration == 0 { 1.0 } else { self.elapsed as f32 / self.duration as f32 };
+        match self.direction {
+            TransitionDirection::Out => progress.min(1.0),
+            TransitionDirection::In => 1.0 - progress.min(1.0),
+        }
+    }
+
+    // Apply the effect to one RGBA output line. `source` is the composed
+    // frame the line was copied from (mosaic blocks sample it).
+    pub fn apply_line(&self, row: &mut [u8], line: usize, width: usize, height: usize, source: &[u8]) {
+        if self.coverage() <= 0.0 {
+            return;
+        }
+        self.apply_shape(row, line, width, height, source, |pixels| scale_pixels(pixels, 0.0));
+        scale_pixels(row, self.brightness());
+    }
+
+    // Indexed version of apply_line, on a line of master palette indices.
+    // Covered pixels take `black`; the fade is left to the palette the frame
+    // is shown with (see apply_palette).
+    pub fn apply_index_line(&self, row: &mut [u8], line: usize, width: usize, height: usize, source: &[u8], black: u8) {
+        if self.coverage() <= 0.0 {
+            return;
+        }
+        self.apply_shape(row, line, width, height, source, |pixels| pixels.fill(black));
+    }
+
+    // Fade an RGBA palette as apply_line fades the pixels
+    pub fn apply_palette(&self, palette: &mut [u8]) {
+        scale_pixels(palette, self.brightness());
+    }
+
+    // How bright the transition leaves the screen, 1.0 (untouched) to 0.0
+    fn brightness(&self) -> f32 {
+        let coverage = self.coverage();
+        match self.kind {
+            TransitionKind::Fade => 1.0 - coverage,
+            // First half grows the blocks, second half fades them out
+            TransitionKind::Mosaic => (2.0 - coverage * 2.0).min(1.0),
+            _ => 1.0,
+        }
+    }
+
+    // Mosaic blocks and covered areas, for RGBA or index lines
+    fn apply_shape(&self, row: &mut [u8], line: usize, width: usize, height: usize, source: &[u8], cover: impl Fn(&mut [u8])) {
+        let coverage = self.coverage();
+        let bytes = row.len() / width; // Per pixel
+        match self.kind {
+            TransitionKind::Fade => {}
+            TransitionKind::Mosaic => {
+                let growth = (coverage * 2.0).min(1.0);
+                let size = 1 + (growth * (MAX_MOSAIC_SIZE - 1) as f32) as usize;
+                if size > 1 {
+                    let source_line = line - line % size;
+                    let source_row = &source[source_line * width * bytes..(source_line + 1) * width * bytes];
+                    for (x, pixel) in row.chunks_exact_mut(bytes).enumerate() {
+                        let source_x = (x - x % size) * bytes;
+                        pixel.copy_from_slice(&source_row[source_x..source_x + bytes]);
+                    }
+                }
+            }
+            TransitionKind::WipeHorizontal => {
+                let covered = (coverage * width as f32).round() as usize;
+                cover(&mut row[..covered.min(width) * bytes]);
+            }
+            TransitionKind::WipeVertical => {
+                if (line as f32) < coverage * height as f32 {
+                    cover(row);
+                }
+            }
+            TransitionKind::Iris => {
+                // Radius shrinks from the farthest corner's distance to zero
+                let corner_x = self.center_x.max(width as f32 - self.center_x);
+                let corner_y = self.center_y.max(height as f32 - self.center_y);
+                let radius = (1.0 - coverage) * (corner_x * corner_x + corner_y * corner_y).sqrt();
+                let dy = line as f32 + 0.5 - self.center_y;
+                for (x, pixel) in row.chunks_exact_mut(bytes).enumerate() {
+                    let dx = x as f32 + 0.5 - self.center_x;
+                    if dx * dx + dy * dy > radius * radius {
+                        cover(pixel);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Scale RGB toward black (alpha stays opaque)
+fn scale_pixels(pixels: &mut [u8], scale: f32) {
+    if scale >= 1.0 {
+        return;
+    }
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel[0] = (pixel[0] as f32 * scale) as u8;
+        pixel[1] = (pixel[1] as f32 * scale) as u8;
+        pixel[2] = (pixel[2] as f32 * scale) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wipe_covers_and_reveals() {
+        let source = vec![200u8; 8 * 4];
+        let mut out = Transition::new(1, TransitionKind::WipeHorizontal, TransitionDirection::Out, 4);
+        out.step();
+        out.step();
+
+        let mut row = source.clone();
+        out.apply_line(&mut row, 0, 8, 1, &source);
+        assert_eq!(row[3 * 4], 0); // Left half covered
+        assert_eq!(row[4 * 4], 200);
+
+        assert!(!out.step());
+        assert!(out.step()); // Finishes on its last frame
+        assert!(out.is_finished());
+        assert_eq!(out.coverage(), 1.0);
+
+        let reveal = Transition::new(2, TransitionKind::WipeHorizontal, TransitionDirection::In, 4);
+        assert_eq!(reveal.coverage(), 1.0);
+    }
+
+    #[test]
+    fn test_zero_frames_finishes_on_the_first_step() {
+        let mut cut = Transition::new(1, TransitionKind::Fade, TransitionDirection::In, 0);
+        assert!(!cut.is_finished());
+        assert_eq!(cut.coverage(), 0.0);
+        assert!(cut.step());
+        assert!(cut.is_finished());
+        assert!(!cut.step());
+    }
+
+    #[test]
+    fn test_fade_scales_towards_black() {
+        let source = vec![200u8; 4 * 4];
+        let mut fade = Transition::new(1, TransitionKind::Fade, TransitionDirection::Out, 4);
+        fade.step();
+        fade.step();
+
+        let mut row = source.clone();
+        fade.apply_line(&mut row, 0, 4, 1, &source);
+        assert_eq!(&row[0..4], [100, 100, 100, 200]); // Alpha stays opaque
+    }
+
+    #[test]
+    fn test_mosaic_repeats_block_corners() {
+        // 16x16 frame where each pixel's red is its x and green its y
+        let source: Vec<u8> = (0..16 * 16).flat_map(|i| [(i % 16) as u8, (i / 16) as u8, 0, 255]).collect();
+        let mut mosaic = Transition::new(1, TransitionKind::Mosaic, TransitionDirection::Out, 8);
+        for _ in 0..2 {
+            mosaic.step(); // Coverage 0.25: 8x8 blocks at full brightness
+        }
+
+        let line = 11;
+        let mut row = source[line * 64..(line + 1) * 64].to_vec();
+        mosaic.apply_line(&mut row, line, 16, 16, &source);
+        assert_eq!(&row[5 * 4..5 * 4 + 2], [0, 8]);
+        assert_eq!(&row[13 * 4..13 * 4 + 2], [8, 8]);
+    }
+
+    #[test]
+    fn test_index_lines_leave_the_fade_to_the_palette() {
+        // 16x16 frame of indices, each its pixel's x plus one
+        let source: Vec<u8> = (0..16 * 16).map(|i| (i % 16 + 1) as u8).collect();
+        let mut mosaic = Transition::new(1, TransitionKind::Mosaic, TransitionDirection::Out, 4);
+        for _ in 0..3 {
+            mosaic.step(); // Coverage 0.75: 16x16 blocks at half brightness
+        }
+
+        let mut row = source[..16].to_vec();
+        mosaic.apply_index_line(&mut row, 3, 16, 16, &source, 0);
+        assert_eq!(row, [1; 16]);
+        let mut palette = vec![200u8; 4];
+        mosaic.apply_palette(&mut palette);
+        assert_eq!(palette, [100, 100, 100, 200]);
+    }
+
+    #[test]
+    fn test_iris_closes_on_its_center() {
+        let source = vec![200u8; 16 * 4];
+        let mut iris = Transition::new(1, TransitionKind::Iris, TransitionDirection::Out, 4).with_center(4.0, 0.0);
+        iris.step();
+        iris.step();
+
+        // Half closed: the radius is half the distance to the far corner (~6)
+        let mut row = source.clone();
+        iris.apply_line(&mut row, 0, 16, 1, &source);
+        assert_eq!(row[4 * 4], 200);
+        assert_eq!(row[9 * 4], 200);
+        assert_eq!(row[11 * 4], 0);
+        assert_eq!(row[15 * 4], 0);
+    }
+}
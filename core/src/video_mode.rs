@@ -0,0 +1,97 @@
+// Video modes selectable through the PPU's mode register. Each mode sets the
+// framebuffer resolution, and the scanline timing the PPU steps through is
+// derived from it: every line is its visible pixels followed by HBlank, and
+// every field is its visible lines followed by VBlank.
+
+// Resolution of the default mode (320x240), shared by both PPUs
+pub const SCREEN_WIDTH: usize = 320;
+pub const SCREEN_HEIGHT: usize = 240;
+
+// Lines of vertical blanking after the last visible line of each field
+pub const VBLANK_LINES: u16 = 22;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VideoMode {
+    Low,              // 256x224 - fewer pixels per line, more sprite bandwidth
+    Standard,         // 320x240 - default
+    HiResInterlaced,  // 320x480 - two alternating 240-line fields, for text-heavy screens
+}
+
+impl VideoMode {
+    pub fn from_u32(mode: u32) -> Option<VideoMode> {
+        match mode {
+            0 => Some(VideoMode::Low),
+            1 => Some(VideoMode::Standard),
+            2 => Some(VideoMode::HiResInterlaced),
+            _ => None,
+        }
+    }
+
+    pub fn to_u32(self) -> u32 {
+        match self {
+            VideoMode::Low => 0,
+            VideoMode::Standard => 1,
+            VideoMode::HiResInterlaced => 2,
+        }
+    }
+
+    pub fn width(self) -> usize {
+        match self {
+            VideoMode::Low => 256,
+            VideoMode::Standard | VideoMode::HiResInterlaced => 320,
+        }
+    }
+
+    pub fn height(self) -> usize {
+        match self {
+            VideoMode::Low => 224,
+            VideoMode::Standard => 240,
+            VideoMode::HiResInterlaced => 480,
+        }
+    }
+
+    pub fn is_interlaced(self) -> bool {
+        self == VideoMode::HiResInterlaced
+    }
+
+    // Scanlines drawn per field - interlaced fields each carry every other line
+    pub fn visible_lines(self) -> u16 {
+        if self.is_interlaced() {
+            self.height() as u16 / 2
+        } else {
+            self.height() as u16
+        }
+    }
+
+    // Horizontal blanking is a quarter of the visible line width
+    pub fn hblank_dots(self) -> u16 {
+        self.width() as u16 / 4
+    }
+
+    // PPU cycles (dots) per scanline: one per visible pixel, then HBlank
+    pub fn dots_per_line(self) -> u16 {
+        self.width() as u16 + self.hblank_dots()
+    }
+
+    // Total scanlines per field including vertical blanking. Interlaced
+    // fields alternate between 262 and 263 lines (262.5 on average).
+    pub fn lines_per_field(self, odd_field: bool) -> u16 {
+        let extra = if self.is_interlaced() && odd_field { 1 } else { 0 };
+        self.visible_lines() + VBLANK_LINES + extra
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing_follows_resolution() {
+        assert_eq!(VideoMode::Standard.dots_per_line(), 400);
+        assert_eq!(VideoMode::Standard.lines_per_field(false), 262);
+        assert_eq!(VideoMode::Low.dots_per_line(), 320);
+        assert_eq!(VideoMode::Low.lines_per_field(false), 246);
+        assert_eq!(VideoMode::HiResInterlaced.visible_lines(), 240);
+        assert_eq!(VideoMode::HiResInterlaced.lines_per_field(true), 263);
+    }
+}
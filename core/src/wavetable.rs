@@ -0,0 +1,129 @@
+// Wavetable voices - 8 voices that each loop a single-cycle waveform from wave
+// RAM. Wave RAM holds 16 tables of 32 or 64 signed 8-bit samples written by
+// the cartridge, so any timbre can be drawn; each voice plays one table at
+// its own frequency, volume and pan. Samples are read without interpolation,
+// which keeps the slightly gritty character of hardware wavetable chips.
+
+use crate::mixer;
+
+pub const WAVETABLE_VOICES: usize = 8;
+pub const WAVETABLE_COUNT: usize = 16;
+
+pub struct WaveRam {
+    tables: Vec<Vec<i8>>, // Empty until written; a voice on an empty table is silent
+}
+
+impl WaveRam {
+    pub fn new() -> WaveRam {
+        WaveRam {
+            tables: vec![Vec::new(); WAVETABLE_COUNT],
+        }
+    }
+
+    // Tables are 32 or 64 samples long. Returns false for other lengths or
+    // an index past the last table.
+    pub fn write_table(&mut self, table: usize, samples: &[i8]) -> bool {
+        if table >= WAVETABLE_COUNT || !matches!(samples.len(), 32 | 64) {
+            return false;
+        }
+        self.tables[table] = samples.to_vec();
+        true
+    }
+
+    pub fn table(&self, table: usize) -> &[i8] {
+        self.tables.get(table).map_or(&[], |samples| samples.as_slice())
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct WavetableVoice {
+    pub enabled: bool,
+    pub table: usize,
+    pub frequency: f32, // Waveform cycles per second
+    pub volume: f32,    // 0.0 to 1.0
+    pub pan: f32,       // -1.0 (left) to 1.0 (right)
+    phase: f32,
+}
+
+impl WavetableVoice {
+    const SILENT: WavetableVoice = WavetableVoice {
+        enabled: false,
+        table: 0,
+        frequency: 440.0,
+        volume: 0.0,
+        pan: 0.0,
+        phase: 0.0,
+    };
+
+    // Start playing from the beginning of the waveform
+    pub fn start(&mut self, table: usize, frequency: f32, volume: f32, pan: f32) {
+        self.enabled = true;
+        self.table = table;
+        self.frequency = frequency;
+        self.volume = volume.clamp(0.0, 1.0);
+        self.pan = pan.clamp(-1.0, 1.0);
+        self.phase = 0.0;
+    }
+}
+
+pub struct WavetableBank {
+    pub wave_ram: WaveRam,
+    voices: [WavetableVoice; WAVETABLE_VOICES],
+}
+
+impl WavetableBank {
+    pub fn new() -> WavetableBank {
+        WavetableBank {
+            wave_ram: WaveRam::new(),
+            voices: [WavetableVoice::SILENT; WAVETABLE_VOICES],
+        }
+    }
+
+    pub fn voice_mut(&mut self, voice: usize) -> Option<&mut WavetableVoice> {
+        self.voices.get_mut(voice)
+    }
+
+    pub fn stop_all(&mut self) {
+        self.voices.iter_mut().for_each(|voice| voice.enabled = false);
+    }
+
+    // Next (left, right) sample of all voices, placed with the pan law in
+    // mixer.rs
+    pub fn sample(&mut self, sample_rate: f32) -> (f32, f32) {
+        let (mut left, mut right) = (0.0, 0.0);
+        for voice in self.voices.iter_mut().filter(|voice| voice.enabled) {
+            let table = self.wave_ram.table(voice.table);
+            if table.is_empty() {
+                continue;
+            }
+
+            let index = (voice.phase * table.len() as f32) as usize % table.len();
+            let level = table[index] as f32 / 128.0 * voice.volume;
+            voice.phase = (voice.phase + voice.frequency / sample_rate).fract();
+
+            let (level_left, level_right) = mixer::pan(level, voice.pan);
+            left += level_left;
+            right += level_right;
+        }
+        (left, right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_voice_reads_table_and_pans() {
+        let mut bank = WavetableBank::new();
+        let square: Vec<i8> = (0..32).map(|i| if i < 16 { 64 } else { -64 }).collect();
+        assert!(bank.wave_ram.write_table(3, &square));
+        assert!(!bank.wave_ram.write_table(3, &square[..20]));
+
+        // One waveform cycle every 4 samples, panned hard right
+        bank.voice_mut(0).unwrap().start(3, 11025.0, 1.0, 1.0);
+        let samples: Vec<(f32, f32)> = (0..4).map(|_| bank.sample(44100.0)).collect();
+        assert_eq!(samples[0], (0.0, 0.5));
+        assert_eq!(samples[2], (0.0, -0.5));
+    }
+}
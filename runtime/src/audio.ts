@@ -34,8 +34,8 @@ export class AudioManager {
       this.gainNode.connect(this.audioContext.destination);
 
       // Create script processor for real-time audio generation
-      // Using 1024 buffer size for lower latency (~23ms at 44.1kHz)
-      this.scriptProcessor = this.audioContext.createScriptProcessor(1024, 0, 1);
+      // Using 1024 buffer size for lower latency (~23ms at 44.1kHz), stereo output
+      this.scriptProcessor = this.audioContext.createScriptProcessor(1024, 0, 2);
       this.scriptProcessor.connect(this.gainNode);
 
       this.isInitialized = true;
@@ -72,18 +72,38 @@ export class AudioManager {
     }
 
     let debugSampleCount = 0;
+    const stereo = typeof system.generate_audio_frames === 'function';
 
     // Set up the audio processing callback
     this.scriptProcessor.onaudioprocess = (event) => {
       const outputBuffer = event.outputBuffer;
-      const outputData = outputBuffer.getChannelData(0); // Mono output
+      const leftData = outputBuffer.getChannelData(0);
+      const rightData = outputBuffer.getChannelData(1);
 
-      // Fill buffer with samples from the APU
+      if (stereo) {
+        // Interleaved left/right pairs for the whole buffer in one call
+        try {
+          const frames = system.generate_audio_frames(outputBuffer.length);
+          for (let i = 0; i < outputBuffer.length; i++) {
+            leftData[i] = frames[i * 2];
+            rightData[i] = frames[i * 2 + 1];
+          }
+        } catch (error) {
+          console.error('Error generating samples:', error);
+          // If APU fails, output silence to prevent audio artifacts
+          leftData.fill(0);
+          rightData.fill(0);
+        }
+        return;
+      }
+
+      // Mono systems: the same sample on both sides
       for (let i = 0; i < outputBuffer.length; i++) {
         try {
           // Get sample from the Rust APU
           const sample = system.generate_audio_sample();
-          outputData[i] = sample;
+          leftData[i] = sample;
+          rightData[i] = sample;
 
           // Debug: Log first few samples to check if we're getting data
           if (debugSampleCount < 10) {
@@ -93,7 +113,8 @@ export class AudioManager {
         } catch (error) {
           console.error('Error generating sample:', error);
           // If APU fails, output silence to prevent audio artifacts
-          outputData[i] = 0;
+          leftData[i] = 0;
+          rightData[i] = 0;
         }
       }
     };
@@ -112,12 +133,14 @@ export class AudioManager {
 
     this.scriptProcessor!.onaudioprocess = (event) => {
       const outputBuffer = event.outputBuffer;
-      const outputData = outputBuffer.getChannelData(0);
+      const leftData = outputBuffer.getChannelData(0);
+      const rightData = outputBuffer.getChannelData(1);
       const sampleRate = this.audioContext!.sampleRate;
 
       for (let i = 0; i < outputBuffer.length; i++) {
         // Generate simple sine wave
-        outputData[i] = Math.sin(phase) * 0.1; // Quiet volume
+        leftData[i] = Math.sin(phase) * 0.1; // Quiet volume
+        rightData[i] = leftData[i];
         phase += (2 * Math.PI * frequency) / sampleRate;
 
         // Keep phase in reasonable bounds
@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use zebratron_core::{EFFECT_SET_SPEED, EFFECT_SET_VOLUME, NOTE_OFF};
+
+// Standard MIDI File (type 0 or 1) to .zsm tracker music converter. Each
+// MIDI track/channel pair becomes one or more music channels (as many as its
+// chords need), notes are quantized to rows of the frame-based tick rate, and
+// General MIDI programs become instruments. The .zsm layout is documented in
+// core/src/music.rs.
+
+const MAX_CHANNELS: usize = 8;
+const ROWS_PER_PATTERN: usize = 64;
+const TICKS_PER_BEAT: usize = 24; // .zsm ticks per beat; BPM then equals the .zsm tempo
+const DRUM_CHANNEL: u8 = 9; // MIDI channel 10
+
+enum EventKind {
+    NoteOn { channel: u8, note: u8, velocity: u8 },
+    NoteOff { channel: u8, note: u8 },
+    Program { channel: u8, program: u8 },
+    Tempo(u32), // Microseconds per quarter note
+}
+
+struct Event {
+    tick: u32,
+    track: usize,
+    kind: EventKind,
+}
+
+struct MidiFile {
+    division: u32, // Ticks per quarter note
+    events: Vec<Event>,
+    track_count: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Note {
+    source: (usize, u8), // Track and MIDI channel
+    start: usize,        // Row
+    end: usize,
+    pitch: u8,
+    velocity: u8,
+    instrument: u8,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Cell {
+    note: u8,
+    instrument: u8,
+    effect: u8,
+    param: u8,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 && args.len() != 4 {
+        eprintln!("Usage: {} <input.mid> <output.zsm> [rows_per_beat]", args[0]);
+        eprintln!("Example: {} level1.mid level1.zsm 4", args[0]);
+        eprintln!("rows_per_beat (default 4) must divide {}", TICKS_PER_BEAT);
+        std::process::exit(1);
+    }
+
+    let rows_per_beat = match args.get(3).map(|value| value.parse::<usize>()) {
+        None => 4,
+        Some(Ok(rows)) if rows > 0 && TICKS_PER_BEAT.is_multiple_of(rows) => rows,
+        Some(_) => {
+            eprintln!("rows_per_beat must be one of 1, 2, 3, 4, 6, 8, 12, 24");
+            std::process::exit(1);
+        }
+    };
+
+    let data = match fs::read(&args[1]) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Error reading MIDI file: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let zsm = parse_midi(&data).and_then(|midi| convert(&midi, rows_per_beat));
+    match zsm {
+        Ok(zsm) => {
+            fs::write(&args[2], &zsm).expect("Failed to write output file");
+            println!("Generated {} ({} bytes)", args[2], zsm.len());
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_midi(data: &[u8]) -> Result<MidiFile, String> {
+    if data.len() < 14 || &data[0..4] != b"MThd" {
+        return Err(String::from("Not a Standard MIDI File"));
+    }
+    let format = u16::from_be_bytes([data[8], data[9]]);
+    let track_count = u16::from_be_bytes([data[10], data[11]]) as usize;
+    let division = u16::from_be_bytes([data[12], data[13]]);
+    if format > 1 {
+        return Err(format!("MIDI format {} is not supported (only 0 and 1)", format));
+    }
+    if division & 0x8000 != 0 {
+        return Err(String::from("SMPTE time division is not supported"));
+    }
+
+    let mut events = Vec::new();
+    let mut pos = 8 + u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    for track in 0..track_count {
+        if pos + 8 > data.len() || &data[pos..pos + 4] != b"MTrk" {
+            return Err(format!("Track {} is missing or damaged", track));
+        }
+        let length = u32::from_be_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]) as usize;
+        let end = (pos + 8 + length).min(data.len());
+        parse_track(&data[pos + 8..end], track, &mut events)?;
+        pos = end;
+    }
+
+    // Stable sort keeps each track's own order for events on the same tick
+    events.sort_by_key(|event| event.tick);
+    Ok(MidiFile { division: division.max(1) as u32, events, track_count })
+}
+
+fn parse_track(data: &[u8], track: usize, events: &mut Vec<Event>) -> Result<(), String> {
+    let mut pos = 0;
+    let mut tick = 0;
+    let mut running_status = 0;
+
+    while pos < data.len() {
+        tick += read_variable_length(data, &mut pos)?;
+        let mut status = *data.get(pos).ok_or("Track ends mid-event")?;
+        if status < 0x80 {
+            status = running_status; // Data byte: repeat the last status
+        } else {
+            pos += 1;
+        }
+
+        match status {
+            0xFF => {
+                let meta_type = *data.get(pos).ok_or("Track ends mid-event")?;
+                pos += 1;
+                let length = read_variable_length(data, &mut pos)? as usize;
+                let meta = data.get(pos..pos + length).ok_or("Track ends mid-event")?;
+                if meta_type == 0x51 && length == 3 {
+                    let tempo = u32::from_be_bytes([0, meta[0], meta[1], meta[2]]);
+                    events.push(Event { tick, track, kind: EventKind::Tempo(tempo) });
+                }
+                pos += length;
+            }
+            0xF0 | 0xF7 => {
+                // SysEx, skipped
+                let length = read_variable_length(data, &mut pos)? as usize;
+                pos += length;
+            }
+            0x80..=0xEF => {
+                running_status = status;
+                let channel = status & 0x0F;
+                let data_length = if matches!(status & 0xF0, 0xC0 | 0xD0) { 1 } else { 2 };
+                let bytes = data.get(pos..pos + data_length).ok_or("Track ends mid-event")?;
+                pos += data_length;
+
+                let kind = match (status & 0xF0, bytes) {
+                    (0x90, &[note, velocity]) if velocity > 0 => EventKind::NoteOn { channel, note, velocity },
+                    (0x80, &[note, _]) | (0x90, &[note, _]) => EventKind::NoteOff { channel, note },
+                    (0xC0, &[program]) => EventKind::Program { channel, program },
+                    _ => continue, // Controllers, pitch bend and aftertouch
+                };
+                events.push(Event { tick, track, kind });
+            }
+            _ => return Err(format!("Unexpected status byte {:02X} in track {}", status, track)),
+        }
+    }
+    Ok(())
+}
+
+fn read_variable_length(data: &[u8], pos: &mut usize) -> Result<u32, String> {
+    let mut value = 0u32;
+    for _ in 0..4 {
+        let byte = *data.get(*pos).ok_or("Track ends mid-event")?;
+        *pos += 1;
+        value = (value << 7) | (byte & 0x7F) as u32;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(String::from("Variable-length value is too long"))
+}
+
+fn convert(midi: &MidiFile, rows_per_beat: usize) -> Result<Vec<u8>, String> {
+    let to_row = |tick: u32| ((tick as u64 * rows_per_beat as u64 * 2 + midi.division as u64) / (midi.division as u64 * 2)) as usize;
+
+    // Pair note-ons with note-offs, tracking programs and tempo changes
+    let mut instruments: Vec<[u8; 8]> = Vec::new();
+    let mut instrument_ids: HashMap<(u8, bool), u8> = HashMap::new();
+    let mut programs = [0u8; 16];
+    let mut active: HashMap<(usize, u8, u8), (u32, u8, u8)> = HashMap::new();
+    let mut notes = Vec::new();
+    let mut tempos: Vec<(usize, u8)> = Vec::new();
+
+    for event in &midi.events {
+        match event.kind {
+            EventKind::Tempo(microseconds) => {
+                let bpm = (60_000_000.0 / microseconds.max(1) as f32).round();
+                if !(32.0..=255.0).contains(&bpm) {
+                    eprintln!("Warning: tempo {} BPM at row {} clamped to 32-255", bpm, to_row(event.tick));
+                }
+                tempos.push((to_row(event.tick), bpm.clamp(32.0, 255.0) as u8));
+            }
+            EventKind::Program { channel, program } => programs[channel as usize] = program,
+            EventKind::NoteOn { channel, note, velocity } => {
+                let key = (programs[channel as usize], channel == DRUM_CHANNEL);
+                let instrument = match instrument_ids.get(&key) {
+                    Some(&id) => id,
+                    None if instruments.len() < 255 => {
+                        instruments.push(instrument_for(key.0, key.1));
+                        instrument_ids.insert(key, instruments.len() as u8);
+                        instruments.len() as u8
+                    }
+                    None => 1,
+                };
+                let key = (event.track, channel, note);
+                if let Some((start, velocity, instrument)) = active.remove(&key) {
+                    notes.push(make_note(key, start, event.tick, velocity, instrument, &to_row));
+                }
+                active.insert(key, (event.tick, velocity, instrument));
+            }
+            EventKind::NoteOff { channel, note } => {
+                let key = (event.track, channel, note);
+                if let Some((start, velocity, instrument)) = active.remove(&key) {
+                    notes.push(make_note(key, start, event.tick, velocity, instrument, &to_row));
+                }
+            }
+        }
+    }
+    let last_tick = midi.events.last().map_or(0, |event| event.tick);
+    for (key, (start, velocity, instrument)) in active {
+        notes.push(make_note(key, start, last_tick, velocity, instrument, &to_row));
+    }
+    if notes.is_empty() {
+        return Err(String::from("The MIDI file has no notes"));
+    }
+
+    let channels = assign_channels(&mut notes);
+    let total_rows = notes.iter().map(|note| note.end + 1).max().unwrap_or(1);
+    let mut grid = vec![vec![Cell::default(); total_rows]; channels.len()];
+    for (channel, channel_notes) in channels.iter().enumerate() {
+        for note in channel_notes {
+            // Velocity scales the instrument's own volume; full velocity
+            // needs no volume effect
+            let instrument_volume = instruments[note.instrument as usize - 1][1];
+            let volume = (instrument_volume as u16 * note.velocity as u16 / 127) as u8;
+            let (effect, param) = if volume == instrument_volume { (0, 0) } else { (EFFECT_SET_VOLUME, volume) };
+            grid[channel][note.start] = Cell { note: note.pitch, instrument: note.instrument, effect, param };
+            if grid[channel][note.end].note == 0 {
+                grid[channel][note.end].note = NOTE_OFF;
+            }
+        }
+    }
+
+    // Initial tempo goes in the header, later changes in the first free
+    // effect slot of their row, or an extra effect-only channel
+    let initial_tempo = tempos.iter().take_while(|(row, _)| *row == 0).last().map_or(120, |&(_, bpm)| bpm);
+    for &(row, bpm) in tempos.iter().filter(|(row, _)| *row > 0 && *row < total_rows) {
+        let free = grid.iter().position(|channel| channel[row].effect == 0 && channel[row].param == 0);
+        let channel = match free {
+            Some(channel) => channel,
+            None if grid.len() < MAX_CHANNELS => {
+                grid.push(vec![Cell::default(); total_rows]);
+                grid.len() - 1
+            }
+            None => {
+                eprintln!("Warning: no free effect slot for the tempo change to {} BPM at row {}, dropped", bpm, row);
+                continue;
+            }
+        };
+        grid[channel][row].effect = EFFECT_SET_SPEED;
+        grid[channel][row].param = bpm;
+    }
+
+    let speed = (TICKS_PER_BEAT / rows_per_beat) as u8;
+    let zsm = build_zsm(&grid, &instruments, speed, initial_tempo)?;
+    println!(
+        "{} MIDI tracks -> {} channels, {} instruments, {} rows at {} BPM",
+        midi.track_count, grid.len(), instruments.len(), total_rows, initial_tempo
+    );
+    Ok(zsm)
+}
+
+fn make_note(key: (usize, u8, u8), start: u32, end: u32, velocity: u8, instrument: u8, to_row: &dyn Fn(u32) -> usize) -> Note {
+    let start = to_row(start);
+    Note {
+        source: (key.0, key.1),
+        start,
+        end: to_row(end).max(start + 1), // At least one row long
+        pitch: key.2.max(1),
+        velocity,
+        instrument,
+    }
+}
+
+// Give each track/channel pair as many music channels as its chords need.
+// Notes that don't fit in the channel limit are dropped with a warning.
+fn assign_channels(notes: &mut [Note]) -> Vec<Vec<Note>> {
+    notes.sort_by_key(|note| (note.source, note.start, std::cmp::Reverse(note.pitch)));
+
+    let mut channels: Vec<Vec<Note>> = Vec::new();
+    let mut owners: Vec<(usize, u8)> = Vec::new();
+    let mut dropped: HashMap<(usize, u8), (usize, usize)> = HashMap::new(); // Count, first row
+
+    for note in notes.iter() {
+        let free = (0..channels.len()).find(|&channel| {
+            owners[channel] == note.source && channels[channel].last().is_none_or(|last| last.end <= note.start)
+        });
+        match free {
+            Some(channel) => channels[channel].push(*note),
+            None if channels.len() < MAX_CHANNELS => {
+                channels.push(vec![*note]);
+                owners.push(note.source);
+            }
+            None => {
+                let entry = dropped.entry(note.source).or_insert((0, note.start));
+                entry.0 += 1;
+            }
+        }
+    }
+
+    let mut dropped: Vec<_> = dropped.into_iter().collect();
+    dropped.sort();
+    for ((track, channel), (count, row)) in dropped {
+        eprintln!(
+            "Warning: track {} MIDI channel {}: polyphony exceeds the {} music channels, {} notes dropped (first at row {})",
+            track, channel + 1, MAX_CHANNELS, count, row
+        );
+    }
+    channels
+}
+
+fn build_zsm(grid: &[Vec<Cell>], instruments: &[[u8; 8]], speed: u8, tempo: u8) -> Result<Vec<u8>, String> {
+    let orders = grid[0].len().div_ceil(ROWS_PER_PATTERN);
+    if orders > 255 {
+        return Err(format!("Song needs {} orders, the limit is 255", orders));
+    }
+
+    // Split each channel into patterns, sharing identical ones
+    let mut patterns: Vec<Vec<u8>> = Vec::new();
+    let mut pattern_ids: HashMap<Vec<u8>, u8> = HashMap::new();
+    let mut order_list = Vec::new();
+    for order in 0..orders {
+        for channel in grid {
+            let mut pattern = Vec::with_capacity(ROWS_PER_PATTERN * 4);
+            for row in order * ROWS_PER_PATTERN..(order + 1) * ROWS_PER_PATTERN {
+                let cell = channel.get(row).copied().unwrap_or_default();
+                pattern.extend_from_slice(&[cell.note, cell.instrument, cell.effect, cell.param]);
+            }
+            let id = match pattern_ids.get(&pattern) {
+                Some(&id) => id,
+                None => {
+                    if patterns.len() == 255 {
+                        return Err(String::from("Song needs more than 255 patterns"));
+                    }
+                    pattern_ids.insert(pattern.clone(), patterns.len() as u8);
+                    patterns.push(pattern);
+                    (patterns.len() - 1) as u8
+                }
+            };
+            order_list.push(id);
+        }
+    }
+
+    let mut zsm = vec![
+        b'Z', b'S', b'M', 1,
+        grid.len() as u8,
+        ROWS_PER_PATTERN as u8,
+        speed,
+        tempo,
+        instruments.len() as u8,
+        patterns.len() as u8,
+        orders as u8,
+        0, // Loop to the start
+    ];
+    for instrument in instruments {
+        zsm.extend_from_slice(instrument);
+    }
+    zsm.extend_from_slice(&order_list);
+    for pattern in &patterns {
+        zsm.extend_from_slice(pattern);
+    }
+    Ok(zsm)
+}
+
+// Instrument for a General MIDI program, chosen by its family: waveform,
+// volume, attack, decay, sustain, release, pulse width, reserved
+fn instrument_for(program: u8, drums: bool) -> [u8; 8] {
+    if drums {
+        return [4, 48, 0, 4, 0, 2, 128, 0]; // Noise hit
+    }
+    match program / 8 {
+        0 => [0, 48, 0, 20, 24, 10, 128, 0],     // Piano: decaying square
+        1 => [2, 52, 0, 12, 0, 8, 128, 0],      // Chromatic percussion: triangle bell
+        2 => [0, 44, 2, 0, 64, 4, 96, 0],       // Organ: sustained pulse
+        3 => [1, 44, 0, 16, 16, 8, 128, 0],     // Guitar: plucked saw
+        4 => [2, 60, 0, 8, 48, 4, 128, 0],      // Bass: triangle
+        5 | 6 => [1, 40, 12, 0, 64, 20, 128, 0], // Strings and ensembles: swelling saw
+        7 => [0, 44, 3, 8, 48, 8, 64, 0],       // Brass: 25% pulse
+        8 => [0, 40, 2, 6, 48, 6, 32, 0],       // Reed: 12.5% pulse
+        9 => [3, 48, 4, 0, 64, 10, 128, 0],     // Pipe: sine
+        10 => [1, 44, 0, 10, 48, 6, 128, 0],    // Synth lead: saw
+        11 => [2, 40, 20, 0, 64, 30, 128, 0],   // Synth pad: slow triangle
+        _ => [0, 44, 0, 10, 32, 6, 128, 0],     // Everything else: plain square
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(tick: u32, kind: EventKind) -> Event {
+        Event { tick, track: 0, kind }
+    }
+
+    // (note, instrument, effect, param) at a row of the first order
+    fn cell(zsm: &[u8], channel: usize, row: usize) -> (u8, u8, u8, u8) {
+        let order_list = 12 + zsm[8] as usize * 8;
+        let patterns = order_list + zsm[10] as usize * zsm[4] as usize;
+        let at = patterns + (zsm[order_list + channel] as usize * ROWS_PER_PATTERN + row) * 4;
+        (zsm[at], zsm[at + 1], zsm[at + 2], zsm[at + 3])
+    }
+
+    #[test]
+    fn test_convert() {
+        // Quarter notes are 24 MIDI ticks, so 6 ticks per row
+        let midi = MidiFile {
+            division: 24,
+            track_count: 1,
+            events: vec![
+                event(0, EventKind::Tempo(500_000)),
+                event(0, EventKind::NoteOn { channel: 0, note: 72, velocity: 127 }),
+                event(24, EventKind::NoteOff { channel: 0, note: 72 }),
+                event(24, EventKind::Tempo(400_000)),
+                event(48, EventKind::NoteOn { channel: 0, note: 74, velocity: 80 }),
+                event(48, EventKind::Tempo(600_000)),
+                event(72, EventKind::NoteOff { channel: 0, note: 74 }),
+            ],
+        };
+        let zsm = convert(&midi, 4).unwrap();
+        assert_eq!(zsm[7], 120); // Initial tempo in the header
+
+        // Full velocity plays at the instrument's volume, lower scales it
+        assert_eq!(cell(&zsm, 0, 0), (72, 1, 0, 0));
+        assert_eq!(cell(&zsm, 0, 8), (74, 1, EFFECT_SET_VOLUME, 30));
+
+        // Note-offs land on the row the note ends
+        assert_eq!(cell(&zsm, 0, 3), (0, 0, 0, 0));
+        assert_eq!(cell(&zsm, 0, 12), (NOTE_OFF, 0, 0, 0));
+
+        // Tempo changes take a free effect slot, or a channel of their own
+        assert_eq!(cell(&zsm, 0, 4), (NOTE_OFF, 0, EFFECT_SET_SPEED, 150));
+        assert_eq!(zsm[4], 2);
+        assert_eq!(cell(&zsm, 1, 8), (0, 0, EFFECT_SET_SPEED, 100));
+    }
+}